@@ -0,0 +1,97 @@
+//! Derive macros for [`clap-adapters`](https://docs.rs/clap-adapters)
+//!
+//! These are re-exported by `clap-adapters` when its `derive` feature is
+//! enabled; see the documentation there for examples.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, DeriveInput, LitStr};
+
+/// Generate a `<Name>Args` struct with `--config`, `--set` and `--profile` flags
+#[proc_macro_derive(ConfigArgs, attributes(config_args))]
+pub fn derive_config_args(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "ConfigArgs does not support generic types",
+        ));
+    }
+
+    let format = parse_format(&input)?;
+    let vis = &input.vis;
+    let name = &input.ident;
+    let args = format_ident!("{}Args", name);
+    let adapter = match format.as_str() {
+        "json" => quote!(::clap_adapters::JsonOf<#name>),
+        "toml" => quote!(::clap_adapters::TomlOf<#name>),
+        "yaml" => quote!(::clap_adapters::YamlOf<#name>),
+        _ => unreachable!("checked by parse_format"),
+    };
+    let doc = format!("Command-line arguments for loading a [`{name}`]");
+    let resolve_doc = format!(
+        "Select `--profile` from `--config`, apply each `--set`, and convert to a [`{name}`]"
+    );
+
+    Ok(quote! {
+        #[doc = #doc]
+        // Clap recognizes `Vec` and `Option` fields by name, so these are unqualified
+        #[derive(Debug, Clone, ::clap::Args)]
+        #vis struct #args {
+            /// Path to the config file
+            #[arg(long, value_name = "PATH", value_hint = ::clap::ValueHint::FilePath)]
+            pub config: ::clap_adapters::ProfileOf<#adapter>,
+
+            /// Override a config value, e.g. `--set server.port=8080`
+            #[arg(long = "set", value_name = "KEY=VALUE")]
+            pub set: Vec<::clap_adapters::SetOverride>,
+
+            /// The profile to select from the config
+            #[arg(long, value_name = "NAME")]
+            pub profile: Option<String>,
+        }
+
+        impl #args {
+            #[doc = #resolve_doc]
+            pub fn resolve(&self) -> ::std::result::Result<#name, ::clap_adapters::Error> {
+                self.config
+                    .select_with_overrides(self.profile.as_deref(), &self.set)
+                    .map(|adapter| adapter.0)
+            }
+        }
+    })
+}
+
+/// Read the format from `#[config_args(format = "...")]`, defaulting to Json
+fn parse_format(input: &DeriveInput) -> syn::Result<String> {
+    let mut format = LitStr::new("json", Span::call_site());
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("config_args"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("format") {
+                format = meta.value()?.parse()?;
+                Ok(())
+            } else {
+                Err(meta.error("expected `format`"))
+            }
+        })?;
+    }
+
+    match format.value().as_str() {
+        "json" | "toml" | "yaml" => Ok(format.value()),
+        _ => Err(syn::Error::new(
+            format.span(),
+            "expected one of \"json\", \"toml\" or \"yaml\"",
+        )),
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::Path;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use serde::de::DeserializeOwned;
+
+use crate::prelude::FromPath;
+use crate::traits::HashingReader;
+use crate::{AdapterOptions, Error};
+
+/// An adapter for deserializing a document in any registered format
+///
+/// The format is chosen by the file's extension, using the parsers in the
+/// [`FormatRegistry`]. Json (`.json`), Toml (`.toml`) and Yaml (`.yaml` and
+/// `.yml`) are registered by default, and applications may register parsers
+/// for their own extensions at startup. Under [`WithOptions`], a `format`
+/// option such as `config?format=yaml` overrides the extension.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Json, Toml or Yaml config
+///     #[clap(long)]
+///     config: PathTo<AnyOf<serde_json::Value>>,
+/// }
+///
+/// // Create config files in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let json_path = config_dir.path().join("config.json");
+/// let toml_path = config_dir.path().join("config.toml");
+/// std::fs::write(&json_path, r#"{"hello":"world"}"#)?;
+/// std::fs::write(&toml_path, r#"hello = "world""#)?;
+///
+/// for config_path in [json_path, toml_path] {
+///     let config_path_string = config_path.display().to_string();
+///     let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+///     assert_eq!(cli.config.data.0, serde_json::json!({"hello":"world"}));
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`WithOptions`]: crate::WithOptions
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct AnyOf<T>(pub T);
+
+impl<T> std::ops::Deref for AnyOf<T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> std::ops::DerefMut for AnyOf<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T> AsRef<T> for AnyOf<T> {
+    fn as_ref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> AnyOf<T> {
+    /// Returns a reference to the inner datatype
+    pub fn data(&self) -> &T {
+        &self.0
+    }
+
+    /// Returns a mutable reference to the inner datatype
+    pub fn data_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+
+    /// Returns the inner datatype
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: DeserializeOwned> FromPath for AnyOf<T> {
+    fn from_path(path: &Path) -> Result<Self, Error> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default();
+        Self::load_as(path, extension)
+    }
+
+    /// Takes `format`, the extension of the format to parse as
+    fn from_path_with_options(path: &Path, options: &mut AdapterOptions) -> Result<Self, Error> {
+        match options.take("format") {
+            Some(format) => Self::load_as(path, &format),
+            None => Self::from_path(path),
+        }
+    }
+
+    fn takes_option(key: &str) -> bool {
+        key == "format"
+    }
+
+    fn from_path_hashed(
+        path: &Path,
+        options: &mut AdapterOptions,
+    ) -> Result<(Self, Option<u64>), Error> {
+        let format = match options.take("format") {
+            Some(format) => format,
+            None => path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .unwrap_or_default()
+                .to_string(),
+        };
+        let (data, hash) = Self::load_hashed(path, &format)?;
+        Ok((data, Some(hash)))
+    }
+}
+
+impl<T: DeserializeOwned> AnyOf<T> {
+    /// Parse the file at `path` with the parser registered for `extension`
+    fn load_as(path: &Path, extension: &str) -> Result<Self, Error> {
+        Self::load_hashed(path, extension).map(|(data, _)| data)
+    }
+
+    /// Like [`AnyOf::load_as`], also returning the hash of the parsed bytes
+    fn load_hashed(path: &Path, extension: &str) -> Result<(Self, u64), Error> {
+        let Some(parser) = FormatRegistry::get(extension) else {
+            return Err(Error::UnknownFormat {
+                path: path.to_path_buf(),
+            });
+        };
+
+        let file = std::fs::File::open(path).map_err(|error| Error::io(path, error))?;
+        let mut reader = HashingReader::new(std::io::BufReader::new(file));
+        let value = parser(&mut reader).map_err(|error| Error::parse_boxed(path.into(), error))?;
+        let hash = reader.finish().map_err(|error| Error::io(path, error))?;
+        let data = serde_json::from_value(value).map_err(|error| Error::Format {
+            path: path.to_path_buf(),
+            source: error.into(),
+        })?;
+        Ok((AnyOf(data), hash))
+    }
+}
+
+/// A parser registered for a file extension
+pub type FormatParser =
+    dyn Fn(&mut dyn BufRead) -> Result<serde_json::Value, BoxError> + Send + Sync;
+
+/// The error returned by a [`FormatParser`]
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The process-wide parsers by extension, initialized with the built-in formats
+static FORMATS: OnceLock<RwLock<HashMap<String, Arc<FormatParser>>>> = OnceLock::new();
+
+/// The process-wide registry of parsers used by [`AnyOf`]
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use std::io::BufRead;
+///
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a config in any registered format
+///     #[clap(long)]
+///     config: PathTo<AnyOf<serde_json::Value>>,
+/// }
+///
+/// // Parse `.conf` files of `key=value` lines
+/// FormatRegistry::register("conf", |reader| {
+///     let mut object = serde_json::Map::new();
+///     for line in reader.lines() {
+///         let line = line?;
+///         let Some((key, value)) = line.split_once('=') else {
+///             return Err(format!("expected key=value, got {line:?}").into());
+///         };
+///         object.insert(key.trim().into(), value.trim().into());
+///     }
+///     Ok(serde_json::Value::Object(object))
+/// });
+/// assert!(FormatRegistry::extensions().contains(&"conf".to_string()));
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("app.conf");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, "hello = world\n")?;
+///
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+/// assert_eq!(cli.config.data.0, serde_json::json!({"hello":"world"}));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct FormatRegistry {
+    _private: (),
+}
+
+impl FormatRegistry {
+    /// Register `parser` for files with the given extension, replacing any existing parser
+    ///
+    /// Extensions are matched case-insensitively, with or without a leading `.`.
+    pub fn register<F>(extension: &str, parser: F)
+    where
+        F: Fn(&mut dyn BufRead) -> Result<serde_json::Value, BoxError> + Send + Sync + 'static,
+    {
+        let extension = normalize(extension);
+        Self::formats()
+            .write()
+            .expect("format registry lock poisoned")
+            .insert(extension, Arc::new(parser));
+    }
+
+    /// Returns the registered extensions, in sorted order
+    pub fn extensions() -> Vec<String> {
+        let formats = Self::formats()
+            .read()
+            .expect("format registry lock poisoned");
+        let mut extensions: Vec<String> = formats.keys().cloned().collect();
+        extensions.sort();
+        extensions
+    }
+
+    /// Returns the parser registered for `extension`, if any
+    pub fn get(extension: &str) -> Option<Arc<FormatParser>> {
+        let formats = Self::formats()
+            .read()
+            .expect("format registry lock poisoned");
+        formats.get(&normalize(extension)).cloned()
+    }
+
+    /// Returns the registered formats, registering the built-in ones on first use
+    fn formats() -> &'static RwLock<HashMap<String, Arc<FormatParser>>> {
+        FORMATS.get_or_init(|| {
+            let mut formats: HashMap<String, Arc<FormatParser>> = HashMap::new();
+            formats.insert("json".into(), Arc::new(parse_json));
+            #[cfg(feature = "toml")]
+            formats.insert("toml".into(), Arc::new(parse_toml));
+            #[cfg(any(feature = "yaml", feature = "yaml-ng"))]
+            {
+                formats.insert("yaml".into(), Arc::new(parse_yaml));
+                formats.insert("yml".into(), Arc::new(parse_yaml));
+            }
+            RwLock::new(formats)
+        })
+    }
+}
+
+/// Lowercases `extension` and strips any leading `.`
+fn normalize(extension: &str) -> String {
+    extension.trim_start_matches('.').to_ascii_lowercase()
+}
+
+fn parse_json(reader: &mut dyn BufRead) -> Result<serde_json::Value, BoxError> {
+    Ok(serde_json::from_reader(reader)?)
+}
+
+#[cfg(feature = "toml")]
+fn parse_toml(reader: &mut dyn BufRead) -> Result<serde_json::Value, BoxError> {
+    let mut string = String::new();
+    reader.read_to_string(&mut string)?;
+    Ok(toml::from_str(&string)?)
+}
+
+#[cfg(any(feature = "yaml", feature = "yaml-ng"))]
+fn parse_yaml(reader: &mut dyn BufRead) -> Result<serde_json::Value, BoxError> {
+    Ok(crate::yaml::from_reader(reader)?)
+}
@@ -0,0 +1,121 @@
+use std::path::{Path, PathBuf};
+
+use crate::Error;
+
+/// Replace the file at `path` with the output of `write`, all at once
+///
+/// The output is written to a temporary file next to `path`, which is then
+/// renamed over `path`. Readers (including reloading adapters) thus see
+/// either the old or the new contents, never a partially written file. If
+/// writing fails, `path` is left untouched.
+///
+/// An existing file keeps its permissions, and if `path` is a symlink, the
+/// file it points to is replaced rather than the link itself.
+pub(crate) fn write<F>(path: &Path, write: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut std::io::BufWriter<std::fs::File>) -> Result<(), Error>,
+{
+    let path = &resolve_symlink(path)?;
+    let permissions = match std::fs::metadata(path) {
+        Ok(metadata) => Some(metadata.permissions()),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => None,
+        Err(error) => return Err(Error::io(path, error)),
+    };
+
+    let (temp_path, file) = create_temp(path)?;
+    let result = write_synced(&temp_path, file, write)
+        .and_then(|()| match permissions {
+            Some(permissions) => std::fs::set_permissions(&temp_path, permissions)
+                .map_err(|error| Error::io(&temp_path, error)),
+            None => Ok(()),
+        })
+        .and_then(|()| std::fs::rename(&temp_path, path).map_err(|error| Error::io(path, error)));
+    if result.is_err() {
+        // Only ever the file created above, never one another writer is using
+        let _ = std::fs::remove_file(&temp_path);
+        return result;
+    }
+    sync_parent(path)
+}
+
+/// Returns the file `path` points to if it's a symlink, or `path` itself otherwise
+fn resolve_symlink(path: &Path) -> Result<PathBuf, Error> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => {
+            std::fs::canonicalize(path).map_err(|error| Error::io(path, error))
+        }
+        _ => Ok(path.to_path_buf()),
+    }
+}
+
+/// Create a new, uniquely named temporary file next to `path`
+///
+/// The name is never one already in use, so concurrent writers (in this
+/// process or another) each get a file of their own.
+fn create_temp(path: &Path) -> Result<(PathBuf, std::fs::File), Error> {
+    loop {
+        let temp_path = temp_path(path);
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+        {
+            Ok(file) => return Ok((temp_path, file)),
+            Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(error) => return Err(Error::io(&temp_path, error)),
+        }
+    }
+}
+
+/// Write the output of `write` to `file` at `path`, and flush it to disk
+fn write_synced<F>(path: &Path, file: std::fs::File, write: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut std::io::BufWriter<std::fs::File>) -> Result<(), Error>,
+{
+    let mut writer = std::io::BufWriter::new(file);
+    write(&mut writer)?;
+    let file = writer
+        .into_inner()
+        .map_err(|error| Error::io(path, error.into_error()))?;
+    file.sync_all().map_err(|error| Error::io(path, error))
+}
+
+/// Flush the rename of `path` to disk by syncing its directory
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> Result<(), Error> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    std::fs::File::open(parent)
+        .and_then(|directory| directory.sync_all())
+        .map_err(|error| Error::io(parent, error))
+}
+
+/// Directories can't be opened as files on this platform, so the rename is
+/// flushed whenever the file system gets to it
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Returns a hidden path in the same directory as `path`, so it can be renamed over it
+///
+/// The name combines the process id, a per-process counter and random bits,
+/// so it is unique unless a stale file of a previous run happens to match.
+fn temp_path(path: &Path) -> PathBuf {
+    use std::hash::{BuildHasher, Hasher};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    // Each `RandomState` is randomly seeded
+    let random = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish();
+
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let pid = std::process::id();
+    let temp_name = format!(".{file_name}.{pid}.{count}.{random:016x}.tmp");
+    path.with_file_name(temp_name)
+}
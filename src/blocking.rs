@@ -0,0 +1,241 @@
+//! Provides a [`Reloading`] adapter that works without an async runtime
+//!
+//! The adapter in this module is powered by a plain background thread, and
+//! exposes only standard library types in its API, making it a good fit for
+//! fully synchronous applications that still want hot-reloading.
+
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex, RwLock};
+
+use crate::fs::PathTo;
+use crate::fs_watch::{changes_contents, FileWatcher};
+use crate::time::{Millis, Time};
+use crate::traits::FromPath;
+
+/// Given a [`Path`] from the user, provides a utility that reloads the file
+/// at the path whenever the file is updated, without requiring tokio
+///
+/// - Use [`Reloading::get`] to get the file contents at a given moment
+/// - Use [`Reloading::subscribe`] to get a [`mpsc::Receiver`] of updates
+/// - Use [`Reloading::reload_now`] to reload the file without a filesystem event
+///
+/// Filesystem events are handled just like the tokio-based
+/// [`Reloading`](crate::Reloading), including debouncing for the duration `D`
+/// and surviving renames and symlink swaps.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use std::time::Duration;
+///
+/// use clap::Parser;
+/// use clap_adapters::blocking::Reloading;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Json config that's reloaded
+///     #[clap(long)]
+///     config: Reloading<PathTo<JsonOf<serde_json::Value>>>,
+/// }
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+///
+/// // No async runtime is needed
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+/// let updates = cli.config.subscribe();
+///
+/// // Editing the file publishes the new document
+/// std::fs::write(&config_path, r#"{"hello":"thread"}"#)?;
+/// let config = updates.recv_timeout(Duration::from_secs(10))?;
+/// assert_eq!(config.data.0, serde_json::json!({"hello":"thread"}));
+/// assert_eq!(cli.config.get().data.0, serde_json::json!({"hello":"thread"}));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Path`]: std::path::Path
+#[derive(Clone)]
+#[must_use = "Dropping the `Reloading` will cancel the file watch"]
+pub struct Reloading<T, D: Time = Millis<100>> {
+    shared: Arc<Shared<T>>,
+    watcher: Arc<FileWatcher>,
+    debounce: std::marker::PhantomData<D>,
+}
+
+/// State shared between a [`Reloading`] and its watcher thread
+struct Shared<T> {
+    current: RwLock<Arc<T>>,
+    subscribers: Mutex<Vec<mpsc::Sender<Arc<T>>>>,
+    load: Box<dyn Fn() -> Result<T, crate::Error> + Send + Sync>,
+    reload_lock: Mutex<()>,
+    error_hook: RwLock<Option<ErrorHook>>,
+}
+
+/// A closure that receives failures of reloads triggered by file changes
+type ErrorHook = Box<dyn Fn(&crate::Error) + Send + Sync>;
+
+impl<T> Shared<T> {
+    /// Load the document, then store and publish it
+    ///
+    /// Concurrent reloads run one at a time, so a slow load never replaces
+    /// a document loaded after it started.
+    fn reload(&self) -> Result<(), crate::Error> {
+        let _reloading = self.reload_lock.lock().expect("reload lock poisoned");
+        let document = Arc::new((self.load)()?);
+        *self.current.write().expect("document lock poisoned") = document.clone();
+
+        let mut subscribers = self.subscribers.lock().expect("subscribers lock poisoned");
+        subscribers.retain(|subscriber| subscriber.send(document.clone()).is_ok());
+        Ok(())
+    }
+}
+
+impl<T, D: Time> Reloading<T, D> {
+    /// Get a shared handle to the current value of the inner document
+    ///
+    /// This is a cheap reference-count bump; the document itself is not cloned.
+    pub fn get(&self) -> Arc<T> {
+        self.shared
+            .current
+            .read()
+            .expect("document lock poisoned")
+            .clone()
+    }
+
+    /// Get a receiver that yields each document reloaded after this call
+    pub fn subscribe(&self) -> mpsc::Receiver<Arc<T>> {
+        let (subscriber, receiver) = mpsc::channel();
+        self.shared
+            .subscribers
+            .lock()
+            .expect("subscribers lock poisoned")
+            .push(subscriber);
+        receiver
+    }
+
+    /// Immediately re-read the file and publish it, returning any load error
+    ///
+    /// If loading fails, the previous document is kept.
+    pub fn reload_now(&self) -> Result<(), crate::Error> {
+        self.shared.reload()
+    }
+
+    /// Install a closure that receives failures of reloads after file changes
+    ///
+    /// By default, these failures are logged with `tracing`. Installing a hook
+    /// replaces that logging. Failures of [`Reloading::reload_now`] are
+    /// returned to the caller instead.
+    pub fn on_error<F>(&self, hook: F)
+    where
+        F: Fn(&crate::Error) + Send + Sync + 'static,
+    {
+        *self
+            .shared
+            .error_hook
+            .write()
+            .expect("error hook lock poisoned") = Some(Box::new(hook));
+    }
+
+    /// Permanently stop watching the file
+    ///
+    /// [`Reloading::get`] keeps returning the last loaded document.
+    pub fn shutdown(&self) {
+        self.watcher.stop();
+    }
+}
+
+impl<T, D: Time> std::fmt::Debug for Reloading<PathTo<T>, D>
+where
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Reloading")
+            .field(&*self.shared.current.read().expect("document lock poisoned"))
+            .finish()
+    }
+}
+
+impl<T, D: Time> Reloading<PathTo<T>, D>
+where
+    T: FromPath + Send + Sync + 'static,
+{
+    /// Load the file at `path` and start watching it for changes
+    ///
+    /// This is what parsing the adapter from an argument does, for building
+    /// values outside of clap (e.g. in tests).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap_adapters::blocking::Reloading;
+    /// use clap_adapters::prelude::*;
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+    ///
+    /// let config = Reloading::<PathTo<JsonOf<serde_json::Value>>>::watch(&config_path)?;
+    /// assert_eq!(config.get().data.0, serde_json::json!({"hello":"world"}));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn watch(path: impl Into<PathBuf>) -> Result<Self, crate::Error> {
+        let path_to = PathTo::<T>::load(path)?;
+        let path = path_to.path.clone();
+
+        let load_path = path.clone();
+        let shared = Arc::new(Shared {
+            current: RwLock::new(Arc::new(path_to)),
+            subscribers: Mutex::new(Vec::new()),
+            load: Box::new(move || PathTo::load(load_path.clone())),
+            reload_lock: Mutex::new(()),
+            error_hook: RwLock::new(None),
+        });
+
+        let watcher_shared = Arc::downgrade(&shared);
+        let notify_path = path.clone();
+        let watcher = FileWatcher::spawn(&path, D::PERIOD, None, changes_contents, move || {
+            let Some(shared) = watcher_shared.upgrade() else {
+                return;
+            };
+
+            let Err(error) = shared.reload() else {
+                return;
+            };
+            let error_hook = shared.error_hook.read().expect("error hook lock poisoned");
+            match &*error_hook {
+                Some(hook) => hook(&error),
+                None => tracing::error!(
+                    error = %error,
+                    path = %notify_path.display(),
+                    "Failed to hotreload after notify",
+                ),
+            }
+        })?;
+
+        let item = Self {
+            shared,
+            watcher: Arc::new(watcher),
+            debounce: std::marker::PhantomData,
+        };
+        Ok(item)
+    }
+}
+
+impl<T, D: Time> std::str::FromStr for Reloading<PathTo<T>, D>
+where
+    T: FromPath + Send + Sync + 'static,
+{
+    type Err = crate::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::watch(s)
+    }
+}
@@ -0,0 +1,94 @@
+use std::path::{Path, PathBuf};
+
+use crate::Error;
+
+/// An adapter that resolves the user-given name in the platform's config directory
+///
+/// Rather than a full path, the user supplies a path relative to the
+/// per-user config directory, such as `app/app.toml`. The config directory is:
+///
+/// - `$XDG_CONFIG_HOME`, or `~/.config` if unset, on Linux and other Unixes
+/// - `~/Library/Application Support` on macOS
+/// - `%APPDATA%` on Windows
+///
+/// The resolved path is handed to the inner adapter. Absolute paths are used
+/// as-is, so users may still point the argument elsewhere. Pair it with
+/// clap's `default_value` to make the argument optional.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// # #[cfg(all(unix, not(target_os = "macos")))]
+/// # {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Name of the Json config within the user's config directory
+///     #[clap(long, default_value = "app/app.json")]
+///     config: ConfigName<PathTo<JsonOf<serde_json::Value>>>,
+/// }
+///
+/// // Create a config file in a temporary config directory
+/// let config_home = tempfile::tempdir()?;
+/// std::fs::create_dir(config_home.path().join("app"))?;
+/// let config_path = config_home.path().join("app/app.json");
+/// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+/// std::env::set_var("XDG_CONFIG_HOME", config_home.path());
+///
+/// // Parse our CLI without passing --config
+/// let cli = Cli::parse_from(["app"]);
+/// assert_eq!(cli.config.0.path, config_path);
+/// assert_eq!(cli.config.0.data.0, serde_json::json!({"hello":"world"}));
+/// # }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConfigName<T>(pub T);
+
+impl<T> std::str::FromStr for ConfigName<T>
+where
+    T: std::str::FromStr,
+    T::Err: Into<Error>,
+{
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = Path::new(s);
+        let path = if name.is_absolute() {
+            name.to_path_buf()
+        } else {
+            config_dir(s)?.join(name)
+        };
+
+        let inner = T::from_str(&path.to_string_lossy()).map_err(Into::into)?;
+        Ok(ConfigName(inner))
+    }
+}
+
+/// Returns the per-user config directory for this platform
+fn config_dir(input: &str) -> Result<PathBuf, Error> {
+    let var = |name: &str| {
+        std::env::var(name).map_err(|source| Error::Expand {
+            input: input.to_string(),
+            placeholder: format!("${name}"),
+            source,
+        })
+    };
+
+    if cfg!(windows) {
+        return Ok(PathBuf::from(var("APPDATA")?));
+    }
+    if cfg!(target_os = "macos") {
+        return Ok(PathBuf::from(var("HOME")?).join("Library/Application Support"));
+    }
+
+    // Relative values are invalid per the XDG spec and must be ignored
+    let xdg_config_home = std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from);
+    match xdg_config_home {
+        Some(dir) if dir.is_absolute() => Ok(dir),
+        _ => Ok(PathBuf::from(var("HOME")?).join(".config")),
+    }
+}
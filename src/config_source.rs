@@ -0,0 +1,186 @@
+//! Implements [`config::Source`] for [`PathTo`] and the reloading adapters
+//!
+//! Each adapter contributes its current document to a `config::Config`, with
+//! every value's origin set to the file's path. Reloading adapters contribute
+//! whichever document is current when the `Config` is built.
+
+use std::path::Path;
+
+use config::{ConfigError, Map, Source, Value, ValueKind};
+use serde::Serialize;
+
+use crate::fs::PathTo;
+
+/// Convert `document` to a table of `config` values originating from `path`
+fn collect_document<T: Serialize>(
+    path: &Path,
+    document: &T,
+) -> Result<Map<String, Value>, ConfigError> {
+    let origin = path.display().to_string();
+    let value = serde_json::to_value(document).map_err(|error| ConfigError::FileParse {
+        uri: Some(origin.clone()),
+        cause: Box::new(error),
+    })?;
+    match to_config_value(&origin, value).kind {
+        ValueKind::Table(table) => Ok(table),
+        _ => Err(ConfigError::Message(format!(
+            "'{origin}' must contain a table to be used as a config source"
+        ))),
+    }
+}
+
+/// Convert a Json value to a `config` value originating from `origin`
+fn to_config_value(origin: &String, value: serde_json::Value) -> Value {
+    let kind = match value {
+        serde_json::Value::Null => ValueKind::Nil,
+        serde_json::Value::Bool(value) => ValueKind::Boolean(value),
+        serde_json::Value::Number(number) => match (number.as_i64(), number.as_u64()) {
+            (Some(value), _) => ValueKind::I64(value),
+            (None, Some(value)) => ValueKind::U64(value),
+            (None, None) => ValueKind::Float(number.as_f64().unwrap_or(f64::NAN)),
+        },
+        serde_json::Value::String(value) => ValueKind::String(value),
+        serde_json::Value::Array(values) => ValueKind::Array(
+            values
+                .into_iter()
+                .map(|value| to_config_value(origin, value))
+                .collect(),
+        ),
+        serde_json::Value::Object(entries) => ValueKind::Table(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key, to_config_value(origin, value)))
+                .collect(),
+        ),
+    };
+    Value::new(Some(origin), kind)
+}
+
+/// Add a CLI-supplied file to a `config::Config` builder
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, serde::Deserialize)]
+/// struct Config {
+///     host: String,
+///     port: u16,
+/// }
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Toml config overriding the defaults
+///     #[clap(long)]
+///     config: PathTo<TomlOf<toml::Table>>,
+/// }
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.toml");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, "port = 8080")?;
+///
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+/// let config: Config = config::Config::builder()
+///     .set_default("host", "localhost")?
+///     .set_default("port", 80)?
+///     .add_source(cli.config)
+///     .build()?
+///     .try_deserialize()?;
+/// assert_eq!(config.host, "localhost");
+/// assert_eq!(config.port, 8080);
+/// # Ok(())
+/// # }
+/// ```
+impl<T> Source for PathTo<T>
+where
+    T: Serialize + Clone + std::fmt::Debug + Send + Sync + 'static,
+{
+    fn clone_into_box(&self) -> Box<dyn Source + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn collect(&self) -> Result<Map<String, Value>, ConfigError> {
+        collect_document(self.path(), &self.data)
+    }
+}
+
+/// Implements [`Source`] for an adapter reloading a `PathTo<T>`, collecting
+/// the document that is current when the `Config` is built
+macro_rules! reloading_source {
+    (impl<$($param:ident),*> $adapter:ty where $($bounds:tt)*) => {
+        impl<$($param),*> Source for $adapter
+        where
+            $($bounds)*
+        {
+            fn clone_into_box(&self) -> Box<dyn Source + Send + Sync> {
+                Box::new(self.clone())
+            }
+
+            fn collect(&self) -> Result<Map<String, Value>, ConfigError> {
+                let document = self.get();
+                collect_document(document.path(), &document.data)
+            }
+        }
+    };
+}
+
+#[cfg(any(
+    feature = "periodic",
+    feature = "reloading",
+    feature = "signal",
+    feature = "sse",
+    feature = "mqtt"
+))]
+reloading_source!(
+    impl<T> crate::ConfigWatcher<PathTo<T>>
+    where
+        T: Serialize + std::fmt::Debug + Send + Sync + 'static,
+);
+
+#[cfg(feature = "reloading")]
+reloading_source!(
+    impl<T, D, B> crate::reloading::Reloading<PathTo<T>, D, B>
+    where
+        T: Serialize + Clone + std::fmt::Debug + Send + Sync + 'static,
+        D: crate::time::Time + Clone + Send + Sync + 'static,
+        B: crate::reloading::WatchBackend + Clone + Send + Sync + 'static,
+);
+
+#[cfg(all(feature = "reloading", feature = "periodic"))]
+reloading_source!(
+    impl<T, P, D> crate::refreshing::Refreshing<PathTo<T>, P, D>
+    where
+        T: Serialize + Clone + std::fmt::Debug + Send + Sync + 'static,
+        P: crate::time::Time + Clone + Send + Sync + 'static,
+        D: crate::time::Time + Clone + Send + Sync + 'static,
+);
+
+#[cfg(feature = "periodic")]
+reloading_source!(
+    impl<T, P, J> crate::periodic::Periodic<PathTo<T>, P, J>
+    where
+        T: Serialize + Clone + std::fmt::Debug + Send + Sync + 'static,
+        P: crate::time::Schedule + Clone + Send + Sync + 'static,
+        J: crate::time::Time + Clone + Send + Sync + 'static,
+);
+
+#[cfg(all(unix, feature = "signal"))]
+reloading_source!(
+    impl<T, S> crate::signal::OnSignal<PathTo<T>, S>
+    where
+        T: Serialize + Clone + std::fmt::Debug + Send + Sync + 'static,
+        S: crate::signal::ReloadSignal + Clone + Send + Sync + 'static,
+);
+
+#[cfg(feature = "blocking")]
+reloading_source!(
+    impl<T, D> crate::blocking::Reloading<PathTo<T>, D>
+    where
+        T: Serialize + Clone + std::fmt::Debug + Send + Sync + 'static,
+        D: crate::time::Time + Clone + Send + Sync + 'static,
+);
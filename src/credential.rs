@@ -0,0 +1,69 @@
+use std::path::PathBuf;
+
+use crate::Error;
+
+/// An adapter that loads a systemd credential by name
+///
+/// The user supplies the name of a credential, such as `db-password`, which
+/// is resolved within `$CREDENTIALS_DIRECTORY`, where systemd places the
+/// credentials configured with `LoadCredential=` or `SetCredential=`. The
+/// resolved path is handed to the inner adapter, e.g. `PathTo<T>`.
+///
+/// Names must be plain file names; parsing fails if they contain a `/` or
+/// if `$CREDENTIALS_DIRECTORY` is unset, i.e. the service was started
+/// without credentials.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Name of the credential holding the database password
+///     #[clap(long, default_value = "db-password")]
+///     db_password: CredentialOf<PathTo<String>>,
+/// }
+///
+/// // Create a credential, as systemd would before starting the service
+/// let credentials = tempfile::tempdir()?;
+/// std::fs::write(credentials.path().join("db-password"), "hunter2")?;
+/// std::env::set_var("CREDENTIALS_DIRECTORY", credentials.path());
+///
+/// let cli = Cli::parse_from(["app"]);
+/// assert_eq!(cli.db_password.0.data, "hunter2");
+///
+/// // Credentials can't escape the credentials directory
+/// assert!(Cli::try_parse_from(["app", "--db-password", "../db-password"]).is_err());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CredentialOf<T>(pub T);
+
+impl<T> std::str::FromStr for CredentialOf<T>
+where
+    T: std::str::FromStr,
+    T::Err: Into<Error>,
+{
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() || s == "." || s == ".." || s.contains('/') || s.contains('\\') {
+            return Err(Error::InvalidCredential {
+                name: s.to_string(),
+            });
+        }
+
+        let dir = std::env::var("CREDENTIALS_DIRECTORY").map_err(|source| Error::Expand {
+            input: s.to_string(),
+            placeholder: "$CREDENTIALS_DIRECTORY".to_string(),
+            source,
+        })?;
+        let path = PathBuf::from(dir).join(s);
+
+        let inner = T::from_str(&path.to_string_lossy()).map_err(Into::into)?;
+        Ok(CredentialOf(inner))
+    }
+}
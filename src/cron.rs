@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Local};
+
+use crate::{time::Schedule, Error};
+
+/// A cron expression deciding when `Periodic` reloads, in local time
+///
+/// Expressions have five fields (minute, hour, day of month, month and day
+/// of week), optionally preceded by a seconds field, as well as nicknames
+/// such as `@daily`. For example, `0 2 * * *` matches 02:00 every day.
+///
+/// Use [`Cron<C>`] to give the expression at the type level, or pass it to
+/// an adapter under [`WithOptions`] as the `cron` option.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use std::time::{Duration, SystemTime};
+///
+/// use chrono::{Local, TimeZone};
+/// use clap_adapters::prelude::*;
+///
+/// let nightly: CronSchedule = "0 2 * * *".parse()?;
+/// let noon = SystemTime::from(Local.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap());
+/// let next = nightly.next_after(noon)?;
+/// assert_eq!(next, SystemTime::from(Local.with_ymd_and_hms(2024, 1, 16, 2, 0, 0).unwrap()));
+/// assert_eq!(nightly.delay_after(noon)?, Duration::from_secs(14 * 60 * 60));
+///
+/// assert!("0 2 * *".parse::<CronSchedule>().is_err());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`WithOptions`]: crate::WithOptions
+#[derive(Debug, Clone)]
+pub struct CronSchedule {
+    /// The cron expression as given
+    pub expression: String,
+
+    cron: croner::Cron,
+}
+
+impl CronSchedule {
+    /// Parse a cron expression, failing if it is malformed or never matches
+    pub fn new(expression: &str) -> Result<Self, Error> {
+        let schedule = Self::parse(expression)?;
+        schedule.next_after(SystemTime::now())?;
+        Ok(schedule)
+    }
+
+    /// Parse a cron expression, without checking that it ever matches
+    fn parse(expression: &str) -> Result<Self, Error> {
+        let cron = expression
+            .parse::<croner::Cron>()
+            .map_err(|error| invalid(expression, error))?;
+        let schedule = CronSchedule {
+            expression: expression.to_string(),
+            cron,
+        };
+        Ok(schedule)
+    }
+
+    /// Returns the first time matched by the expression strictly after `time`
+    pub fn next_after(&self, time: SystemTime) -> Result<SystemTime, Error> {
+        let time = DateTime::<Local>::from(time);
+        let next = self
+            .cron
+            .find_next_occurrence(&time, false)
+            .map_err(|error| invalid(&self.expression, error))?;
+        Ok(next.into())
+    }
+
+    /// Returns how long to wait after `now` until the next matched time
+    pub fn delay_after(&self, now: SystemTime) -> Result<Duration, Error> {
+        let next = self.next_after(now)?;
+        Ok(next.duration_since(now).unwrap_or_default())
+    }
+}
+
+impl std::str::FromStr for CronSchedule {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+/// A [`Schedule`] that reloads at the times matched by the cron expression of `C`
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// /// Reload at 02:00 every day
+/// #[derive(Debug, Clone, Copy)]
+/// enum Nightly {}
+/// impl CronExpression for Nightly {
+///     const EXPRESSION: &'static str = "0 2 * * *";
+/// }
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Json config to be reloaded every night
+///     #[clap(long)]
+///     config: Periodic<PathTo<JsonOf<serde_json::Value>>, Cron<Nightly>>,
+/// }
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+///
+/// let runtime = tokio::runtime::Runtime::new()?;
+/// runtime.block_on(async {
+///     let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+///     assert_eq!(cli.config.get().data.0, serde_json::json!({"hello":"world"}));
+/// });
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Cron<C: CronExpression>(PhantomData<C>);
+
+/// Trait for type markers holding a cron expression, see [`Cron`]
+pub trait CronExpression {
+    /// The cron expression, such as `0 2 * * *`
+    const EXPRESSION: &'static str;
+}
+
+impl<C: CronExpression> Schedule for Cron<C> {
+    fn delay_after(now: SystemTime) -> Result<Duration, Error> {
+        cached_schedule(C::EXPRESSION)?.delay_after(now)
+    }
+}
+
+/// Returns the schedule of `expression`, parsing it only on first use
+///
+/// Statics can't be generic over `C`, so schedules are cached by expression.
+fn cached_schedule(expression: &'static str) -> Result<Arc<CronSchedule>, Error> {
+    static SCHEDULES: OnceLock<Mutex<HashMap<&'static str, Arc<CronSchedule>>>> = OnceLock::new();
+
+    let mut schedules = SCHEDULES
+        .get_or_init(Default::default)
+        .lock()
+        .expect("cron schedule cache lock poisoned");
+    if let Some(schedule) = schedules.get(expression) {
+        return Ok(Arc::clone(schedule));
+    }
+    let schedule = Arc::new(CronSchedule::parse(expression)?);
+    schedules.insert(expression, Arc::clone(&schedule));
+    Ok(schedule)
+}
+
+fn invalid(expression: &str, error: croner::errors::CronError) -> Error {
+    Error::InvalidSchedule {
+        expression: expression.to_string(),
+        reason: error.to_string(),
+    }
+}
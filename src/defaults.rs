@@ -0,0 +1,118 @@
+use std::marker::PhantomData;
+
+use serde_json::Value;
+
+use crate::traits::{FromMerged, FromReader};
+
+/// An adapter that deep-merges the user's document over embedded defaults
+///
+/// The defaults are a complete document in the same format as `T`, usually
+/// embedded at compile time with `include_str!`, and given by a
+/// [`DefaultDocument`] marker. The user's file only needs to contain the keys
+/// it changes, so partial configs work without making every field of `T` an
+/// `Option`.
+///
+/// Objects are merged recursively, while any other value (including arrays)
+/// from the user's file replaces the default outright.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Clone, serde::Deserialize)]
+/// struct Config {
+///     host: String,
+///     port: u16,
+/// }
+///
+/// /// The defaults, usually embedded with `include_str!("defaults.toml")`
+/// #[derive(Debug, Clone)]
+/// enum Defaults {}
+/// impl DefaultDocument for Defaults {
+///     const DOCUMENT: &'static str = r#"
+///         host = "localhost"
+///         port = 80
+///     "#;
+/// }
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Toml config overriding the defaults
+///     #[clap(long)]
+///     config: PathTo<WithDefaults<TomlOf<Config>, Defaults>>,
+/// }
+///
+/// // Create a partial config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.toml");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, "port = 8080")?;
+///
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+/// assert_eq!(cli.config.host, "localhost");
+/// assert_eq!(cli.config.port, 8080);
+/// # Ok(())
+/// # }
+/// ```
+pub struct WithDefaults<T, D: DefaultDocument>(pub T, PhantomData<fn() -> D>);
+
+impl<T, D: DefaultDocument> WithDefaults<T, D> {
+    /// Wrap an already merged document
+    pub fn new(data: T) -> Self {
+        WithDefaults(data, PhantomData)
+    }
+
+    /// Returns the merged document
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: FromMerged, D: DefaultDocument> FromReader for WithDefaults<T, D> {
+    type Error = T::Error;
+    fn from_reader(reader: &mut impl std::io::BufRead) -> Result<Self, Self::Error> {
+        let value = Self::value_from_reader(reader)?;
+        Self::from_merged(value)
+    }
+}
+
+impl<T: FromMerged, D: DefaultDocument> FromMerged for WithDefaults<T, D> {
+    const EXTENSIONS: &'static [&'static str] = T::EXTENSIONS;
+    type Error = T::Error;
+    fn value_from_reader(reader: &mut impl std::io::BufRead) -> Result<Value, Self::Error> {
+        T::value_from_reader(reader)
+    }
+    fn from_merged(value: Value) -> Result<Self, Self::Error> {
+        let mut merged = T::value_from_reader(&mut D::DOCUMENT.as_bytes())?;
+        crate::merge::deep_merge(&mut merged, value);
+        T::from_merged(merged).map(WithDefaults::new)
+    }
+}
+
+impl<T, D: DefaultDocument> std::ops::Deref for WithDefaults<T, D> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T: Clone, D: DefaultDocument> Clone for WithDefaults<T, D> {
+    fn clone(&self) -> Self {
+        WithDefaults::new(self.0.clone())
+    }
+}
+
+impl<T: std::fmt::Debug, D: DefaultDocument> std::fmt::Debug for WithDefaults<T, D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("WithDefaults").field(&self.0).finish()
+    }
+}
+
+/// Trait for type markers providing the default document for [`WithDefaults`]
+pub trait DefaultDocument {
+    /// The default document, in the same format as the user's file
+    const DOCUMENT: &'static str;
+}
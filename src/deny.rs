@@ -0,0 +1,238 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::traits::{invalid_data, FromMerged, FromReader};
+
+/// An adapter that rejects documents containing keys unknown to `T`
+///
+/// Serde ignores unknown keys by default, so a typo in a config key silently
+/// leaves the intended field at its default. Wrapping a format adapter as
+/// `DenyUnknown<T>` (e.g. `DenyUnknown<JsonOf<Config>>`) fails parsing
+/// instead, listing every key that `T` didn't consume, along with the
+/// closest known key as a suggestion.
+///
+/// Unlike `#[serde(deny_unknown_fields)]`, this works for types from other
+/// crates and with `#[serde(flatten)]`, and reports all unknown keys at once.
+/// The unknown keys are available by downcasting the error's source to
+/// [`UnknownKeys`].
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Clone, serde::Deserialize)]
+/// struct Config {
+///     host: String,
+///     #[serde(default)]
+///     port: u16,
+/// }
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Json config
+///     #[clap(long)]
+///     config: PathTo<DenyUnknown<JsonOf<Config>>>,
+/// }
+///
+/// // Create a config file with a typo in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, r#"{"host": "localhost", "prot": 8080}"#)?;
+///
+/// let error = Cli::try_parse_from(["app", "--config", &config_path_string]).unwrap_err();
+/// assert!(error.to_string().contains("unknown key 'prot'"));
+///
+/// // Keys similar to a known key are suggested
+/// std::fs::write(&config_path, r#"{"host": "localhost", "port": 80, "prt": 8080}"#)?;
+/// let error = Cli::try_parse_from(["app", "--config", &config_path_string]).unwrap_err();
+/// assert!(error.to_string().contains("unknown key 'prt' (did you mean 'port'?)"));
+///
+/// std::fs::write(&config_path, r#"{"host": "localhost", "port": 8080}"#)?;
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+/// assert_eq!(cli.config.port, 8080);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DenyUnknown<T>(pub T);
+
+impl<T> std::ops::Deref for DenyUnknown<T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DenyUnknown<T> {
+    /// Returns the inner document
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: FromMerged + DeserializeOwned> FromReader for DenyUnknown<T> {
+    type Error = std::io::Error;
+    fn from_reader(reader: &mut impl std::io::BufRead) -> Result<Self, Self::Error> {
+        let value = Self::value_from_reader(reader)?;
+        Self::from_merged(value)
+    }
+}
+
+impl<T: FromMerged + DeserializeOwned> FromMerged for DenyUnknown<T> {
+    const EXTENSIONS: &'static [&'static str] = T::EXTENSIONS;
+    type Error = std::io::Error;
+    fn value_from_reader(reader: &mut impl std::io::BufRead) -> Result<Value, Self::Error> {
+        T::value_from_reader(reader).map_err(invalid_data)
+    }
+    fn from_merged(value: Value) -> Result<Self, Self::Error> {
+        let unknown = unknown_keys::<T>(&value);
+        let data = T::from_merged(value).map_err(invalid_data)?;
+        if !unknown.keys.is_empty() {
+            return Err(invalid_data(unknown));
+        }
+        Ok(DenyUnknown(data))
+    }
+}
+
+/// Find the keys in `document` that deserializing a `T` ignores
+fn unknown_keys<T: DeserializeOwned>(document: &Value) -> UnknownKeys {
+    let mut paths = Vec::new();
+    let _ = serde_ignored::deserialize::<_, _, T>(document, |path| paths.push(segments(&path)));
+
+    let keys = paths
+        .iter()
+        .map(|path| {
+            let (name, parent) = path.split_last().expect("ignored keys are never the root");
+            let known = parent
+                .iter()
+                .try_fold(document, |value, segment| match segment {
+                    Segment::Key(key) => value.get(key),
+                    Segment::Index(index) => value.get(index),
+                })
+                .and_then(Value::as_object)
+                .into_iter()
+                .flat_map(|object| object.keys())
+                .filter(|key| {
+                    !paths.iter().any(|path| {
+                        path.split_last().is_some_and(|(other, other_parent)| {
+                            other_parent == parent && *other == Segment::Key(key.to_string())
+                        })
+                    })
+                });
+            let suggestion = match name {
+                Segment::Key(name) => closest(name, known),
+                Segment::Index(_) => None,
+            };
+            UnknownKey {
+                key: join(path),
+                suggestion,
+            }
+        })
+        .collect();
+    UnknownKeys { keys }
+}
+
+/// A step in the path to a value in a document
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Flatten an ignored path into its keys and indices
+fn segments(path: &serde_ignored::Path) -> Vec<Segment> {
+    use serde_ignored::Path;
+    match path {
+        Path::Root => Vec::new(),
+        Path::Seq { parent, index } => {
+            let mut segments = segments(parent);
+            segments.push(Segment::Index(*index));
+            segments
+        }
+        Path::Map { parent, key } => {
+            let mut segments = segments(parent);
+            segments.push(Segment::Key(key.clone()));
+            segments
+        }
+        Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => segments(parent),
+    }
+}
+
+/// Formats `path` as a dotted key, e.g. `servers.0.host`
+fn join(path: &[Segment]) -> String {
+    let parts: Vec<String> = path
+        .iter()
+        .map(|segment| match segment {
+            Segment::Key(key) => key.clone(),
+            Segment::Index(index) => index.to_string(),
+        })
+        .collect();
+    parts.join(".")
+}
+
+/// Returns the known key most similar to `name`, if any is similar enough
+fn closest<'a>(name: &str, known: impl Iterator<Item = &'a String>) -> Option<String> {
+    let threshold = (name.chars().count() / 3).max(1);
+    known
+        .map(|key| (edit_distance(name, key), key))
+        .filter(|(distance, _)| *distance <= threshold)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, key)| key.clone())
+}
+
+/// The Levenshtein distance between `a` and `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// The error reported by [`DenyUnknown`] for documents with unknown keys
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownKeys {
+    /// The unknown keys, in document order
+    pub keys: Vec<UnknownKey>,
+}
+
+/// A key that was not consumed when deserializing a document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownKey {
+    /// The dotted path to the key, e.g. `server.prot`
+    pub key: String,
+
+    /// The most similar known key at the same level, if any
+    pub suggestion: Option<String>,
+}
+
+impl std::fmt::Display for UnknownKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let keys: Vec<String> = self.keys.iter().map(ToString::to_string).collect();
+        f.write_str(&keys.join(", "))
+    }
+}
+
+impl std::error::Error for UnknownKeys {}
+
+impl std::fmt::Display for UnknownKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown key '{}'", self.key)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (did you mean '{suggestion}'?)")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+//! Provides [`ParseDiagnostic`] for showing where a document failed to parse
+
+use std::path::PathBuf;
+
+use miette::{NamedSource, SourceSpan};
+
+use crate::Error;
+
+/// A parse error pointing at the offending line of the document
+///
+/// Rendered with [`miette`], the error shows a snippet of the file with the
+/// location of the error underlined. [`AdapterParser`] shows it in place of
+/// the single-line message when the `miette` feature is enabled, and it is
+/// available for errors from other sources through [`Error::diagnostic`].
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Toml config
+///     #[clap(long)]
+///     config: PathTo<TomlOf<serde_json::Value>>,
+/// }
+///
+/// // Create a config file with a typo on its second line
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.toml");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, "host = \"localhost\"\nport = 80 80\n")?;
+///
+/// let error = Cli::try_parse_from(["app", "--config", &config_path_string]).unwrap_err();
+/// let message = error.to_string();
+/// assert!(message.contains("port = 80 80"));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`AdapterParser`]: crate::AdapterParser
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+#[error("failed to parse '{}' as {format}", path.display())]
+pub struct ParseDiagnostic {
+    /// The path of the malformed file
+    pub path: PathBuf,
+
+    /// The name of the format the file failed to parse as
+    pub format: &'static str,
+
+    /// What is wrong at the location
+    pub message: String,
+
+    #[source_code]
+    source_code: NamedSource<String>,
+
+    #[label("{message}")]
+    span: SourceSpan,
+}
+
+impl Error {
+    /// Returns a diagnostic with a source snippet, if this is a parse error with a location
+    ///
+    /// The file is read again to show its contents, so this returns `None` if
+    /// it is empty or has become unreadable since.
+    pub fn diagnostic(&self) -> Option<ParseDiagnostic> {
+        let (path, format, message, location) = match self {
+            Error::Json { path, source } => {
+                let location = Location::LineColumn(source.line(), source.column());
+                (path, "Json", source.to_string(), location)
+            }
+            #[cfg(feature = "toml")]
+            Error::Toml { path, source } => {
+                let location = Location::Offset(source.span()?.start);
+                (path, "Toml", source.message().to_string(), location)
+            }
+            #[cfg(any(feature = "yaml", feature = "yaml-ng"))]
+            Error::Yaml { path, source } => {
+                let location = Location::Offset(source.offset()?);
+                (path, "Yaml", source.to_string(), location)
+            }
+            _ => return None,
+        };
+
+        let contents = std::fs::read_to_string(path)
+            .ok()
+            .filter(|contents| !contents.is_empty())?;
+        let offset = location.offset(&contents);
+        let message = strip_location(&message);
+        Some(ParseDiagnostic {
+            path: path.clone(),
+            format,
+            message,
+            source_code: NamedSource::new(path.display().to_string(), contents),
+            span: SourceSpan::from(offset..offset),
+        })
+    }
+}
+
+/// Where in a document an error occurred
+enum Location {
+    /// A byte offset
+    #[cfg(any(feature = "toml", feature = "yaml", feature = "yaml-ng"))]
+    Offset(usize),
+    /// A one-based line and column
+    LineColumn(usize, usize),
+}
+
+impl Location {
+    /// Returns the byte offset into `contents`, at a character boundary
+    fn offset(&self, contents: &str) -> usize {
+        let mut offset = match *self {
+            #[cfg(any(feature = "toml", feature = "yaml", feature = "yaml-ng"))]
+            Location::Offset(offset) => offset,
+            Location::LineColumn(line, column) => {
+                let line_start: usize = contents
+                    .split_inclusive('\n')
+                    .take(line.saturating_sub(1))
+                    .map(str::len)
+                    .sum();
+                // Column 0 refers to the end of the previous line
+                (line_start + column).saturating_sub(1)
+            }
+        };
+        offset = offset.min(contents.len());
+        while !contents.is_char_boundary(offset) {
+            offset -= 1;
+        }
+
+        // Point errors at the end of a line or file at the last character before them
+        while offset > 0 && contents[offset..].chars().next().is_none_or(is_line_end) {
+            offset = contents[..offset]
+                .char_indices()
+                .next_back()
+                .map_or(0, |(index, _)| index);
+        }
+        offset
+    }
+}
+
+/// Remove each ` at line L column C` from `message`, as the snippet shows the location
+fn strip_location(message: &str) -> String {
+    let mut stripped = String::new();
+    let mut rest = message;
+    while let Some((before, after)) = rest.split_once(" at line ") {
+        stripped.push_str(before);
+        let after = after.trim_start_matches(|c: char| c.is_ascii_digit());
+        let after = after.strip_prefix(" column ").unwrap_or(after);
+        rest = after.trim_start_matches(|c: char| c.is_ascii_digit());
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+fn is_line_end(character: char) -> bool {
+    character == '\n' || character == '\r'
+}
@@ -0,0 +1,161 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    traits::{FromMerged, HashingReader, Reload},
+    Error,
+};
+
+/// An adapter for loading and deep-merging every file in a directory
+///
+/// Given a directory path from the user, `DirOf<T>` reads each regular file
+/// in the directory whose extension is recognized by `T` (see
+/// [`FromMerged::EXTENSIONS`]), in sorted filename order, and deep-merges
+/// the documents into a single `T`. Keys from later files take precedence
+/// over earlier ones, which supports the conventional `conf.d/` layout:
+///
+/// ```text
+/// /etc/app/conf.d/
+///   00-defaults.json
+///   10-site.json
+///   20-local.json
+/// ```
+///
+/// Objects are merged recursively, while any other value (including arrays)
+/// from a later file replaces the earlier value outright.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a directory of Json config fragments
+///     #[clap(long)]
+///     config_dir: DirOf<JsonOf<serde_json::Value>>,
+/// }
+///
+/// // Create a directory with two config fragments and an unrelated file
+/// let config_dir = tempfile::tempdir()?;
+/// let config_dir_string = config_dir.path().display().to_string();
+/// std::fs::write(
+///     config_dir.path().join("00-defaults.json"),
+///     r#"{"server": {"host": "localhost", "port": 80}}"#,
+/// )?;
+/// std::fs::write(
+///     config_dir.path().join("10-local.json"),
+///     r#"{"server": {"port": 8080}}"#,
+/// )?;
+/// std::fs::write(config_dir.path().join("README.md"), "ignored")?;
+///
+/// // Parse our CLI, passing our config directory to --config-dir
+/// let cli = Cli::parse_from(["app", "--config-dir", &config_dir_string]);
+///
+/// // The fragments are merged in filename order
+/// assert_eq!(cli.config_dir.files.len(), 2);
+/// assert_eq!(
+///     cli.config_dir.data.0,
+///     serde_json::json!({"server": {"host": "localhost", "port": 8080}}),
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DirOf<T> {
+    /// The directory path given as an argument by the user
+    pub path: PathBuf,
+
+    /// The files that were merged, in the order they were applied
+    pub files: Vec<PathBuf>,
+
+    /// The data merged from all files in the directory
+    pub data: T,
+
+    /// A hash of the names and contents of the merged files, taken while parsing them
+    pub hash: u64,
+}
+
+impl<T: FromMerged> DirOf<T> {
+    /// Merge the recognized files in the directory at `path`
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        use std::hash::{Hash, Hasher};
+
+        let path = path.into();
+        let files = Self::recognized_files(&path)?;
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        let mut merged = serde_json::Value::Object(Default::default());
+        for file_path in &files {
+            let file =
+                std::fs::File::open(file_path).map_err(|error| Error::io(file_path, error))?;
+            let mut reader = HashingReader::new(std::io::BufReader::new(file));
+            let value = T::value_from_reader(&mut reader)
+                .map_err(|error| Error::parse(file_path.clone(), error))?;
+            file_path.hash(&mut hasher);
+            hasher.write_u64(
+                reader
+                    .finish()
+                    .map_err(|error| Error::io(file_path, error))?,
+            );
+            crate::merge::deep_merge(&mut merged, value);
+        }
+
+        let data = T::from_merged(merged).map_err(|error| Error::parse(path.clone(), error))?;
+        let item = DirOf {
+            path,
+            files,
+            data,
+            hash: hasher.finish(),
+        };
+        Ok(item)
+    }
+
+    /// List the files in the directory at `path` with a recognized extension, sorted
+    fn recognized_files(path: &Path) -> Result<Vec<PathBuf>, Error> {
+        let mut files = Vec::new();
+        let entries = std::fs::read_dir(path).map_err(|error| Error::io(path, error))?;
+        for entry in entries {
+            let file_path = entry.map_err(|error| Error::io(path, error))?.path();
+            if !file_path.is_file() {
+                continue;
+            }
+
+            let recognized = file_path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| T::EXTENSIONS.contains(&extension));
+            if recognized {
+                files.push(file_path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+}
+
+impl<T: FromMerged> std::str::FromStr for DirOf<T> {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::load(s)
+    }
+}
+
+impl<T: FromMerged> Reload for DirOf<T> {
+    fn location(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn reload(&self) -> Result<Self, Error> {
+        Self::load(self.path.clone())
+    }
+
+    fn source_hash(&self) -> Option<u64> {
+        Some(self.hash)
+    }
+
+    fn value_hint() -> clap::ValueHint {
+        clap::ValueHint::DirPath
+    }
+}
@@ -0,0 +1,191 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::Error;
+
+/// The format to dump an effective config in, e.g. given to a `--dump-config` flag
+///
+/// Only the formats whose feature is enabled are available, except for Json
+/// which is always available. As the available formats depend on features,
+/// matching on a `DumpFormat` requires a wildcard arm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DumpFormat {
+    /// Pretty-printed Json
+    Json,
+    /// Toml, which has no `null`, so keys with null values are left out
+    #[cfg(feature = "toml")]
+    Toml,
+    /// Yaml
+    #[cfg(any(feature = "yaml", feature = "yaml-ng"))]
+    Yaml,
+}
+
+impl clap::ValueEnum for DumpFormat {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            DumpFormat::Json,
+            #[cfg(feature = "toml")]
+            DumpFormat::Toml,
+            #[cfg(any(feature = "yaml", feature = "yaml-ng"))]
+            DumpFormat::Yaml,
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        let name = match self {
+            DumpFormat::Json => "json",
+            #[cfg(feature = "toml")]
+            DumpFormat::Toml => "toml",
+            #[cfg(any(feature = "yaml", feature = "yaml-ng"))]
+            DumpFormat::Yaml => "yaml",
+        };
+        Some(clap::builder::PossibleValue::new(name))
+    }
+}
+
+/// The value that redacted secrets are replaced with
+pub const REDACTED: &str = "[redacted]";
+
+/// Serialize the effective config `value` as `format`, with secrets redacted
+///
+/// Values under keys that look like secrets according to [`is_secret_key`]
+/// are replaced with [`REDACTED`], so that the output is safe to print or
+/// attach to a bug report.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Json config
+///     #[clap(long)]
+///     config: PathTo<JsonOf<serde_json::Value>>,
+///
+///     /// Print the effective config in the given format and exit
+///     #[clap(long, value_name = "FORMAT")]
+///     dump_config: Option<DumpFormat>,
+/// }
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(
+///     &config_path,
+///     r#"{"database": {"host": "localhost", "password": "hunter2"}}"#,
+/// )?;
+///
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string, "--dump-config", "json"]);
+/// if let Some(format) = cli.dump_config {
+///     let dump = dump_effective_config(&cli.config, format)?;
+///     let dumped: serde_json::Value = serde_json::from_str(&dump)?;
+///     assert_eq!(
+///         dumped,
+///         serde_json::json!({"database": {"host": "localhost", "password": "[redacted]"}}),
+///     );
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn dump_effective_config<T: Serialize>(value: &T, format: DumpFormat) -> Result<String, Error> {
+    let mut value = serde_json::to_value(value).map_err(dump_error)?;
+    redact(&mut value, is_secret_key);
+    dump_value(&value, format)
+}
+
+/// Replace the values under every key matching `is_secret` in `value` with [`REDACTED`]
+///
+/// # Example
+///
+/// ```
+/// use clap_adapters::prelude::*;
+///
+/// let mut value = serde_json::json!({"api": {"url": "https://example.com", "bearer": "abc"}});
+/// redact(&mut value, |key| key == "bearer");
+/// assert_eq!(value, serde_json::json!({"api": {"url": "https://example.com", "bearer": "[redacted]"}}));
+/// ```
+pub fn redact(value: &mut Value, is_secret: impl Fn(&str) -> bool + Copy) {
+    match value {
+        Value::Object(entries) => {
+            for (key, value) in entries.iter_mut() {
+                if is_secret(key) {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact(value, is_secret);
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                redact(value, is_secret);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns true if `key` names a secret, such as `password`, `api_key` or `clientSecret`
+///
+/// Keys are matched case-insensitively, ignoring `_` and `-`, against a
+/// list of common names for secrets.
+pub fn is_secret_key(key: &str) -> bool {
+    const SECRETS: &[&str] = &[
+        "password",
+        "passwd",
+        "passphrase",
+        "secret",
+        "token",
+        "apikey",
+        "privatekey",
+        "credential",
+    ];
+    let key: String = key
+        .chars()
+        .filter(|c| !matches!(c, '_' | '-'))
+        .collect::<String>()
+        .to_lowercase();
+    SECRETS.iter().any(|secret| key.contains(secret))
+}
+
+/// Serialize the already redacted `value` as `format`
+pub(crate) fn dump_value(value: &Value, format: DumpFormat) -> Result<String, Error> {
+    match format {
+        DumpFormat::Json => {
+            let mut dump = serde_json::to_string_pretty(value).map_err(dump_error)?;
+            dump.push('\n');
+            Ok(dump)
+        }
+        #[cfg(feature = "toml")]
+        DumpFormat::Toml => {
+            let mut value = value.clone();
+            remove_nulls(&mut value);
+            toml::to_string_pretty(&value).map_err(dump_error)
+        }
+        #[cfg(any(feature = "yaml", feature = "yaml-ng"))]
+        DumpFormat::Yaml => crate::yaml::backend::to_string(value).map_err(dump_error),
+    }
+}
+
+/// Remove the keys with null values in `value`, which Toml can't represent
+#[cfg(feature = "toml")]
+fn remove_nulls(value: &mut Value) {
+    match value {
+        Value::Object(entries) => {
+            entries.retain(|_, value| !value.is_null());
+            entries.values_mut().for_each(remove_nulls);
+        }
+        Value::Array(values) => values.iter_mut().for_each(remove_nulls),
+        _ => {}
+    }
+}
+
+fn dump_error(error: impl std::error::Error + Send + Sync + 'static) -> Error {
+    Error::Dump {
+        source: Box::new(error),
+    }
+}
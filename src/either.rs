@@ -0,0 +1,117 @@
+use std::str::FromStr;
+
+use crate::Error;
+
+/// An adapter for arguments that may be either a URL or a local path
+///
+/// Arguments that look like a URL, i.e. start with a scheme followed by
+/// `://` such as `https://` or `mqtt://`, are parsed as `A`. Anything else
+/// is parsed as `B`, typically a `PathTo<T>`. A `file://` URL is turned
+/// into its path and parsed as `B` as well.
+///
+/// A URL is never retried as a path, so a URL that fails to load is
+/// reported as such. To fall back from one source to another, use
+/// [`FirstOf`](crate::FirstOf) instead.
+///
+/// When `A` and `B` dereference to the same type, so does `Either`, to
+/// expose the data uniformly.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// A URL to fetch the config from, or a path to a Json config
+///     #[clap(long)]
+///     config: Either<String, PathTo<JsonOf<serde_json::Value>>>,
+/// }
+///
+/// let cli = Cli::parse_from(["app", "--config", "https://example.com/config.json"]);
+/// assert!(matches!(cli.config, Either::Left(url) if url == "https://example.com/config.json"));
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+///
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+/// let Either::Right(config) = cli.config else {
+///     panic!("a path should be loaded as a file");
+/// };
+/// assert_eq!(config.data.0, serde_json::json!({"hello":"world"}));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Either<A, B> {
+    /// The argument was a URL
+    Left(A),
+    /// The argument was a path
+    Right(B),
+}
+
+impl<A, B> Either<A, B> {
+    /// Returns the URL adapter, if the argument was a URL
+    pub fn left(&self) -> Option<&A> {
+        match self {
+            Either::Left(left) => Some(left),
+            Either::Right(_) => None,
+        }
+    }
+
+    /// Returns the path adapter, if the argument was a path
+    pub fn right(&self) -> Option<&B> {
+        match self {
+            Either::Left(_) => None,
+            Either::Right(right) => Some(right),
+        }
+    }
+}
+
+impl<A, B, U: ?Sized> std::ops::Deref for Either<A, B>
+where
+    A: std::ops::Deref<Target = U>,
+    B: std::ops::Deref<Target = U>,
+{
+    type Target = U;
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Either::Left(left) => left,
+            Either::Right(right) => right,
+        }
+    }
+}
+
+impl<A, B> FromStr for Either<A, B>
+where
+    A: FromStr,
+    A::Err: Into<Error>,
+    B: FromStr,
+    B::Err: Into<Error>,
+{
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match url_scheme(s) {
+            Some("file") => B::from_str(&s["file://".len()..])
+                .map(Either::Right)
+                .map_err(Into::into),
+            Some(_) => A::from_str(s).map(Either::Left).map_err(Into::into),
+            None => B::from_str(s).map(Either::Right).map_err(Into::into),
+        }
+    }
+}
+
+/// Returns the scheme of `input` if it starts like a URL, as in `scheme://`
+fn url_scheme(input: &str) -> Option<&str> {
+    let (scheme, _) = input.split_once("://")?;
+    let mut characters = scheme.chars();
+    let starts_with_letter = characters.next()?.is_ascii_alphabetic();
+    let valid = characters.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    // A single letter is a Windows drive, as in `C://config.json`
+    (starts_with_letter && valid && scheme.len() > 1).then_some(scheme)
+}
@@ -0,0 +1,178 @@
+use std::marker::PhantomData;
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+
+use crate::traits::{invalid_data, FromReader};
+
+/// Length of the nonce that prefixes each encrypted file
+const NONCE_LEN: usize = 12;
+
+/// An adapter that decrypts an AES-256-GCM encrypted document before parsing it
+///
+/// The file holds a random 12-byte nonce followed by the ciphertext and its
+/// authentication tag. It is decrypted with the 256-bit key in the
+/// environment variable named by the [`KeyVar`] marker `K`, given as 64 hex
+/// digits (e.g. generated with `openssl rand -hex 32`), and the plaintext is
+/// parsed as `T`. Files that were tampered with or encrypted with another key
+/// fail to load.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use aes_gcm::aead::{Aead, KeyInit};
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// /// The variable holding the hex-encoded key
+/// #[derive(Debug, Clone)]
+/// enum AppKey {}
+/// impl KeyVar for AppKey {
+///     const NAME: &'static str = "APP_CONFIG_KEY";
+/// }
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to an encrypted Json config
+///     #[clap(long)]
+///     config: PathTo<EncryptedOf<JsonOf<serde_json::Value>, AppKey>>,
+/// }
+///
+/// // Encrypt a config, as a deployment pipeline would
+/// let key = [7u8; 32];
+/// let nonce = [1u8; 12];
+/// let cipher = aes_gcm::Aes256Gcm::new(&key.into());
+/// let ciphertext = cipher
+///     .encrypt(&nonce.into(), br#"{"token":"hunter2"}"#.as_slice())
+///     .map_err(|error| anyhow::anyhow!("{error}"))?;
+///
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json.enc");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, [nonce.as_slice(), &ciphertext].concat())?;
+///
+/// let hex_key: String = key.iter().map(|byte| format!("{byte:02x}")).collect();
+/// std::env::set_var("APP_CONFIG_KEY", hex_key);
+///
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+/// assert_eq!(cli.config.0["token"], "hunter2");
+///
+/// // The wrong key is rejected
+/// std::env::set_var("APP_CONFIG_KEY", "00".repeat(32));
+/// assert!(Cli::try_parse_from(["app", "--config", &config_path_string]).is_err());
+/// # Ok(())
+/// # }
+/// ```
+pub struct EncryptedOf<T, K: KeyVar>(pub T, PhantomData<fn() -> K>);
+
+impl<T, K: KeyVar> EncryptedOf<T, K> {
+    /// Wrap an already decrypted document
+    pub fn new(data: T) -> Self {
+        EncryptedOf(data, PhantomData)
+    }
+
+    /// Returns the decrypted document
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: FromReader, K: KeyVar> FromReader for EncryptedOf<T, K> {
+    type Error = std::io::Error;
+    fn from_reader(reader: &mut impl std::io::BufRead) -> Result<Self, Self::Error> {
+        let mut payload = Vec::new();
+        reader.read_to_end(&mut payload)?;
+
+        let plaintext = decrypt::<K>(&payload).map_err(invalid_data)?;
+        let data = T::from_reader(&mut plaintext.as_slice()).map_err(invalid_data)?;
+        Ok(EncryptedOf::new(data))
+    }
+}
+
+/// Decrypt a nonce-prefixed `payload` with the key in `K`'s variable
+fn decrypt<K: KeyVar>(payload: &[u8]) -> Result<Vec<u8>, DecryptError> {
+    let hex_key = std::env::var(K::NAME).map_err(|source| DecryptError::MissingKey {
+        var: K::NAME,
+        source,
+    })?;
+    let key = decode_hex(hex_key.trim()).ok_or(DecryptError::InvalidKey { var: K::NAME })?;
+
+    if payload.len() < NONCE_LEN {
+        return Err(DecryptError::Truncated);
+    }
+    let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+    let nonce: [u8; NONCE_LEN] = nonce.try_into().expect("nonce was just split off");
+
+    let cipher = Aes256Gcm::new(&Key::<Aes256Gcm>::from(key));
+    cipher
+        .decrypt(&Nonce::from(nonce), ciphertext)
+        .map_err(|_| DecryptError::Decrypt)
+}
+
+/// Decode a 256-bit key from 64 hex digits
+fn decode_hex(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+
+    let mut key = [0; 32];
+    for (byte, digits) in key.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        let digits = std::str::from_utf8(digits).ok()?;
+        *byte = u8::from_str_radix(digits, 16).ok()?;
+    }
+    Some(key)
+}
+
+/// Why an [`EncryptedOf`] document could not be decrypted
+#[derive(Debug, thiserror::Error)]
+pub enum DecryptError {
+    /// The key's environment variable is unset or not Unicode
+    #[error("failed to read decryption key from ${var}: {source}")]
+    MissingKey {
+        /// The name of the environment variable
+        var: &'static str,
+        /// Why the environment variable could not be read
+        source: std::env::VarError,
+    },
+
+    /// The key's environment variable does not hold 64 hex digits
+    #[error("decryption key in ${var} must be 64 hex digits")]
+    InvalidKey {
+        /// The name of the environment variable
+        var: &'static str,
+    },
+
+    /// The file is too short to hold a nonce
+    #[error("encrypted document is truncated")]
+    Truncated,
+
+    /// The ciphertext did not authenticate with the key
+    #[error("failed to decrypt document: wrong key or corrupted file")]
+    Decrypt,
+}
+
+impl<T, K: KeyVar> std::ops::Deref for EncryptedOf<T, K> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T: Clone, K: KeyVar> Clone for EncryptedOf<T, K> {
+    fn clone(&self) -> Self {
+        EncryptedOf::new(self.0.clone())
+    }
+}
+
+impl<T: std::fmt::Debug, K: KeyVar> std::fmt::Debug for EncryptedOf<T, K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("EncryptedOf").field(&self.0).finish()
+    }
+}
+
+/// Trait for type markers naming the environment variable that holds the key
+pub trait KeyVar {
+    /// The name of the environment variable, e.g. `APP_CONFIG_KEY`
+    const NAME: &'static str;
+}
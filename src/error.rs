@@ -0,0 +1,425 @@
+//! Provides the [`Error`] returned when parsing an adapter fails
+
+use std::path::PathBuf;
+
+/// An error that occurred while parsing an adapter from an argument
+///
+/// Matching on the variant tells apart e.g. a missing file from a malformed
+/// document, without depending on the error messages.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap_adapters::prelude::*;
+///
+/// // Create a malformed config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json");
+/// std::fs::write(&config_path, "{not json")?;
+///
+/// let path = config_path.display().to_string();
+/// let result = path.parse::<PathTo<JsonOf<serde_json::Value>>>();
+/// assert!(matches!(result, Err(clap_adapters::Error::Json { .. })));
+///
+/// let missing = config_dir.path().join("missing.json").display().to_string();
+/// let result = missing.parse::<PathTo<JsonOf<serde_json::Value>>>();
+/// assert!(matches!(result, Err(clap_adapters::Error::Io { .. })));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// Reading a file or directory failed
+    #[error("failed to read '{}': {source}", path.display())]
+    Io {
+        /// The path that could not be read
+        path: PathBuf,
+        /// The underlying IO error
+        source: std::io::Error,
+    },
+
+    /// A file was not a valid Json document
+    #[error("failed to parse '{}' as Json: {source}", path.display())]
+    Json {
+        /// The path of the malformed file
+        path: PathBuf,
+        /// The underlying Json error
+        source: serde_json::Error,
+    },
+
+    /// A file was not a valid Toml document
+    #[cfg(feature = "toml")]
+    #[error("failed to parse '{}' as Toml: {source}", path.display())]
+    Toml {
+        /// The path of the malformed file
+        path: PathBuf,
+        /// The underlying Toml error
+        source: toml::de::Error,
+    },
+
+    /// A file was not a valid Yaml document
+    #[cfg(any(feature = "yaml", feature = "yaml-ng"))]
+    #[error("failed to parse '{}' as Yaml: {source}", path.display())]
+    Yaml {
+        /// The path of the malformed file
+        path: PathBuf,
+        /// The underlying Yaml error
+        source: crate::yaml::YamlError,
+    },
+
+    /// No format is registered for a file's extension
+    #[error("'{}' has no registered format for its extension", path.display())]
+    UnknownFormat {
+        /// The path of the unrecognized file
+        path: PathBuf,
+    },
+
+    /// A file could not be parsed by a format without a dedicated variant
+    #[error("failed to parse '{}': {source}", path.display())]
+    Format {
+        /// The path of the malformed file
+        path: PathBuf,
+        /// The error reported by the format
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// A document could not be serialized to be written to a file
+    #[error("failed to write '{}': {source}", path.display())]
+    Serialize {
+        /// The path that was being written
+        path: PathBuf,
+        /// The error reported by the format
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// A path that must point to a file points to something else
+    #[error("'{}' is not a file", path.display())]
+    NotAFile {
+        /// The offending path
+        path: PathBuf,
+    },
+
+    /// None of the candidate paths for an argument exist
+    #[error("none of the candidate paths exist: {}", display_paths(candidates))]
+    NoneExist {
+        /// The candidate paths that were tried, in order
+        candidates: Vec<PathBuf>,
+    },
+
+    /// Every adapter tried for an argument failed
+    #[error("no source could load '{input}': {}", display_errors(errors))]
+    AllFailed {
+        /// The argument as given by the user
+        input: String,
+        /// The error from each adapter, in the order they were tried
+        errors: Vec<Error>,
+    },
+
+    /// A path that must point to a directory points to something else
+    #[error("'{}' is not a directory", path.display())]
+    NotADirectory {
+        /// The offending path
+        path: PathBuf,
+    },
+
+    /// A file that must be private is accessible by its group or others
+    #[error(
+        "permissions {mode:04o} for '{}' are too open, it must not be accessible by others",
+        path.display()
+    )]
+    TooOpen {
+        /// The offending path
+        path: PathBuf,
+        /// The file's permission bits
+        mode: u32,
+    },
+
+    /// A `~` or environment variable in a path could not be expanded
+    #[error("failed to expand '{placeholder}' in path '{input}': {source}")]
+    Expand {
+        /// The path as given by the user
+        input: String,
+        /// The part of the path that could not be expanded, such as `$HOME`
+        placeholder: String,
+        /// Why the environment variable could not be read
+        source: std::env::VarError,
+    },
+
+    /// A `${` in a path was never closed
+    #[error("unterminated '${{' in path '{input}'")]
+    UnterminatedVariable {
+        /// The path as given by the user
+        input: String,
+    },
+
+    /// A credential name was not a plain file name
+    #[error("invalid credential name '{name}', expected a file name without '/'")]
+    InvalidCredential {
+        /// The credential name as given by the user
+        name: String,
+    },
+
+    /// A file descriptor argument was not of the form `fd:N`
+    #[error("invalid file descriptor '{input}', expected e.g. 'fd:3'")]
+    InvalidDescriptor {
+        /// The argument as given by the user
+        input: String,
+    },
+
+    /// An override was not of the form `key.path=value`
+    #[error("invalid override '{input}': {reason}")]
+    InvalidOverride {
+        /// The override as given by the user
+        input: String,
+        /// What is wrong with the override
+        reason: String,
+    },
+
+    /// An option appended to an argument was malformed
+    #[error("invalid option '{input}': {reason}")]
+    InvalidOption {
+        /// The option as given by the user
+        input: String,
+        /// What is wrong with the option
+        reason: String,
+    },
+
+    /// A cron expression was malformed or never matches
+    #[error("invalid cron expression '{expression}': {reason}")]
+    InvalidSchedule {
+        /// The cron expression
+        expression: String,
+        /// What is wrong with the expression
+        reason: String,
+    },
+
+    /// An option appended to an argument is not understood by any adapter
+    #[error("unknown option '{key}'")]
+    UnknownOption {
+        /// The key of the option
+        key: String,
+    },
+
+    /// A file is larger than the size allowed by its `max_size` option
+    #[error("'{}' is {size} bytes, larger than the maximum of {max} bytes", path.display())]
+    TooLarge {
+        /// The offending path
+        path: PathBuf,
+        /// The size of the file in bytes
+        size: u64,
+        /// The maximum allowed size in bytes
+        max: u64,
+    },
+
+    /// An override's value could not be converted to the requested type
+    #[error("invalid value for '{key}': {source}")]
+    OverrideValue {
+        /// The dotted key of the override
+        key: String,
+        /// The underlying Json error
+        source: serde_json::Error,
+    },
+
+    /// A selected profile does not exist in a document
+    #[error(
+        "'{}' has no profile '{profile}', available profiles: {}",
+        path.display(),
+        available.join(", ")
+    )]
+    UnknownProfile {
+        /// The path of the document
+        path: PathBuf,
+        /// The profile that was selected
+        profile: String,
+        /// The profiles in the document
+        available: Vec<String>,
+    },
+
+    /// Watching a file for changes could not be set up
+    #[cfg(any(doc, feature = "reloading", feature = "blocking"))]
+    #[error("failed to watch '{}': {source}", path.display())]
+    Watch {
+        /// The path that could not be watched
+        path: PathBuf,
+        /// The underlying notify error
+        source: notify::Error,
+    },
+
+    /// Listening for a Unix signal could not be set up
+    #[cfg(any(doc, feature = "signal"))]
+    #[error("failed to listen for signal: {source}")]
+    Signal {
+        /// The underlying IO error
+        source: std::io::Error,
+    },
+
+    /// Subscribing to an event stream failed
+    #[cfg(any(feature = "sse", feature = "mqtt"))]
+    #[error("failed to subscribe to '{url}': {source}")]
+    Subscribe {
+        /// The URL of the event stream
+        url: String,
+        /// The underlying IO error
+        source: std::io::Error,
+    },
+
+    /// A document was rejected by a validator registered on a reloading adapter
+    #[error("document was rejected by a validator: {source}")]
+    Rejected {
+        /// The error reported by the validator
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// An effective config could not be serialized for dumping
+    #[error("failed to serialize the effective config: {source}")]
+    Dump {
+        /// The underlying serialization error
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// An environment variable to be merged as a layer is not valid Unicode
+    #[error("environment variable '{}' is not valid Unicode", name.to_string_lossy())]
+    EnvNotUnicode {
+        /// The name of the variable
+        name: std::ffi::OsString,
+    },
+
+    /// The document merged from layered sources could not be converted
+    #[error("failed to convert the config merged from {}: {source}", layers.join(", "))]
+    Merged {
+        /// The names of the merged layers, from lowest to highest precedence
+        layers: Vec<String>,
+        /// The underlying conversion error
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// An error from a user-provided adapter
+    #[error(transparent)]
+    Custom(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl Error {
+    /// Classify an error produced while parsing the file at `path`
+    pub(crate) fn parse<E>(path: PathBuf, error: E) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        Self::parse_boxed(path, Box::new(error))
+    }
+
+    /// Classify a type-erased error produced while parsing the file at `path`
+    pub(crate) fn parse_boxed(
+        path: PathBuf,
+        error: Box<dyn std::error::Error + Send + Sync>,
+    ) -> Self {
+        // Formats without their own error type report parse errors as `InvalidData`
+        let error = match error.downcast::<std::io::Error>() {
+            Ok(source) if is_wrapped_parse_error(&source) => {
+                source.into_inner().expect("inner error was just checked")
+            }
+            Ok(source) => {
+                return Error::Io {
+                    path,
+                    source: *source,
+                }
+            }
+            Err(error) => error,
+        };
+
+        let error = match error.downcast::<serde_json::Error>() {
+            Ok(source) => {
+                return Error::Json {
+                    path,
+                    source: *source,
+                }
+            }
+            Err(error) => error,
+        };
+        #[cfg(any(feature = "yaml", feature = "yaml-ng"))]
+        let error = match error.downcast::<crate::yaml::YamlError>() {
+            Ok(source) => {
+                return Error::Yaml {
+                    path,
+                    source: *source,
+                }
+            }
+            Err(error) => error,
+        };
+        #[cfg(feature = "toml")]
+        let error = match error.downcast::<toml::de::Error>() {
+            Ok(source) => {
+                return Error::Toml {
+                    path,
+                    source: *source,
+                }
+            }
+            Err(error) => error,
+        };
+        Error::Format {
+            path,
+            source: error,
+        }
+    }
+
+    /// Classify an error produced while writing the file at `path`
+    pub(crate) fn serialize<E>(path: PathBuf, error: E) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        let error: Box<dyn std::error::Error + Send + Sync> = Box::new(error);
+        match error.downcast::<std::io::Error>() {
+            Ok(source) if is_wrapped_parse_error(&source) => Error::Serialize {
+                path,
+                source: source.into_inner().expect("inner error was just checked"),
+            },
+            Ok(source) => Error::Io {
+                path,
+                source: *source,
+            },
+            Err(source) => Error::Serialize { path, source },
+        }
+    }
+
+    /// Wrap an IO error that occurred while accessing `path`
+    pub(crate) fn io(path: impl Into<PathBuf>, source: std::io::Error) -> Self {
+        Error::Io {
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+/// Formats `paths` as a comma-separated list
+fn display_paths(paths: &[PathBuf]) -> String {
+    let paths: Vec<String> = paths
+        .iter()
+        .map(|path| format!("'{}'", path.display()))
+        .collect();
+    paths.join(", ")
+}
+
+/// Formats `errors` as a semicolon-separated list
+fn display_errors(errors: &[Error]) -> String {
+    let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+    errors.join("; ")
+}
+
+/// Whether an IO error merely wraps an error from parsing the data that was read
+fn is_wrapped_parse_error(error: &std::io::Error) -> bool {
+    error.kind() == std::io::ErrorKind::InvalidData && error.get_ref().is_some()
+}
+
+#[cfg(feature = "anyhow")]
+impl From<anyhow::Error> for Error {
+    fn from(error: anyhow::Error) -> Self {
+        Error::Custom(error.into())
+    }
+}
+
+impl From<std::convert::Infallible> for Error {
+    fn from(error: std::convert::Infallible) -> Self {
+        match error {}
+    }
+}
@@ -0,0 +1,301 @@
+use std::path::{Path, PathBuf};
+
+use crate::Error;
+
+/// An adapter that checks the user-given path exists, without reading it
+///
+/// The path may point to a file, a directory, or anything else. Use
+/// [`ExistingFile`] to require a readable regular file instead.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to anything that exists
+///     #[clap(long)]
+///     input: ExistingPath,
+/// }
+///
+/// let input_dir = tempfile::tempdir()?;
+/// let input_dir_string = input_dir.path().display().to_string();
+/// let cli = Cli::parse_from(["app", "--input", &input_dir_string]);
+/// assert!(cli.input.metadata.is_dir());
+///
+/// // A missing path is rejected
+/// let missing = input_dir.path().join("missing").display().to_string();
+/// assert!(Cli::try_parse_from(["app", "--input", &missing]).is_err());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExistingPath {
+    /// The path given as an argument by the user
+    pub path: PathBuf,
+
+    /// The metadata of the path at the time of parsing, following symlinks
+    pub metadata: std::fs::Metadata,
+}
+
+impl std::str::FromStr for ExistingPath {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let path = PathBuf::from(s);
+        let metadata = std::fs::metadata(&path).map_err(|error| Error::io(&path, error))?;
+        Ok(ExistingPath { path, metadata })
+    }
+}
+
+impl std::ops::Deref for ExistingPath {
+    type Target = Path;
+    fn deref(&self) -> &Self::Target {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for ExistingPath {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// An adapter that checks the user-given path is a readable regular file,
+/// without reading its contents
+///
+/// This suits applications that stream the file later, but still want clap
+/// to reject bad paths up front. The file is opened to check it's readable,
+/// then closed again right away.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a large input file, which is streamed later
+///     #[clap(long)]
+///     input: ExistingFile,
+/// }
+///
+/// // Create an input file in a temporary directory
+/// let input_dir = tempfile::tempdir()?;
+/// let input_path = input_dir.path().join("input.csv");
+/// let input_path_string = input_path.display().to_string();
+/// std::fs::write(&input_path, "a,b\n1,2\n")?;
+///
+/// let cli = Cli::parse_from(["app", "--input", &input_path_string]);
+/// assert_eq!(cli.input.path, input_path);
+/// assert_eq!(cli.input.size(), 8);
+///
+/// // A directory is rejected
+/// let input_dir_string = input_dir.path().display().to_string();
+/// assert!(Cli::try_parse_from(["app", "--input", &input_dir_string]).is_err());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExistingFile {
+    /// The path given as an argument by the user
+    pub path: PathBuf,
+
+    /// The metadata of the file at the time of parsing, following symlinks
+    pub metadata: std::fs::Metadata,
+}
+
+impl ExistingFile {
+    /// Returns the size of the file in bytes at the time of parsing
+    pub fn size(&self) -> u64 {
+        self.metadata.len()
+    }
+}
+
+impl std::str::FromStr for ExistingFile {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let path = PathBuf::from(s);
+        let metadata = std::fs::metadata(&path).map_err(|error| Error::io(&path, error))?;
+        if !metadata.is_file() {
+            return Err(Error::NotAFile { path });
+        }
+
+        std::fs::File::open(&path).map_err(|error| Error::io(&path, error))?;
+        Ok(ExistingFile { path, metadata })
+    }
+}
+
+impl std::ops::Deref for ExistingFile {
+    type Target = Path;
+    fn deref(&self) -> &Self::Target {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for ExistingFile {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// An adapter that checks the user-given path is an existing directory
+///
+/// By default, the directory must be readable. Use `DirPath<Writable>` to
+/// additionally require that files can be created in it, e.g. for a
+/// `--data-dir` flag.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Directory to read input files from
+///     #[clap(long)]
+///     input_dir: DirPath,
+///
+///     /// Directory to store data in
+///     #[clap(long)]
+///     data_dir: DirPath<Writable>,
+/// }
+///
+/// let data_dir = tempfile::tempdir()?;
+/// let data_dir_string = data_dir.path().display().to_string();
+/// let cli = Cli::parse_from([
+///     "app",
+///     "--input-dir",
+///     &data_dir_string,
+///     "--data-dir",
+///     &data_dir_string,
+/// ]);
+/// assert_eq!(cli.data_dir.path, data_dir.path());
+///
+/// // A file is rejected
+/// let file_path = data_dir.path().join("file.txt");
+/// std::fs::write(&file_path, "not a directory")?;
+/// let file_path_string = file_path.display().to_string();
+/// let result = Cli::try_parse_from([
+///     "app",
+///     "--input-dir",
+///     &file_path_string,
+///     "--data-dir",
+///     &data_dir_string,
+/// ]);
+/// assert!(result.is_err());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DirPath<A: DirAccess = Readable> {
+    /// The path given as an argument by the user
+    pub path: PathBuf,
+
+    /// The metadata of the directory at the time of parsing, following symlinks
+    pub metadata: std::fs::Metadata,
+
+    access: std::marker::PhantomData<A>,
+}
+
+impl<A: DirAccess> std::str::FromStr for DirPath<A> {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let path = PathBuf::from(s);
+        let metadata = std::fs::metadata(&path).map_err(|error| Error::io(&path, error))?;
+        if !metadata.is_dir() {
+            return Err(Error::NotADirectory { path });
+        }
+
+        std::fs::read_dir(&path).map_err(|error| Error::io(&path, error))?;
+        if A::WRITABLE {
+            ensure_writable(&path)?;
+        }
+
+        let item = DirPath {
+            path,
+            metadata,
+            access: std::marker::PhantomData,
+        };
+        Ok(item)
+    }
+}
+
+impl<A: DirAccess> std::ops::Deref for DirPath<A> {
+    type Target = Path;
+    fn deref(&self) -> &Self::Target {
+        &self.path
+    }
+}
+
+impl<A: DirAccess> AsRef<Path> for DirPath<A> {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Checks that files can be created in `dir`, without creating one
+///
+/// This asks the OS whether the process may write to and search the
+/// directory, with its effective user and group, including whether the file
+/// system is mounted read-only.
+#[cfg(unix)]
+pub(crate) fn ensure_writable(dir: &Path) -> Result<(), Error> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_dir = std::ffi::CString::new(dir.as_os_str().as_bytes())
+        .map_err(|error| Error::io(dir, error.into()))?;
+    // SAFETY: `c_dir` is a valid, NUL-terminated path that outlives the call
+    let result = unsafe {
+        libc::faccessat(
+            libc::AT_FDCWD,
+            c_dir.as_ptr(),
+            libc::W_OK | libc::X_OK,
+            libc::AT_EACCESS,
+        )
+    };
+    if result != 0 {
+        return Err(Error::io(dir, std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+/// Checks that files can be created in `dir`, without creating one
+///
+/// Only the read-only attribute is checked here; access control lists are
+/// left for the eventual write to enforce.
+#[cfg(not(unix))]
+pub(crate) fn ensure_writable(dir: &Path) -> Result<(), Error> {
+    let metadata = std::fs::metadata(dir).map_err(|error| Error::io(dir, error))?;
+    if metadata.permissions().readonly() {
+        let error = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        return Err(Error::io(dir, error));
+    }
+    Ok(())
+}
+
+/// Trait for type markers configuring the access [`DirPath`] requires
+pub trait DirAccess {
+    /// Whether files must be creatable in the directory
+    const WRITABLE: bool;
+}
+
+/// Requires that the directory can be listed
+#[derive(Debug, Clone, Copy)]
+pub enum Readable {}
+impl DirAccess for Readable {
+    const WRITABLE: bool = false;
+}
+
+/// Requires that the directory can be listed and files can be created in it
+#[derive(Debug, Clone, Copy)]
+pub enum Writable {}
+impl DirAccess for Writable {
+    const WRITABLE: bool = true;
+}
@@ -0,0 +1,129 @@
+use crate::{options::AdapterOptions, traits::FromOptions, Error};
+
+/// An adapter that performs shell-style expansion on the user-given path
+///
+/// Before handing the argument to the inner adapter, `Expand<T>` expands:
+///
+/// - A leading `~` (alone or followed by a path separator) to the user's
+///   home directory, taken from `$HOME` (or `%USERPROFILE%` on Windows)
+/// - `$VAR` and `${VAR}` to the value of the environment variable `VAR`
+///
+/// Referencing an undefined environment variable is a parse error. A `$`
+/// that isn't followed by a variable name is left as-is.
+///
+/// This is useful because arguments given via `default_value` or
+/// environment variables never pass through a shell, so `~` and `$VAR`
+/// would otherwise be used verbatim.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a config file, supporting `~` and `$VAR`
+///     #[clap(long)]
+///     config: Expand<PathTo<JsonOf<serde_json::Value>>>,
+/// }
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// std::fs::write(config_dir.path().join("config.json"), r#"{"hello":"world"}"#)?;
+///
+/// // Point an environment variable at the config directory
+/// std::env::set_var("MY_APP_CONFIG_DIR", config_dir.path());
+///
+/// // Parse our CLI, referencing the variable in the path
+/// let cli = Cli::parse_from(["app", "--config", "${MY_APP_CONFIG_DIR}/config.json"]);
+/// assert_eq!(cli.config.0.path, config_dir.path().join("config.json"));
+/// assert_eq!(cli.config.0.data.0, serde_json::json!({"hello":"world"}));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Expand<T>(pub T);
+
+impl<T> std::str::FromStr for Expand<T>
+where
+    T: std::str::FromStr,
+    T::Err: Into<Error>,
+{
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expanded = expand(s)?;
+        let inner = T::from_str(&expanded).map_err(Into::into)?;
+        Ok(Expand(inner))
+    }
+}
+
+impl<T: FromOptions> FromOptions for Expand<T> {
+    fn from_options(input: &str, options: &mut AdapterOptions) -> Result<Self, Error> {
+        let expanded = expand(input)?;
+        T::from_options(&expanded, options).map(Expand)
+    }
+
+    fn takes_option(key: &str) -> bool {
+        T::takes_option(key)
+    }
+}
+
+/// Expands a leading tilde and any environment variables in `input`
+pub(crate) fn expand(input: &str) -> Result<String, Error> {
+    let input = expand_tilde(input)?;
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input.as_str();
+
+    while let Some(index) = rest.find('$') {
+        output.push_str(&rest[..index]);
+        rest = &rest[index + 1..];
+
+        let (name, remainder) = if let Some(braced) = rest.strip_prefix('{') {
+            let Some(end) = braced.find('}') else {
+                return Err(Error::UnterminatedVariable { input });
+            };
+            (&braced[..end], &braced[end + 1..])
+        } else {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            (&rest[..end], &rest[end..])
+        };
+
+        if name.is_empty() {
+            output.push('$');
+            continue;
+        }
+
+        let value = std::env::var(name).map_err(|source| Error::Expand {
+            input: input.clone(),
+            placeholder: format!("${name}"),
+            source,
+        })?;
+        output.push_str(&value);
+        rest = remainder;
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// Replaces a leading `~` with the user's home directory
+fn expand_tilde(input: &str) -> Result<String, Error> {
+    let Some(rest) = input.strip_prefix('~') else {
+        return Ok(input.to_string());
+    };
+    if !(rest.is_empty() || rest.starts_with(std::path::is_separator)) {
+        return Ok(input.to_string());
+    }
+
+    let home_var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    let home = std::env::var(home_var).map_err(|source| Error::Expand {
+        input: input.to_string(),
+        placeholder: "~".to_string(),
+        source,
+    })?;
+    Ok(format!("{home}{rest}"))
+}
@@ -0,0 +1,105 @@
+use std::path::PathBuf;
+
+use crate::{parser::AdapterParser, traits::FromPath, Error};
+
+/// An adapter for loading the contents of an inherited file descriptor
+///
+/// `FdTo<T>` accepts arguments of the form `fd:N` and parses the file open
+/// at descriptor `N` via [`FromPath`], e.g. for secrets passed by a service
+/// manager such as systemd without ever touching a path on disk. The
+/// descriptor is reopened through `/dev/fd/N`, so it is left open and
+/// regular files are read from their start.
+///
+/// `FdTo<T>` serializes as just its data, like [`PathTo`](crate::PathTo).
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use std::os::fd::AsRawFd;
+///
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// A Json credential passed as an open file descriptor
+///     #[clap(long)]
+///     credential: FdTo<JsonOf<serde_json::Value>>,
+/// }
+///
+/// // Open a credential file, as a parent process would before exec
+/// let secret_dir = tempfile::tempdir()?;
+/// let secret_path = secret_dir.path().join("credential.json");
+/// std::fs::write(&secret_path, r#"{"token":"hunter2"}"#)?;
+/// let secret = std::fs::File::open(&secret_path)?;
+///
+/// let argument = format!("fd:{}", secret.as_raw_fd());
+/// let cli = Cli::parse_from(["app", "--credential", &argument]);
+/// assert_eq!(cli.credential["token"], "hunter2");
+///
+/// // Anything but `fd:N` is rejected
+/// assert!(Cli::try_parse_from(["app", "--credential", "3"]).is_err());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize)]
+#[serde(transparent)]
+pub struct FdTo<T> {
+    /// The file descriptor given as an argument by the user
+    #[serde(skip)]
+    pub fd: i32,
+
+    /// The data extracted from the file descriptor
+    pub data: T,
+}
+
+impl<T> std::ops::Deref for FdTo<T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl<T> std::ops::DerefMut for FdTo<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.data
+    }
+}
+
+impl<T: FromPath> FdTo<T> {
+    /// Load and parse the file open at descriptor `fd`
+    pub fn load(fd: i32) -> Result<Self, Error> {
+        let data = T::from_path(&Self::path_of(fd))?;
+        Ok(FdTo { fd, data })
+    }
+
+    /// The path through which descriptor `fd` is reopened
+    fn path_of(fd: i32) -> PathBuf {
+        PathBuf::from(format!("/dev/fd/{fd}"))
+    }
+}
+
+impl<T: FromPath> std::str::FromStr for FdTo<T> {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fd = s
+            .strip_prefix("fd:")
+            .and_then(|fd| fd.parse::<i32>().ok())
+            .filter(|fd| *fd >= 0)
+            .ok_or_else(|| Error::InvalidDescriptor {
+                input: s.to_string(),
+            })?;
+        Self::load(fd)
+    }
+}
+
+impl<T> clap::builder::ValueParserFactory for FdTo<T>
+where
+    T: FromPath + Clone + Send + Sync + 'static,
+{
+    type Parser = AdapterParser<Self>;
+    fn value_parser() -> Self::Parser {
+        AdapterParser::new()
+    }
+}
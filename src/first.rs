@@ -0,0 +1,260 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::Error;
+
+/// An adapter that uses the first existing path from a list of candidates
+///
+/// The argument is a list of paths separated like `$PATH` (by `:` on Unix,
+/// or `;` on Windows). Each candidate has `~` and environment variables
+/// expanded as in [`Expand`], and the first one that exists is handed to the
+/// inner adapter. Candidates referencing undefined variables are skipped.
+///
+/// Combined with clap's `default_value`, this makes an argument optional,
+/// falling back to conventional locations such as the working directory, the
+/// user's config directory, then a system-wide path. The path that was used
+/// is recorded in [`FirstExisting::path`].
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Json config, defaulting to the first one found
+///     #[clap(long, default_value = "./app.json:~/.config/app/app.json:/etc/app/app.json")]
+///     config: FirstExisting<PathTo<JsonOf<serde_json::Value>>>,
+/// }
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("app.json");
+/// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+///
+/// // Only the second candidate exists
+/// let missing_path = config_dir.path().join("missing.json");
+/// let candidates = std::env::join_paths([&missing_path, &config_path])?;
+/// let candidates = candidates.to_str().unwrap();
+///
+/// let cli = Cli::parse_from(["app", "--config", candidates]);
+/// assert_eq!(cli.config.path, config_path);
+/// assert_eq!(cli.config.data.data.0, serde_json::json!({"hello":"world"}));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Expand`]: crate::Expand
+#[derive(Debug, Clone)]
+pub struct FirstExisting<T> {
+    /// The candidate that was used
+    pub path: PathBuf,
+
+    /// All candidates given by the user, in the order they were tried
+    pub candidates: Vec<PathBuf>,
+
+    /// The inner adapter, parsed from the candidate that was used
+    pub data: T,
+}
+
+impl<T> FromStr for FirstExisting<T>
+where
+    T: FromStr,
+    T::Err: Into<Error>,
+{
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let candidates: Vec<PathBuf> = std::env::split_paths(s).collect();
+        let found = candidates.iter().find_map(|candidate| {
+            let expanded = crate::expand::expand(candidate.to_str()?).ok()?;
+            let expanded = PathBuf::from(expanded);
+            expanded.exists().then_some(expanded)
+        });
+
+        let Some(path) = found else {
+            return Err(Error::NoneExist { candidates });
+        };
+
+        let data = T::from_str(&path.to_string_lossy()).map_err(Into::into)?;
+        let item = FirstExisting {
+            path,
+            candidates,
+            data,
+        };
+        Ok(item)
+    }
+}
+
+/// An adapter that tries several source adapters in order for the same argument
+///
+/// The argument is given to each adapter of the tuple `S` in turn, e.g.
+/// `FirstOf<(A, B)>` tries `A` and falls back to `B` if `A` fails to parse.
+/// The adapter that succeeded is kept in its slot of [`FirstOf::sources`],
+/// with its position in [`FirstOf::index`]. Failures of the adapters tried
+/// before it are logged as warnings, and if every adapter fails, all of
+/// their errors are reported.
+///
+/// Tuples of two to four adapters are supported. When the adapters
+/// dereference to the same type, e.g. `FdTo<T>` and `PathTo<T>`, so does
+/// `FirstOf`.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Json or Toml config
+///     #[clap(long)]
+///     config: FirstOf<(PathTo<JsonOf<serde_json::Value>>, PathTo<TomlOf<serde_json::Value>>)>,
+/// }
+///
+/// // Create a Toml config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, r#"hello = "world""#)?;
+///
+/// // Parsing as Json fails, so the Toml adapter is used
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+/// assert_eq!(cli.config.index, 1);
+/// let (_, Some(toml)) = &cli.config.sources else {
+///     panic!("the Toml adapter should have been used");
+/// };
+/// assert_eq!(toml.data.0, serde_json::json!({"hello":"world"}));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Accepting either an inherited file descriptor or a path on Unix:
+///
+/// ```
+/// # #[cfg(unix)]
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// A Json config given as `fd:N` or as a path
+///     #[clap(long)]
+///     config: FirstOf<(FdTo<JsonOf<serde_json::Value>>, PathTo<JsonOf<serde_json::Value>>)>,
+/// }
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+///
+/// // Both adapters dereference to the document
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+/// assert_eq!(cli.config.index, 1);
+/// assert_eq!(cli.config.0, serde_json::json!({"hello":"world"}));
+/// # Ok(())
+/// # }
+/// # #[cfg(not(unix))]
+/// # fn main() {}
+/// ```
+pub struct FirstOf<S: Sources> {
+    /// The position in `S` of the adapter that succeeded
+    pub index: usize,
+
+    /// A slot for each adapter in `S`, holding only the one that succeeded
+    pub sources: S::Slots,
+}
+
+/// A tuple of adapters that [`FirstOf`] tries in order
+pub trait Sources {
+    /// A tuple with an `Option` for each adapter
+    type Slots: Clone + std::fmt::Debug;
+
+    /// Parse `input` with the first adapter that succeeds, or return each error
+    fn first(input: &str) -> Result<(usize, Self::Slots), Vec<Error>>;
+}
+
+impl<S: Sources> Clone for FirstOf<S> {
+    fn clone(&self) -> Self {
+        FirstOf {
+            index: self.index,
+            sources: self.sources.clone(),
+        }
+    }
+}
+
+impl<S: Sources> std::fmt::Debug for FirstOf<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FirstOf")
+            .field("index", &self.index)
+            .field("sources", &self.sources)
+            .finish()
+    }
+}
+
+impl<S: Sources> FromStr for FirstOf<S> {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (index, sources) = S::first(s).map_err(|errors| Error::AllFailed {
+            input: s.to_string(),
+            errors,
+        })?;
+        Ok(FirstOf { index, sources })
+    }
+}
+
+macro_rules! impl_sources {
+    ($($source:ident: $index:tt),+) => {
+        impl<$($source),+> Sources for ($($source,)+)
+        where
+            $($source: FromStr + Clone + std::fmt::Debug, $source::Err: Into<Error>,)+
+        {
+            type Slots = ($(Option<$source>,)+);
+
+            fn first(input: &str) -> Result<(usize, Self::Slots), Vec<Error>> {
+                let mut errors: Vec<Error> = Vec::new();
+                $(
+                    match $source::from_str(input) {
+                        Ok(source) => {
+                            for error in &errors {
+                                tracing::warn!(
+                                    error = format!("{error:#}"),
+                                    "Source failed to load, used a fallback"
+                                );
+                            }
+                            let mut slots = Self::Slots::default();
+                            slots.$index = Some(source);
+                            return Ok(($index, slots));
+                        }
+                        Err(error) => errors.push(error.into()),
+                    }
+                )+
+                Err(errors)
+            }
+        }
+
+        impl<U: ?Sized, $($source),+> std::ops::Deref for FirstOf<($($source,)+)>
+        where
+            ($($source,)+): Sources<Slots = ($(Option<$source>,)+)>,
+            $($source: std::ops::Deref<Target = U>,)+
+        {
+            type Target = U;
+            fn deref(&self) -> &Self::Target {
+                $(
+                    if let Some(source) = &self.sources.$index {
+                        return source;
+                    }
+                )+
+                unreachable!("the adapter that succeeded is always kept")
+            }
+        }
+    };
+}
+
+impl_sources!(A: 0, B: 1);
+impl_sources!(A: 0, B: 1, C: 2);
+impl_sources!(A: 0, B: 1, C: 2, D: 3);
@@ -1,25 +1,537 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
-use crate::traits::FromReader;
+use crate::{
+    options::AdapterOptions,
+    parser::AdapterParser,
+    traits::{FromOptions, FromPath, Reload, ToWriter},
+    Error,
+};
 
 /// An adapter for automatically loading the contents of a file path
-#[derive(Debug, Clone)]
+///
+/// `PathTo<T>` serializes as just its data, e.g. to log the effective config.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Toml config
+///     #[clap(long)]
+///     config: PathTo<TomlOf<serde_json::Value>>,
+/// }
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.toml");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, r#"hello = "world""#)?;
+///
+/// // Re-emit the Toml config as Json
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+/// assert_eq!(serde_json::to_string(&cli.config)?, r#"{"hello":"world"}"#);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Windows paths
+///
+/// On Windows, verbatim paths (`\\?\C:\config.toml`), UNC shares
+/// (`\\server\share\config.toml`) and drive-relative paths (`C:config.toml`,
+/// relative to the current directory of drive `C:`) are all accepted, and
+/// the `path` field keeps the path exactly as the user gave it.
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     #[clap(long)]
+///     config: PathTo<JsonOf<serde_json::Value>>,
+/// }
+///
+/// let config_dir = tempfile::tempdir()?;
+/// std::fs::write(config_dir.path().join("config.json"), r#"{"port": 8080}"#)?;
+///
+/// // On Windows, canonical paths are verbatim paths
+/// let config_path = std::fs::canonicalize(config_dir.path())?.join("config.json");
+/// #[cfg(windows)]
+/// assert!(config_path.to_string_lossy().starts_with(r"\\?\"));
+///
+/// let config_path_string = config_path.display().to_string();
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+/// assert_eq!(cli.config.path, config_path);
+/// assert_eq!(cli.config["port"], 8080);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(transparent)]
 pub struct PathTo<T> {
     /// The path given as an argument by the user
+    #[serde(skip)]
     pub path: PathBuf,
 
     /// The data extracted from the file at the path
     pub data: T,
+
+    /// When and from which version of the file the data was loaded
+    #[serde(skip)]
+    pub metadata: LoadMetadata,
 }
 
-impl<T: FromReader> std::str::FromStr for PathTo<T> {
-    type Err = anyhow::Error;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let path = PathBuf::from(s);
-        let file = std::fs::File::open(&path)?;
-        let mut reader = std::io::BufReader::new(file);
-        let data = T::from_reader(&mut reader)?;
-        let item = PathTo { path, data };
+/// Documents compare and hash by path and data, regardless of when they were loaded
+impl<T: PartialEq> PartialEq for PathTo<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path && self.data == other.data
+    }
+}
+
+impl<T: Eq> Eq for PathTo<T> {}
+
+impl<T: std::hash::Hash> std::hash::Hash for PathTo<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.path.hash(state);
+        self.data.hash(state);
+    }
+}
+
+/// When a file was loaded, and the modification time and length it had then
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap_adapters::prelude::*;
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json");
+/// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+///
+/// let config = PathTo::<JsonOf<serde_json::Value>>::load(&config_path)?;
+/// assert_eq!(config.metadata.len, Some(17));
+/// assert!(config.metadata.modified.is_some());
+/// println!("config loaded {:?} ago from {}", config.metadata.age(), config.path.display());
+///
+/// // The file changed since it was loaded
+/// std::fs::write(&config_path, r#"{"hello":"there!"}"#)?;
+/// assert!(config.is_stale());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LoadMetadata {
+    /// When the file was loaded
+    pub loaded_at: SystemTime,
+
+    /// The file's modification time, if the platform reports one
+    pub modified: Option<SystemTime>,
+
+    /// The file's length in bytes, if its metadata could be read
+    pub len: Option<u64>,
+
+    /// A hash of the bytes the data was parsed from, see [`FromPath::from_path_hashed`]
+    pub hash: Option<u64>,
+}
+
+impl LoadMetadata {
+    /// Record the metadata of the file at `path` as of now
+    ///
+    /// This is read before the file's contents, so that a write racing the
+    /// load makes the document look stale rather than fresh.
+    pub fn read(path: &Path) -> Self {
+        let metadata = std::fs::metadata(path).ok();
+        LoadMetadata {
+            loaded_at: SystemTime::now(),
+            modified: metadata
+                .as_ref()
+                .and_then(|metadata| metadata.modified().ok()),
+            len: metadata.map(|metadata| metadata.len()),
+            hash: None,
+        }
+    }
+
+    /// Metadata for data that was not read from a file
+    pub fn now() -> Self {
+        LoadMetadata {
+            loaded_at: SystemTime::now(),
+            modified: None,
+            len: None,
+            hash: None,
+        }
+    }
+
+    /// How long ago the file was loaded
+    pub fn age(&self) -> Duration {
+        self.loaded_at.elapsed().unwrap_or_default()
+    }
+}
+
+/// Dereferences to the loaded data, so its fields and methods are directly accessible
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Clone, serde::Deserialize)]
+/// struct Config {
+///     workers: u32,
+/// }
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Json config
+///     #[clap(long)]
+///     config: PathTo<JsonOf<Config>>,
+/// }
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, r#"{"workers": 4}"#)?;
+///
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+/// assert_eq!(cli.config.workers, 4);
+/// # Ok(())
+/// # }
+/// ```
+impl<T> std::ops::Deref for PathTo<T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl<T> std::ops::DerefMut for PathTo<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.data
+    }
+}
+
+impl<T> AsRef<T> for PathTo<T> {
+    fn as_ref(&self) -> &T {
+        &self.data
+    }
+}
+
+impl<T> PathTo<T> {
+    /// Assemble a document from a path and already loaded data
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use clap_adapters::prelude::*;
+    ///
+    /// let config = PathTo::from_parts("config.json", JsonOf(serde_json::json!({"hello":"world"})));
+    /// assert_eq!(config.path().to_str(), Some("config.json"));
+    /// ```
+    pub fn from_parts(path: impl Into<PathBuf>, data: T) -> Self {
+        PathTo {
+            path: path.into(),
+            data,
+            metadata: LoadMetadata::now(),
+        }
+    }
+
+    /// Returns the path given as an argument by the user
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap_adapters::prelude::*;
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+    ///
+    /// let config: PathTo<JsonOf<serde_json::Value>> = config_path.display().to_string().parse()?;
+    /// assert_eq!(config.path(), config_path);
+    /// assert_eq!(config.into_inner().into_inner(), serde_json::json!({"hello":"world"}));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Resolve a path referenced by the config against the config's directory
+    ///
+    /// Configs often reference other files, such as certificates or includes,
+    /// by paths meant relative to the config file rather than the current
+    /// directory. Absolute paths are returned unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap_adapters::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, serde::Deserialize)]
+    /// struct Config {
+    ///     cert: std::path::PathBuf,
+    /// }
+    ///
+    /// // Create a config file referencing a certificate next to it
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// std::fs::write(&config_path, r#"{"cert": "certs/server.pem"}"#)?;
+    ///
+    /// let config = PathTo::<JsonOf<Config>>::load(&config_path)?;
+    /// let cert_path = config.resolve(&config.cert);
+    /// assert_eq!(cert_path, config_dir.path().join("certs/server.pem"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resolve(&self, relative: impl AsRef<Path>) -> PathBuf {
+        let relative = relative.as_ref();
+        match self.path.parent() {
+            Some(dir) if relative.is_relative() => dir.join(relative),
+            _ => relative.to_path_buf(),
+        }
+    }
+
+    /// Returns true if the file's modification time or length changed since it was loaded
+    ///
+    /// A file that can no longer be read is stale as well.
+    pub fn is_stale(&self) -> bool {
+        let current = LoadMetadata::read(&self.path);
+        current.len.is_none()
+            || current.len != self.metadata.len
+            || current.modified != self.metadata.modified
+    }
+
+    /// Returns the loaded data, discarding the path
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+}
+
+impl<T: FromPath> PathTo<T> {
+    /// Load and parse the file at `path`
+    ///
+    /// This is what parsing the adapter from an argument does, for building
+    /// values outside of clap (e.g. in tests).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap_adapters::prelude::*;
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+    ///
+    /// let config = PathTo::<JsonOf<serde_json::Value>>::load(&config_path)?;
+    /// assert_eq!(config.data.0, serde_json::json!({"hello":"world"}));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+        let mut metadata = LoadMetadata::read(&path);
+        let (data, hash) = T::from_path_hashed(&path, &mut AdapterOptions::default())?;
+        metadata.hash = hash;
+        let item = PathTo {
+            path,
+            data,
+            metadata,
+        };
+        Ok(item)
+    }
+}
+
+impl<T: ToWriter> PathTo<T> {
+    /// Write the data back to the file at the path it was loaded from
+    ///
+    /// The file is replaced atomically, see [`PathTo::save_as`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap::Parser;
+    /// use clap_adapters::prelude::*;
+    ///
+    /// #[derive(Debug, Parser)]
+    /// struct Cli {
+    ///     /// Path to a config file of arbitrary Json
+    ///     #[clap(long)]
+    ///     config: PathTo<JsonOf<serde_json::Value>>,
+    /// }
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// let config_path_string = config_path.display().to_string();
+    /// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+    ///
+    /// // Update the config and persist it
+    /// let mut cli = Cli::parse_from(["app", "--config", &config_path_string]);
+    /// cli.config.data.0["token"] = serde_json::json!("hunter2");
+    /// cli.config.save()?;
+    ///
+    /// let saved: PathTo<JsonOf<serde_json::Value>> = config_path_string.parse()?;
+    /// assert_eq!(saved.data.0, serde_json::json!({"hello":"world","token":"hunter2"}));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn save(&self) -> Result<(), Error> {
+        self.save_as(&self.path)
+    }
+
+    /// Write the data to the file at `path`, replacing it atomically
+    ///
+    /// The data is written and synced to a temporary file in the same
+    /// directory, which is then renamed over `path`. Readers, such as a
+    /// `Reloading` adapter in another process, thus never observe a partially
+    /// written file, and `path` is left untouched if writing fails. An
+    /// existing file keeps its permissions.
+    pub fn save_as(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        crate::atomic::write(path, |writer| {
+            self.data
+                .to_writer(writer)
+                .map_err(|error| Error::serialize(path.to_path_buf(), error))
+        })
+    }
+}
+
+#[cfg(any(doc, feature = "async"))]
+impl<T: crate::traits::AsyncFromReader> PathTo<T> {
+    /// Asynchronously load and parse the file at `path`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap_adapters::prelude::*;
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+    ///
+    /// let runtime = tokio::runtime::Runtime::new()?;
+    /// runtime.block_on(async {
+    ///     let config = PathTo::<JsonOf<serde_json::Value>>::load_async(&config_path).await?;
+    ///     assert_eq!(config.data.0, serde_json::json!({"hello":"world"}));
+    ///
+    ///     // Load a fresh copy from the same path
+    ///     let config = config.reload_async().await?;
+    ///     assert_eq!(config.data.0, serde_json::json!({"hello":"world"}));
+    ///     anyhow::Ok(())
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn load_async(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+        let metadata = tokio::fs::metadata(&path).await.ok();
+        let metadata = LoadMetadata {
+            loaded_at: SystemTime::now(),
+            modified: metadata
+                .as_ref()
+                .and_then(|metadata| metadata.modified().ok()),
+            len: metadata.map(|metadata| metadata.len()),
+            hash: None,
+        };
+        let file = tokio::fs::File::open(&path)
+            .await
+            .map_err(|error| Error::io(&path, error))?;
+        let mut reader = tokio::io::BufReader::new(file);
+        let data = T::from_async_reader(&mut reader)
+            .await
+            .map_err(|error| Error::parse(path.clone(), error))?;
+        let item = PathTo {
+            path,
+            data,
+            metadata,
+        };
         Ok(item)
     }
+
+    /// Asynchronously load a fresh copy of the file from the same path
+    pub async fn reload_async(&self) -> Result<Self, Error> {
+        Self::load_async(self.path.clone()).await
+    }
+}
+
+impl<T: FromPath> std::str::FromStr for PathTo<T> {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::load(PathBuf::from(s))
+    }
+}
+
+impl<T: FromPath> FromOptions for PathTo<T> {
+    /// Takes `max_size`, refusing larger files, and gives the rest to `T`
+    fn from_options(input: &str, options: &mut AdapterOptions) -> Result<Self, Error> {
+        let path = PathBuf::from(input);
+        if let Some(max) = options.take_size("max_size")? {
+            let metadata = std::fs::metadata(&path).map_err(|error| Error::io(&path, error))?;
+            if metadata.len() > max {
+                return Err(Error::TooLarge {
+                    path,
+                    size: metadata.len(),
+                    max,
+                });
+            }
+        }
+        let mut metadata = LoadMetadata::read(&path);
+        let (data, hash) = T::from_path_hashed(&path, options)?;
+        metadata.hash = hash;
+        Ok(PathTo {
+            path,
+            data,
+            metadata,
+        })
+    }
+
+    fn takes_option(key: &str) -> bool {
+        key == "max_size" || T::takes_option(key)
+    }
+}
+
+impl<T> clap::builder::ValueParserFactory for PathTo<T>
+where
+    T: FromPath + Clone + Send + Sync + 'static,
+{
+    type Parser = AdapterParser<Self>;
+    fn value_parser() -> Self::Parser {
+        AdapterParser::with_value_hint(clap::ValueHint::FilePath)
+    }
+}
+
+impl<T: FromPath> Reload for PathTo<T> {
+    fn location(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn reload(&self) -> Result<Self, Error> {
+        Self::load(self.path.clone())
+    }
+
+    fn source_hash(&self) -> Option<u64> {
+        self.metadata.hash
+    }
+
+    fn value_hint() -> clap::ValueHint {
+        clap::ValueHint::FilePath
+    }
 }
@@ -0,0 +1,445 @@
+//! Runtime-agnostic filesystem watching shared by the notify-based adapters
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+use notify::event::{MetadataKind, ModifyKind};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+use crate::path::normalize;
+use crate::Error;
+
+/// The notify watchers shared by every [`FileWatcher`] in the process
+///
+/// There is one watcher per backend, keyed by the poll interval, or `None`
+/// for notify's recommended watcher. Each is created on first use, so that an
+/// application with many watched arguments only ever uses a single OS watcher
+/// instance.
+static REGISTRIES: Mutex<Option<HashMap<Option<Duration>, Registry>>> = Mutex::new(None);
+
+/// Event senders of every live [`FileWatcher`], keyed by watcher id
+///
+/// Kept apart from [`REGISTRIES`] so that notify's event handler never waits on
+/// a lock that is held while a directory is being (un)watched.
+static SUBSCRIBERS: Mutex<Vec<(u64, mpsc::Sender<Event>)>> = Mutex::new(Vec::new());
+
+/// Source of unique [`FileWatcher`] ids
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// The shared watcher, along with how many [`FileWatcher`]s use each directory
+struct Registry {
+    watcher: Box<dyn Watcher + Send>,
+    dirs: HashMap<PathBuf, usize>,
+}
+
+impl Registry {
+    /// Run `f` against the shared registry of a backend, creating it if needed
+    ///
+    /// `poll_interval` selects notify's `PollWatcher` with that interval, or
+    /// the recommended watcher for the platform if `None`.
+    fn with<R>(
+        poll_interval: Option<Duration>,
+        f: impl FnOnce(&mut Registry) -> notify::Result<R>,
+    ) -> notify::Result<R> {
+        let mut registries = REGISTRIES.lock().expect("watcher registry lock poisoned");
+        let registries = registries.get_or_insert_with(HashMap::new);
+        let registry = match registries.entry(poll_interval) {
+            std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                let watcher: Box<dyn Watcher + Send> = match poll_interval {
+                    Some(interval) => {
+                        // Modification times only have a resolution of seconds,
+                        // so compare contents to catch quick successive edits.
+                        // Polled files are watched directly rather than through
+                        // their directory, so only they are re-read.
+                        let config = notify::Config::default()
+                            .with_poll_interval(interval)
+                            .with_compare_contents(true);
+                        Box::new(notify::PollWatcher::new(fan_out, config)?)
+                    }
+                    None => Box::new(notify::recommended_watcher(fan_out)?),
+                };
+                entry.insert(Registry {
+                    watcher,
+                    dirs: HashMap::new(),
+                })
+            }
+        };
+        f(registry)
+    }
+
+    /// Watch `dir` (or a polled file), unless another [`FileWatcher`] already does
+    fn watch(&mut self, dir: &Path) -> notify::Result<()> {
+        if let Some(count) = self.dirs.get_mut(dir) {
+            *count += 1;
+            return Ok(());
+        }
+        self.watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        self.dirs.insert(dir.to_path_buf(), 1);
+        Ok(())
+    }
+
+    /// Release one use of `dir`, unwatching it once nobody needs it
+    fn unwatch(&mut self, dir: &Path) {
+        let Some(count) = self.dirs.get_mut(dir) else {
+            return;
+        };
+        *count -= 1;
+        if *count == 0 {
+            self.dirs.remove(dir);
+            let _ = self.watcher.unwatch(dir);
+        }
+    }
+}
+
+/// Forward a notify event to every [`FileWatcher`]
+fn fan_out(result: notify::Result<Event>) {
+    match result {
+        Ok(event) => {
+            let mut subscribers = SUBSCRIBERS.lock().expect("subscribers lock poisoned");
+            subscribers.retain(|(_, subscriber)| subscriber.send(event.clone()).is_ok());
+        }
+        Err(error) => {
+            tracing::warn!(
+                error = format!("{error:#}"),
+                "Notify triggered with error, skipping"
+            );
+        }
+    }
+}
+
+/// Watches a single file or directory for changes, invoking a callback on a
+/// background thread
+///
+/// Rather than watching the file itself, the directory containing it is
+/// watched, and events are filtered by the file's path. This keeps the watch
+/// alive when the file is replaced by a rename or removed and re-created. If
+/// the path is a symlink, the directory of its target is watched as well, and
+/// a change of the symlink's target counts as a change of the file.
+///
+/// When polling, the file's path is watched instead, as each poll re-reads
+/// every watched file to compare contents. Polls re-resolve the path, so
+/// this survives renames and symlink swaps all the same.
+///
+/// All file watchers with the same backend share one process-wide notify
+/// watcher, and directories are only watched once no matter how many files
+/// in them are watched.
+///
+/// Only events for which `is_relevant` returns true count as changes, by
+/// default those that may change the file's contents, see
+/// [`changes_contents`]. Events are debounced: the callback runs only once
+/// no new events have arrived for the debounce duration.
+///
+/// The background thread exits once the watcher is stopped or dropped.
+pub(crate) struct FileWatcher {
+    id: u64,
+    poll_interval: Option<Duration>,
+    /// Directories this watcher holds in the registry, or `None` once stopped
+    dirs: Arc<Mutex<Option<Vec<PathBuf>>>>,
+}
+
+impl FileWatcher {
+    /// Start watching the file at `path`, calling `on_change` after it changes
+    ///
+    /// Changes are detected by polling every `poll_interval` if given, which
+    /// works on network filesystems where OS notifications are unavailable.
+    /// Files on Windows UNC shares are always polled, since notifications for
+    /// them depend on the server and are silently dropped by many.
+    pub(crate) fn spawn<F>(
+        path: &Path,
+        debounce: Duration,
+        poll_interval: Option<Duration>,
+        is_relevant: fn(&EventKind) -> bool,
+        on_change: F,
+    ) -> Result<Self, Error>
+    where
+        F: Fn() + Send + 'static,
+    {
+        // Watch the parent directories so that the watch survives the file
+        // being renamed over or re-created. Paths are normalized so that
+        // verbatim Windows paths compare equal to the ones in events.
+        let watch_path = absolute(path)?;
+        let mut target = canonical_target(&watch_path);
+        let poll_interval =
+            poll_interval.or_else(|| is_unc(&watch_path).then_some(UNC_POLL_INTERVAL));
+        let dirs = match poll_interval {
+            Some(_) => vec![watch_path.clone()],
+            None => {
+                let mut dirs = vec![parent_dir(&watch_path)];
+                dirs.extend(target_dir(&watch_path, target.as_deref()));
+                dirs
+            }
+        };
+
+        let error_path = watch_path.clone();
+        Self::start(
+            error_path,
+            dirs,
+            debounce,
+            poll_interval,
+            move |events, dirs| {
+                // Detect whether a symlink now points somewhere else
+                let new_target = canonical_target(&watch_path);
+                let retargeted = new_target != target;
+                if retargeted && poll_interval.is_none() {
+                    rewatch_target(dirs, poll_interval, &watch_path, &target, &new_target);
+                }
+
+                let touched = events
+                    .iter()
+                    .filter(|event| is_relevant(&event.kind))
+                    .any(|event| {
+                        event
+                            .paths
+                            .iter()
+                            .map(|path| normalize(path))
+                            .any(|event_path| {
+                                event_path == watch_path
+                                    || Some(&event_path) == target.as_ref()
+                                    || Some(&event_path) == new_target.as_ref()
+                            })
+                    });
+                target = new_target;
+
+                if touched || retargeted {
+                    on_change();
+                }
+            },
+        )
+    }
+
+    /// Start watching the entries of the directory at `path`, calling
+    /// `on_change` after any of them changes
+    ///
+    /// Only the directory's direct entries are watched, not subdirectories.
+    /// Polling and event filtering work as for [`FileWatcher::spawn`].
+    #[cfg(feature = "reloading")]
+    pub(crate) fn spawn_dir<F>(
+        path: &Path,
+        debounce: Duration,
+        poll_interval: Option<Duration>,
+        is_relevant: fn(&EventKind) -> bool,
+        on_change: F,
+    ) -> Result<Self, Error>
+    where
+        F: Fn() + Send + 'static,
+    {
+        let watch_path = absolute(path)?;
+        let poll_interval =
+            poll_interval.or_else(|| is_unc(&watch_path).then_some(UNC_POLL_INTERVAL));
+
+        let dirs = vec![watch_path.clone()];
+        Self::start(
+            watch_path.clone(),
+            dirs,
+            debounce,
+            poll_interval,
+            move |events, _| {
+                let touched = events
+                    .iter()
+                    .filter(|event| is_relevant(&event.kind))
+                    .any(|event| {
+                        event
+                            .paths
+                            .iter()
+                            .map(|path| normalize(path))
+                            .any(|event_path| event_path.parent() == Some(watch_path.as_path()))
+                    });
+                if touched {
+                    on_change();
+                }
+            },
+        )
+    }
+
+    /// Watch `dirs`, passing each debounced batch of events to `on_events`
+    ///
+    /// `on_events` also receives the directories this watcher holds, so that
+    /// it may move watches around. `path` is only used for error messages.
+    fn start<F>(
+        path: PathBuf,
+        dirs: Vec<PathBuf>,
+        debounce: Duration,
+        poll_interval: Option<Duration>,
+        mut on_events: F,
+    ) -> Result<Self, Error>
+    where
+        F: FnMut(&[Event], &Mutex<Option<Vec<PathBuf>>>) + Send + 'static,
+    {
+        Registry::with(poll_interval, |registry| {
+            for (index, dir) in dirs.iter().enumerate() {
+                if let Err(error) = registry.watch(dir) {
+                    dirs[..index].iter().for_each(|dir| registry.unwatch(dir));
+                    return Err(error);
+                }
+            }
+            Ok(())
+        })
+        .map_err(|source| Error::Watch {
+            path: path.clone(),
+            source,
+        })?;
+        let dirs = Arc::new(Mutex::new(Some(dirs)));
+
+        // Receive events on a debouncing thread, which exits once the watcher
+        // is stopped and its sender removed
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let (event_tx, event_rx) = mpsc::channel();
+        SUBSCRIBERS
+            .lock()
+            .expect("subscribers lock poisoned")
+            .push((id, event_tx));
+
+        // Dropping the watcher cleans up if the thread can't be spawned, e.g.
+        // on WASI, which has no threads
+        let watcher = Self {
+            id,
+            poll_interval,
+            dirs: dirs.clone(),
+        };
+        let spawned = std::thread::Builder::new().spawn(move || {
+            while let Ok(first_event) = event_rx.recv() {
+                // Wait for events to settle before reloading
+                let mut events = vec![first_event];
+                loop {
+                    match event_rx.recv_timeout(debounce) {
+                        Ok(event) => events.push(event),
+                        Err(mpsc::RecvTimeoutError::Timeout) => break,
+                        Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+                on_events(&events, &dirs);
+            }
+        });
+        spawned.map_err(|error| Error::Watch {
+            path,
+            source: notify::Error::io(error),
+        })?;
+
+        Ok(watcher)
+    }
+
+    /// Stop watching the file, ending the background thread
+    pub(crate) fn stop(&self) {
+        let Some(dirs) = self.dirs.lock().ok().and_then(|mut dirs| dirs.take()) else {
+            return;
+        };
+
+        if let Ok(mut subscribers) = SUBSCRIBERS.lock() {
+            subscribers.retain(|(id, _)| *id != self.id);
+        }
+        let _ = Registry::with(self.poll_interval, |registry| {
+            dirs.iter().for_each(|dir| registry.unwatch(dir));
+            Ok(())
+        });
+    }
+}
+
+impl Drop for FileWatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Whether an event of `kind` may have changed a file's contents
+///
+/// Creating, removing, renaming and writing to the file count, as do events
+/// of unknown kind and rescans. Accesses and metadata changes other than the
+/// modification time are ignored, the latter being how polling reports writes.
+pub(crate) fn changes_contents(kind: &EventKind) -> bool {
+    match kind {
+        EventKind::Access(_) => false,
+        EventKind::Modify(ModifyKind::Metadata(metadata)) => {
+            matches!(metadata, MetadataKind::WriteTime)
+        }
+        EventKind::Any
+        | EventKind::Create(_)
+        | EventKind::Modify(_)
+        | EventKind::Remove(_)
+        | EventKind::Other => true,
+    }
+}
+
+/// How often files on UNC shares are polled when no interval was chosen
+const UNC_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Returns the absolute form of `path`, normalized to compare equal to event paths
+fn absolute(path: &Path) -> Result<PathBuf, Error> {
+    std::path::absolute(path)
+        .map(|path| normalize(&path))
+        .map_err(|error| Error::io(path, error))
+}
+
+/// Returns the directory containing `path`
+fn parent_dir(path: &Path) -> PathBuf {
+    path.parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Whether `path` is on a Windows UNC share such as `\\server\share`
+fn is_unc(path: &Path) -> bool {
+    use std::path::{Component, Prefix};
+
+    matches!(
+        path.components().next(),
+        Some(Component::Prefix(prefix))
+            if matches!(prefix.kind(), Prefix::UNC(..) | Prefix::VerbatimUNC(..))
+    )
+}
+
+/// Returns the resolved location of `path`, if it currently exists
+fn canonical_target(path: &Path) -> Option<PathBuf> {
+    std::fs::canonicalize(path)
+        .ok()
+        .map(|target| normalize(&target))
+}
+
+/// Returns the directory of a symlink's target, if it needs a separate watch
+fn target_dir(path: &Path, target: Option<&Path>) -> Option<PathBuf> {
+    let target_dir = parent_dir(target?);
+    (target_dir != parent_dir(path)).then_some(target_dir)
+}
+
+/// Moves the watch on a symlink's target directory after the link changed
+fn rewatch_target(
+    dirs: &Mutex<Option<Vec<PathBuf>>>,
+    poll_interval: Option<Duration>,
+    path: &Path,
+    old_target: &Option<PathBuf>,
+    new_target: &Option<PathBuf>,
+) {
+    let old_dir = target_dir(path, old_target.as_deref());
+    let new_dir = target_dir(path, new_target.as_deref());
+    if old_dir == new_dir {
+        return;
+    }
+
+    let mut dirs = dirs.lock().expect("watched directories lock poisoned");
+    let Some(dirs) = dirs.as_mut() else {
+        return;
+    };
+
+    let result = Registry::with(poll_interval, |registry| {
+        if let Some(old_dir) = old_dir {
+            if let Some(index) = dirs.iter().position(|dir| *dir == old_dir) {
+                registry.unwatch(&dirs.swap_remove(index));
+            }
+        }
+        if let Some(new_dir) = &new_dir {
+            registry.watch(new_dir)?;
+            dirs.push(new_dir.clone());
+        }
+        Ok(())
+    });
+    if let Err(error) = result {
+        tracing::warn!(
+            error = format!("{error:#}"),
+            path = ?new_dir,
+            "Failed to watch new symlink target directory",
+        );
+    }
+}
@@ -0,0 +1,556 @@
+//! Provides the [`ReloadHandle`] for triggering reloads on demand, the
+//! [`ReloadError`] reported when a reload fails, the [`ReloadStats`]
+//! counting reload activity, and the [`Snapshot`] of each published document
+
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::SystemTime;
+
+use tokio::sync::watch;
+
+#[cfg(any(feature = "periodic", feature = "reloading", feature = "signal"))]
+use crate::traits::Reload;
+use crate::Error;
+
+/// Validation closures that reloaded documents must pass before being published
+pub(crate) type Validators<T> = Arc<RwLock<Vec<Validator<T>>>>;
+
+/// A validation closure registered on a reloading adapter
+pub(crate) type Validator<T> =
+    Box<dyn Fn(&T) -> Result<(), Box<dyn std::error::Error + Send + Sync>> + Send + Sync>;
+
+/// A closure that receives failures of reloads triggered in the background
+pub(crate) type ErrorHook = Box<dyn Fn(&ReloadError) + Send + Sync>;
+
+/// A cloneable handle that forces an adapter to re-read its file immediately
+///
+/// Obtain a handle from a reloading adapter with [`ConfigWatcher::reload_handle`],
+/// which every reloading adapter dereferences to, to wire up custom reload
+/// triggers, such as an admin endpoint or a keypress, without duplicating the loading logic.
+/// Successfully reloaded documents are published to the adapter's receivers
+/// just like any other update.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Json config that's reloaded
+///     #[clap(long)]
+///     config: Reloading<PathTo<JsonOf<serde_json::Value>>>,
+/// }
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+///
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+/// let reload_handle = cli.config.reload_handle();
+///
+/// // Update the file and reload it right away
+/// std::fs::write(&config_path, r#"{"hello":"handle"}"#)?;
+/// reload_handle.reload_now()?;
+/// assert_eq!(cli.config.get().data.0, serde_json::json!({"hello":"handle"}));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`ConfigWatcher::reload_handle`]: crate::ConfigWatcher::reload_handle
+#[derive(Clone)]
+pub struct ReloadHandle {
+    reload: Arc<dyn Fn() -> Result<(), Error> + Send + Sync>,
+    reload_lock: Arc<Mutex<()>>,
+    location: String,
+    error_tx: Arc<watch::Sender<Option<ReloadError>>>,
+    stats: Arc<Mutex<ReloadStats>>,
+    error_hook: Arc<RwLock<Option<ErrorHook>>>,
+}
+
+impl ReloadHandle {
+    /// Create a handle that reloads the document in `reload_tx` and publishes the result
+    ///
+    /// Each reloaded document must pass all `validators` to be published.
+    #[cfg(any(feature = "periodic", feature = "reloading", feature = "signal"))]
+    pub(crate) fn new<T>(reload_tx: watch::Sender<Snapshot<T>>, validators: Validators<T>) -> Self
+    where
+        T: Reload + Send + Sync + 'static,
+    {
+        let location = reload_tx.borrow().document.location();
+        Self::with_source(
+            reload_tx,
+            validators,
+            location,
+            skip_unchanged(T::reload_if_changed),
+        )
+    }
+
+    /// Create a handle that publishes documents produced by `next`
+    ///
+    /// `next` receives the current document, and returns `None` if it is unchanged.
+    pub(crate) fn with_source<T, F>(
+        reload_tx: watch::Sender<Snapshot<T>>,
+        validators: Validators<T>,
+        location: String,
+        next: F,
+    ) -> Self
+    where
+        T: Send + Sync + 'static,
+        F: Fn(&T) -> Result<Option<T>, Error> + Send + Sync + 'static,
+    {
+        let reload = move || -> Result<(), Error> {
+            let current = reload_tx.borrow().document.clone();
+            let Some(updated) = next(&current)? else {
+                return Ok(());
+            };
+
+            let validators = validators.read().expect("validators lock poisoned");
+            for validator in validators.iter() {
+                validator(&updated).map_err(|source| Error::Rejected { source })?;
+            }
+
+            reload_tx.send_modify(|snapshot| {
+                *snapshot = Snapshot {
+                    document: Arc::new(updated),
+                    generation: snapshot.generation + 1,
+                    loaded_at: SystemTime::now(),
+                };
+            });
+            Ok(())
+        };
+
+        let (error_tx, _) = watch::channel(None);
+        Self {
+            reload: Arc::new(reload),
+            reload_lock: Arc::default(),
+            location,
+            error_tx: Arc::new(error_tx),
+            stats: Arc::default(),
+            error_hook: Arc::default(),
+        }
+    }
+
+    /// Reload the document and publish the result, returning any load error
+    ///
+    /// If loading fails, receivers keep observing the previous document, and
+    /// the error is published to the adapter's error receivers. Concurrent
+    /// reloads run one at a time, so a slow reload never publishes a document
+    /// or an error over the result of a reload that started after it.
+    pub fn reload_now(&self) -> Result<(), ReloadError> {
+        let _reloading = self.reload_lock.lock().expect("reload lock poisoned");
+        let result = (self.reload)();
+        let time = SystemTime::now();
+        let mut stats = self.stats.lock().expect("stats lock poisoned");
+        stats.attempts += 1;
+        match result {
+            Ok(()) => {
+                stats.successes += 1;
+                stats.last_success = Some(time);
+                self.error_tx.send_replace(None);
+                Ok(())
+            }
+            Err(error) => {
+                stats.failures += 1;
+                let error = ReloadError {
+                    location: self.location.clone(),
+                    time,
+                    error: Arc::new(error),
+                };
+                self.error_tx.send_replace(Some(error.clone()));
+                Err(error)
+            }
+        }
+    }
+
+    /// Get a receiver of the most recent reload error, if the last reload failed
+    pub fn error_receiver(&self) -> watch::Receiver<Option<ReloadError>> {
+        self.error_tx.subscribe()
+    }
+
+    /// Wait for the next reload to fail, returning its error
+    ///
+    /// Failures are observed from the moment of this call, not from the first poll.
+    pub(crate) fn next_error(&self) -> impl std::future::Future<Output = ReloadError> + Send {
+        let mut error_rx = self.error_tx.subscribe();
+        async move {
+            while error_rx.changed().await.is_ok() {
+                if let Some(error) = error_rx.borrow_and_update().clone() {
+                    return error;
+                }
+            }
+            std::future::pending().await
+        }
+    }
+
+    /// Get a snapshot of the reload activity so far
+    pub fn stats(&self) -> ReloadStats {
+        *self.stats.lock().expect("stats lock poisoned")
+    }
+
+    /// Replace how failures of background reloads are reported
+    pub(crate) fn set_error_hook(&self, hook: ErrorHook) {
+        *self.error_hook.write().expect("error hook lock poisoned") = Some(hook);
+    }
+
+    /// Report a failed background reload to the error hook, or log it if none is set
+    pub(crate) fn report(&self, error: &ReloadError, message: &str) {
+        match &*self.error_hook.read().expect("error hook lock poisoned") {
+            Some(hook) => hook(error),
+            None => tracing::error!(
+                error = %error.error,
+                location = %error.location,
+                "{message}",
+            ),
+        }
+    }
+}
+
+impl std::fmt::Debug for ReloadHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReloadHandle").finish_non_exhaustive()
+    }
+}
+
+/// An error that occurred while reloading a document in the background
+///
+/// When a reload fails, adapters keep serving the previous document. Use
+/// e.g. [`ConfigWatcher::errors`] or [`ConfigWatcher::last_error`] to surface these
+/// failures to operators instead of silently running with stale data.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Json config that's reloaded
+///     #[clap(long)]
+///     config: Reloading<PathTo<JsonOf<serde_json::Value>>>,
+/// }
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+///
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+///
+/// // Break the config file, then reload it
+/// std::fs::write(&config_path, "{not json")?;
+/// assert!(cli.config.reload_now().is_err());
+///
+/// // The previous document is kept, and the error is reported
+/// assert_eq!(cli.config.get().data.0, serde_json::json!({"hello":"world"}));
+/// let error = cli.config.last_error().expect("reload should have failed");
+/// assert_eq!(error.location, config_path_string);
+///
+/// // Fixing the file clears the error
+/// std::fs::write(&config_path, r#"{"hello":"again"}"#)?;
+/// cli.config.reload_now()?;
+/// assert!(cli.config.last_error().is_none());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`ConfigWatcher::errors`]: crate::ConfigWatcher::errors
+/// [`ConfigWatcher::last_error`]: crate::ConfigWatcher::last_error
+#[derive(Debug, Clone)]
+pub struct ReloadError {
+    /// Where the document failed to reload from, such as a file path
+    pub location: String,
+
+    /// When the reload was attempted
+    pub time: SystemTime,
+
+    /// The underlying cause of the failure
+    pub error: Arc<Error>,
+}
+
+impl std::fmt::Display for ReloadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "failed to reload '{}'", self.location)
+    }
+}
+
+impl std::error::Error for ReloadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.error.as_ref())
+    }
+}
+
+/// A published document, along with when and in which generation it was loaded
+///
+/// The generation starts at zero for the document loaded while parsing, and
+/// increases by one with each reloaded document that is published. Comparing
+/// generations is a cheap way to tell whether state derived from a document
+/// is stale. [`Snapshot`] dereferences to the document itself.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Json config that's reloaded
+///     #[clap(long)]
+///     config: Reloading<PathTo<JsonOf<serde_json::Value>>>,
+/// }
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+///
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+/// let initial = cli.config.snapshot();
+/// assert_eq!(initial.generation, 0);
+///
+/// std::fs::write(&config_path, r#"{"hello":"snapshot"}"#)?;
+/// cli.config.reload_now()?;
+///
+/// let current = cli.config.snapshot();
+/// assert!(current.generation > initial.generation);
+/// assert_eq!(current.data.0, serde_json::json!({"hello":"snapshot"}));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Snapshot<T> {
+    /// The published document
+    pub document: Arc<T>,
+
+    /// How many reloaded documents were published before this one
+    pub generation: u64,
+
+    /// When the document was loaded
+    pub loaded_at: SystemTime,
+}
+
+impl<T> Snapshot<T> {
+    /// Wrap the document loaded while parsing as the first generation
+    pub(crate) fn new(document: T) -> Self {
+        Self {
+            document: Arc::new(document),
+            generation: 0,
+            loaded_at: SystemTime::now(),
+        }
+    }
+}
+
+impl<T> Clone for Snapshot<T> {
+    fn clone(&self) -> Self {
+        Self {
+            document: self.document.clone(),
+            generation: self.generation,
+            loaded_at: self.loaded_at,
+        }
+    }
+}
+
+impl<T> std::ops::Deref for Snapshot<T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.document
+    }
+}
+
+/// A lightweight signal that fires whenever a new document is published
+///
+/// Unlike a stream of documents, waiting on a [`ChangeSignal`] doesn't clone
+/// or hold on to any document: after it fires, call `get()` on the adapter
+/// to read the current document. Changes published while the signal isn't
+/// being awaited are not lost, the next [`ChangeSignal::changed`] completes
+/// immediately instead. Several changes in a row fire the signal only once.
+///
+/// Get one with `change_signal()` on any reloading adapter.
+pub struct ChangeSignal<T> {
+    reload_rx: watch::Receiver<Snapshot<T>>,
+}
+
+impl<T> ChangeSignal<T> {
+    /// Create a signal that fires for documents published after the current one
+    pub(crate) fn new(mut reload_rx: watch::Receiver<Snapshot<T>>) -> Self {
+        reload_rx.borrow_and_update();
+        Self { reload_rx }
+    }
+
+    /// Wait until a document newer than the last one signaled is published
+    ///
+    /// If the adapter can never publish again, this never completes.
+    pub async fn changed(&mut self) {
+        if self.reload_rx.changed().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+
+    /// Whether a document was published since the signal last fired
+    pub fn has_changed(&self) -> bool {
+        self.reload_rx.has_changed().unwrap_or(false)
+    }
+}
+
+impl<T> Clone for ChangeSignal<T> {
+    fn clone(&self) -> Self {
+        Self {
+            reload_rx: self.reload_rx.clone(),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for ChangeSignal<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChangeSignal").finish_non_exhaustive()
+    }
+}
+
+/// Counters describing the reload activity of an adapter
+///
+/// Reloads that find the document unchanged still count as successes. Use
+/// e.g. [`ConfigWatcher::stats`] to export these to your metrics system, or to
+/// alert when a config has not refreshed in a while.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Json config that's reloaded
+///     #[clap(long)]
+///     config: Reloading<PathTo<JsonOf<serde_json::Value>>>,
+/// }
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+///
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+/// cli.config.reload_now()?;
+///
+/// std::fs::write(&config_path, "{not json")?;
+/// assert!(cli.config.reload_now().is_err());
+///
+/// let stats = cli.config.stats();
+/// assert_eq!(stats.attempts, 2);
+/// assert_eq!(stats.successes, 1);
+/// assert_eq!(stats.failures, 1);
+/// assert!(stats.last_success.is_some());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`ConfigWatcher::stats`]: crate::ConfigWatcher::stats
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ReloadStats {
+    /// How many reloads were attempted
+    pub attempts: u64,
+
+    /// How many reloads succeeded
+    pub successes: u64,
+
+    /// How many reloads failed
+    pub failures: u64,
+
+    /// When a reload last succeeded, if ever
+    pub last_success: Option<SystemTime>,
+}
+
+/// Whether an adapter's background reloading is active
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RunState {
+    /// Background reloads are performed as usual
+    Running,
+    /// Background reloads are skipped until resumed
+    Paused,
+    /// Background work has stopped for good
+    Shutdown,
+}
+
+/// Shared control over the background work of a reloading adapter
+///
+/// Background tasks observe the state via [`Lifecycle::subscribe`], and stop
+/// once every clone of the lifecycle (i.e. of the adapter) is dropped.
+#[derive(Debug, Clone)]
+pub(crate) struct Lifecycle {
+    state_tx: Arc<watch::Sender<RunState>>,
+}
+
+impl Lifecycle {
+    /// Create a lifecycle in the running state
+    pub(crate) fn new() -> Self {
+        let (state_tx, _) = watch::channel(RunState::Running);
+        Self {
+            state_tx: Arc::new(state_tx),
+        }
+    }
+
+    /// Get a receiver observing state changes
+    pub(crate) fn subscribe(&self) -> watch::Receiver<RunState> {
+        self.state_tx.subscribe()
+    }
+
+    /// Pause if running, returning whether the state changed
+    pub(crate) fn pause(&self) -> bool {
+        self.transition(RunState::Running, RunState::Paused)
+    }
+
+    /// Resume if paused, returning whether the state changed
+    pub(crate) fn resume(&self) -> bool {
+        self.transition(RunState::Paused, RunState::Running)
+    }
+
+    /// Permanently stop background work
+    pub(crate) fn shutdown(&self) {
+        self.state_tx.send_replace(RunState::Shutdown);
+    }
+
+    fn transition(&self, from: RunState, to: RunState) -> bool {
+        self.state_tx.send_if_modified(|state| {
+            let matches = *state == from;
+            if matches {
+                *state = to;
+            }
+            matches
+        })
+    }
+}
+
+/// Wrap `next` so that documents loaded from unchanged raw content aren't published
+///
+/// Each reloaded document is compared by [`Reload::source_hash`] to the
+/// current one, which is the document loaded while parsing until a reload
+/// is published.
+#[cfg(any(feature = "periodic", feature = "reloading", feature = "signal"))]
+pub(crate) fn skip_unchanged<T, F>(next: F) -> impl Fn(&T) -> Result<Option<T>, Error>
+where
+    T: Reload,
+    F: Fn(&T) -> Result<Option<T>, Error>,
+{
+    move |current| {
+        let Some(next) = next(current)? else {
+            return Ok(None);
+        };
+        let hash = next.source_hash();
+        if hash.is_some() && hash == current.source_hash() {
+            return Ok(None);
+        }
+        Ok(Some(next))
+    }
+}
@@ -1,17 +1,111 @@
-use serde::de::DeserializeOwned;
+use serde::{de::DeserializeOwned, Serialize};
 
-use crate::prelude::FromReader;
+use crate::prelude::{FromMerged, FromReader, ToWriter};
 
 /// An adapter for deserializing a Json document from a buffered reader
-#[derive(Debug, Clone)]
+///
+/// With the `simd-json` feature, documents are parsed with [simd-json], which
+/// is considerably faster for large documents. Errors are still reported as
+/// [`serde_json::Error`]s.
+///
+/// [simd-json]: https://docs.rs/simd-json
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
 pub struct JsonOf<T>(pub T);
 
+impl<T> std::ops::Deref for JsonOf<T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> std::ops::DerefMut for JsonOf<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T> AsRef<T> for JsonOf<T> {
+    fn as_ref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> JsonOf<T> {
+    /// Returns a reference to the inner JSON datatype
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use clap_adapters::prelude::*;
+    ///
+    /// let mut document = JsonOf(vec![1, 2]);
+    /// document.data_mut().push(3);
+    /// assert_eq!(document.data(), &[1, 2, 3]);
+    /// assert_eq!(document.into_inner(), vec![1, 2, 3]);
+    /// ```
+    pub fn data(&self) -> &T {
+        &self.0
+    }
+
+    /// Returns a mutable reference to the inner JSON datatype
+    pub fn data_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+
+    /// Returns the inner JSON datatype
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
 impl<T: DeserializeOwned> FromReader for JsonOf<T> {
     type Error = serde_json::Error;
+    #[cfg(not(feature = "simd-json"))]
     fn from_reader(reader: &mut impl std::io::BufRead) -> Result<Self, Self::Error> {
         let json = serde_json::from_reader::<_, T>(reader)?;
         Ok(JsonOf(json))
     }
+
+    /// Parses the document with simd-json, which needs the whole document in memory
+    #[cfg(feature = "simd-json")]
+    fn from_reader(reader: &mut impl std::io::BufRead) -> Result<Self, Self::Error> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .map_err(serde_json::Error::io)?;
+
+        // simd-json may modify its input, and its errors have no line and column,
+        // so on failure the original bytes are parsed again by serde_json to
+        // report where the document is malformed
+        let mut scratch = bytes.clone();
+        match simd_json::serde::from_slice::<T>(&mut scratch) {
+            Ok(json) => Ok(JsonOf(json)),
+            Err(_) => serde_json::from_slice::<T>(&bytes).map(JsonOf),
+        }
+    }
+}
+
+impl<T: Serialize> ToWriter for JsonOf<T> {
+    type Error = serde_json::Error;
+    fn to_writer(&self, writer: &mut impl std::io::Write) -> Result<(), Self::Error> {
+        serde_json::to_writer_pretty(writer, &self.0)
+    }
+}
+
+impl<T: DeserializeOwned> FromMerged for JsonOf<T> {
+    const EXTENSIONS: &'static [&'static str] = &["json"];
+    type Error = serde_json::Error;
+    fn value_from_reader(
+        reader: &mut impl std::io::BufRead,
+    ) -> Result<serde_json::Value, Self::Error> {
+        serde_json::from_reader(reader)
+    }
+    fn from_merged(value: serde_json::Value) -> Result<Self, Self::Error> {
+        let json = serde_json::from_value::<T>(value)?;
+        Ok(JsonOf(json))
+    }
 }
 
 impl<T> crate::fs::PathTo<JsonOf<T>> {
@@ -53,6 +147,38 @@ impl<T> crate::fs::PathTo<JsonOf<T>> {
         &self.data.0
     }
 
+    /// Returns a mutable reference to the inner JSON datatype
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap::Parser;
+    /// use clap_adapters::prelude::*;
+    ///
+    /// #[derive(Debug, Parser)]
+    /// struct Cli {
+    ///     #[clap(long)]
+    ///     config: PathTo<JsonOf<serde_json::Value>>,
+    /// }
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// let config_path_string = config_path.display().to_string();
+    /// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+    ///
+    /// // Parse our CLI, then override a value
+    /// let mut cli = Cli::parse_from(["app", "--config", &config_path_string]);
+    /// cli.config.data_mut()["hello"] = serde_json::json!("there");
+    /// assert_eq!(cli.config.data(), &serde_json::json!({"hello":"there"}));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn data_mut(&mut self) -> &mut T {
+        &mut self.data.0
+    }
+
     /// Returns reference to the inner JSON datatype
     ///
     /// # Example
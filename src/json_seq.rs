@@ -0,0 +1,321 @@
+use std::io::BufRead;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+
+use serde::de::{DeserializeOwned, Error as _};
+
+use crate::Error;
+
+/// An adapter for streaming the items of huge Json arrays without loading them at once
+///
+/// Given a path from the user, `JsonSeqOf<T>` checks while parsing that
+/// every item in the file deserializes as `T`, holding only one item in
+/// memory at a time, and then discards them. Use [`JsonSeqOf::iter`] to read
+/// the items again, one at a time, when they are needed.
+///
+/// The file may hold either a single top-level array, or a sequence of
+/// whitespace-separated Json values such as newline-delimited Json.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, PartialEq, serde::Deserialize)]
+/// struct Event {
+///     id: u32,
+/// }
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Json array of events
+///     #[clap(long)]
+///     events: JsonSeqOf<Event>,
+/// }
+///
+/// // Create an events file in a temporary directory
+/// let data_dir = tempfile::tempdir()?;
+/// let events_path = data_dir.path().join("events.json");
+/// let events_path_string = events_path.display().to_string();
+/// std::fs::write(&events_path, r#"[{"id": 1}, {"id": 2}, {"id": 3}]"#)?;
+///
+/// let cli = Cli::parse_from(["app", "--events", &events_path_string]);
+/// assert_eq!(cli.events.len, 3);
+/// let mut ids = Vec::new();
+/// for event in cli.events.iter()? {
+///     ids.push(event?.id);
+/// }
+/// assert_eq!(ids, [1, 2, 3]);
+///
+/// // Newline-delimited Json works the same way
+/// std::fs::write(&events_path, "{\"id\": 4}\n{\"id\": 5}\n")?;
+/// let cli = Cli::parse_from(["app", "--events", &events_path_string]);
+/// assert_eq!(cli.events.iter()?.next().transpose()?, Some(Event { id: 4 }));
+///
+/// // Files with an invalid item are rejected while parsing
+/// std::fs::write(&events_path, r#"[{"id": 1}, {"id": "two"}]"#)?;
+/// assert!(Cli::try_parse_from(["app", "--events", &events_path_string]).is_err());
+/// # Ok(())
+/// # }
+/// ```
+pub struct JsonSeqOf<T> {
+    /// The path given as an argument by the user
+    pub path: PathBuf,
+
+    /// The number of items, counted while checking the file
+    pub len: usize,
+
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> JsonSeqOf<T> {
+    /// Check that every item in the file at `path` deserializes as `T`
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let mut item = JsonSeqOf {
+            path: path.into(),
+            len: 0,
+            marker: PhantomData,
+        };
+        for result in item.iter()? {
+            result?;
+            item.len += 1;
+        }
+        Ok(item)
+    }
+
+    /// Open the file again and iterate over its items, reading one at a time
+    ///
+    /// If the file changed since it was checked, items may fail to
+    /// deserialize, in which case the iterator ends after the error.
+    pub fn iter(&self) -> Result<JsonSeqIter<T>, Error> {
+        let file = std::fs::File::open(&self.path).map_err(|error| Error::io(&self.path, error))?;
+        let mut reader = std::io::BufReader::new(file);
+        let mut offset = 0;
+        let items = match skip_whitespace(&mut reader, &mut offset) {
+            Ok(Some(b'[')) => Items::Array(ArrayItems {
+                reader,
+                state: ArrayState::Start,
+                offset,
+            }),
+            Ok(_) => Items::Values(serde_json::Deserializer::from_reader(reader).into_iter()),
+            Err(error) => return Err(Error::io(&self.path, error)),
+        };
+        Ok(JsonSeqIter {
+            path: self.path.clone(),
+            items,
+        })
+    }
+}
+
+impl<T: DeserializeOwned> std::str::FromStr for JsonSeqOf<T> {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::load(s)
+    }
+}
+
+impl<T> Clone for JsonSeqOf<T> {
+    fn clone(&self) -> Self {
+        JsonSeqOf {
+            path: self.path.clone(),
+            len: self.len,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for JsonSeqOf<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JsonSeqOf")
+            .field("path", &self.path)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+/// An iterator over the items of a [`JsonSeqOf`], see [`JsonSeqOf::iter`]
+pub struct JsonSeqIter<T> {
+    path: PathBuf,
+    items: Items<T>,
+}
+
+type Reader = std::io::BufReader<std::fs::File>;
+
+enum Items<T> {
+    Array(ArrayItems),
+    Values(serde_json::StreamDeserializer<'static, serde_json::de::IoRead<Reader>, T>),
+    Done,
+}
+
+impl<T: DeserializeOwned> Iterator for JsonSeqIter<T> {
+    type Item = Result<T, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = match &mut self.items {
+            Items::Array(array) => array.next_item().transpose()?.and_then(|(offset, bytes)| {
+                serde_json::from_slice(&bytes)
+                    .map_err(|error| at_offset_in_file(error, offset, &bytes))
+            }),
+            Items::Values(values) => values.next()?,
+            Items::Done => return None,
+        };
+        if result.is_err() {
+            self.items = Items::Done;
+        }
+        Some(result.map_err(|source| Error::Json {
+            path: self.path.clone(),
+            source,
+        }))
+    }
+}
+
+/// Splits a top-level Json array into the bytes of its items
+///
+/// Only the nesting of brackets and strings is tracked, so that each item
+/// is parsed by serde_json on its own, without buffering the whole array.
+///
+/// serde_json can't do this itself: its `StreamDeserializer` only splits a
+/// sequence of top-level values, so it would yield the whole array as one
+/// value, and the elements of an array can only be visited from within a
+/// single `deserialize` call, which doesn't fit an `Iterator` that hands
+/// out one item per call to `next`.
+///
+/// As serde_json positions errors relative to the bytes it is given, errors
+/// report the byte offset in the file instead.
+struct ArrayItems {
+    reader: Reader,
+    state: ArrayState,
+    /// The number of bytes consumed from the file so far
+    offset: u64,
+}
+
+enum ArrayState {
+    Start,
+    Next,
+    Done,
+}
+
+impl ArrayItems {
+    /// Returns the byte offset and bytes of the next item, or `None` after the closing `]`
+    fn next_item(&mut self) -> Result<Option<(u64, Vec<u8>)>, serde_json::Error> {
+        let first = match self.state {
+            ArrayState::Done => return Ok(None),
+            ArrayState::Start => {
+                self.expect_byte()?; // The opening `[`, already peeked
+                match self.next_non_whitespace()? {
+                    b']' => return self.finish().map(|()| None),
+                    first => first,
+                }
+            }
+            ArrayState::Next => match self.next_non_whitespace()? {
+                b']' => return Err(invalid("trailing comma in array", self.offset - 1)),
+                first => first,
+            },
+        };
+
+        let start = self.offset - 1;
+        if first == b',' {
+            return Err(invalid("expected a value in array", start));
+        }
+
+        let mut bytes = Vec::new();
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+        let mut byte = first;
+        loop {
+            if in_string {
+                if escaped {
+                    escaped = false;
+                } else if byte == b'\\' {
+                    escaped = true;
+                } else if byte == b'"' {
+                    in_string = false;
+                }
+            } else {
+                match byte {
+                    b'"' => in_string = true,
+                    b'[' | b'{' => depth += 1,
+                    b']' | b'}' if depth > 0 => depth -= 1,
+                    b']' => {
+                        self.finish()?;
+                        return Ok(Some((start, bytes)));
+                    }
+                    b',' if depth == 0 => {
+                        self.state = ArrayState::Next;
+                        return Ok(Some((start, bytes)));
+                    }
+                    _ => {}
+                }
+            }
+            bytes.push(byte);
+            byte = self.expect_byte()?;
+        }
+    }
+
+    /// Check that only whitespace follows the closing `]`
+    fn finish(&mut self) -> Result<(), serde_json::Error> {
+        self.state = ArrayState::Done;
+        let next = skip_whitespace(&mut self.reader, &mut self.offset);
+        match next.map_err(serde_json::Error::io)? {
+            None => Ok(()),
+            Some(_) => Err(invalid("trailing characters after array", self.offset)),
+        }
+    }
+
+    fn next_non_whitespace(&mut self) -> Result<u8, serde_json::Error> {
+        skip_whitespace(&mut self.reader, &mut self.offset).map_err(serde_json::Error::io)?;
+        self.expect_byte()
+    }
+
+    fn expect_byte(&mut self) -> Result<u8, serde_json::Error> {
+        let buffer = self.reader.fill_buf().map_err(serde_json::Error::io)?;
+        let byte = *buffer
+            .first()
+            .ok_or_else(|| invalid("unexpected end of array", self.offset))?;
+        self.reader.consume(1);
+        self.offset += 1;
+        Ok(byte)
+    }
+}
+
+/// Skip whitespace in `reader`, returning the next byte without consuming it
+///
+/// `offset` is advanced by the number of bytes skipped.
+fn skip_whitespace(reader: &mut impl BufRead, offset: &mut u64) -> std::io::Result<Option<u8>> {
+    loop {
+        let buffer = reader.fill_buf()?;
+        let Some(&byte) = buffer.first() else {
+            return Ok(None);
+        };
+        if !matches!(byte, b' ' | b'\t' | b'\n' | b'\r') {
+            return Ok(Some(byte));
+        }
+        reader.consume(1);
+        *offset += 1;
+    }
+}
+
+/// Report the parse `error` of the item at `item_offset` at its byte offset in the file
+///
+/// serde_json positions the error by line and column within `item`.
+fn at_offset_in_file(error: serde_json::Error, item_offset: u64, item: &[u8]) -> serde_json::Error {
+    if error.line() == 0 {
+        return error;
+    }
+    let line_start: usize = item
+        .split_inclusive(|&byte| byte == b'\n')
+        .take(error.line() - 1)
+        .map(<[u8]>::len)
+        .sum();
+    let offset = item_offset + (line_start + error.column()).saturating_sub(1) as u64;
+
+    let message = error.to_string();
+    let position = format!(" at line {} column {}", error.line(), error.column());
+    invalid(message.strip_suffix(&position).unwrap_or(&message), offset)
+}
+
+fn invalid(message: &str, offset: u64) -> serde_json::Error {
+    serde_json::Error::custom(format_args!("{message} at byte offset {offset}"))
+}
@@ -0,0 +1,283 @@
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::{dump::DumpFormat, overrides::SetOverride, traits::FromMerged, Error};
+
+/// Merges layered sources in precedence order, recording which layer set each key
+///
+/// Layers are added from lowest to highest precedence, typically defaults,
+/// then a system file, a user file, environment variables and finally
+/// `--set` overrides from the command line. Each layer is deep-merged over
+/// the previous ones, as with [`DirOf`](crate::DirOf), and given a name.
+///
+/// For every key whose value is not a table, [`Layered`] remembers the name
+/// of the layer that provided its effective value. Use
+/// [`Layered::source_of`] to look up a single key, or
+/// [`Layered::provenance`] to list all of them, e.g. to explain the
+/// effective config to a user.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, serde::Deserialize)]
+/// struct Config {
+///     host: String,
+///     port: u16,
+///     workers: u32,
+/// }
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Override a config value, e.g. `--set port=8080`
+///     #[clap(long = "set")]
+///     overrides: Vec<SetOverride>,
+/// }
+///
+/// // Create a system and a user config in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let system_path = config_dir.path().join("system.json");
+/// std::fs::write(&system_path, r#"{"host": "example.com", "port": 80}"#)?;
+/// let user_path = config_dir.path().join("user.json");
+///
+/// std::env::set_var("MY_APP_WORKERS", "8");
+/// let cli = Cli::parse_from(["app", "--set", "port=8080"]);
+///
+/// let layered = Layered::<JsonOf<Config>>::new()
+///     .with_value("defaults", serde_json::json!({"host": "localhost", "port": 80, "workers": 1}))
+///     .with_file("system", &system_path)?
+///     .with_optional_file("user", &user_path)?
+///     .with_env("env", "MY_APP_")?
+///     .with_overrides("cli", &cli.overrides);
+///
+/// let config = layered.build()?;
+/// assert_eq!((config.host.as_str(), config.port, config.workers), ("example.com", 8080, 8));
+///
+/// assert_eq!(layered.source_of("host"), Some("system"));
+/// assert_eq!(layered.source_of("port"), Some("cli"));
+/// assert_eq!(layered.source_of("workers"), Some("env"));
+/// assert_eq!(layered.source_of("missing"), None);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Layered<T> {
+    /// The merged document as a value tree
+    pub document: Value,
+
+    /// The names of the layers that were added, from lowest to highest precedence
+    pub layers: Vec<String>,
+
+    provenance: BTreeMap<String, String>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: FromMerged> Layered<T> {
+    /// Start with an empty document and no layers
+    pub fn new() -> Self {
+        Layered {
+            document: Value::Object(Default::default()),
+            layers: Vec::new(),
+            provenance: BTreeMap::new(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Merge the value tree `value` as the layer `layer`
+    pub fn with_value(mut self, layer: &str, value: Value) -> Self {
+        record(&mut self.provenance, String::new(), &value, layer);
+        crate::merge::deep_merge(&mut self.document, value);
+        self.layers.push(layer.to_string());
+        self
+    }
+
+    /// Merge the file at `path` as the layer `layer`
+    pub fn with_file(self, layer: &str, path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path).map_err(|error| Error::io(path, error))?;
+        let mut reader = std::io::BufReader::new(file);
+        let value = T::value_from_reader(&mut reader)
+            .map_err(|error| Error::parse(path.to_path_buf(), error))?;
+        Ok(self.with_value(layer, value))
+    }
+
+    /// Merge the file at `path` as the layer `layer` if it exists, or skip the layer
+    pub fn with_optional_file(self, layer: &str, path: impl AsRef<Path>) -> Result<Self, Error> {
+        match path.as_ref().try_exists() {
+            Ok(false) => Ok(self),
+            _ => self.with_file(layer, path),
+        }
+    }
+
+    /// Merge the environment variables starting with `prefix` as the layer `layer`
+    ///
+    /// The prefix is removed and the rest of the name is lowercased and split
+    /// on `__` into nested keys, so with the prefix `APP_`, the variable
+    /// `APP_SERVER__PORT` sets `server.port`. Values are parsed as with
+    /// [`SetOverride`]. Variables starting with `prefix` must be valid
+    /// Unicode, while any other variable is ignored.
+    pub fn with_env(self, layer: &str, prefix: &str) -> Result<Self, Error> {
+        let mut overrides = Vec::new();
+        let mut variables = Vec::new();
+        for (name, value) in std::env::vars_os() {
+            if !name.as_encoded_bytes().starts_with(prefix.as_bytes()) {
+                continue;
+            }
+            match (name.into_string(), value.into_string()) {
+                (Ok(name), Ok(value)) => variables.push((name, value)),
+                (Ok(name), Err(_)) => return Err(Error::EnvNotUnicode { name: name.into() }),
+                (Err(name), _) => return Err(Error::EnvNotUnicode { name }),
+            }
+        }
+        variables.sort();
+        for (name, value) in variables {
+            let key = name[prefix.len()..].to_lowercase().replace("__", ".");
+            overrides.push(format!("{key}={value}").parse::<SetOverride>()?);
+        }
+        Ok(self.with_overrides(layer, &overrides))
+    }
+
+    /// Apply `overrides` in order as the layer `layer`
+    pub fn with_overrides(mut self, layer: &str, overrides: &[SetOverride]) -> Self {
+        for set_override in overrides {
+            let mut key_path = String::new();
+            for key in &set_override.key {
+                // A table replacing a value no longer comes from that value's layer
+                self.provenance.remove(&key_path);
+                key_path = join(&key_path, key);
+            }
+            record(&mut self.provenance, key_path, &set_override.value, layer);
+            set_override.apply(&mut self.document);
+        }
+        self.layers.push(layer.to_string());
+        self
+    }
+
+    /// Convert the merged document into `T`
+    pub fn build(&self) -> Result<T, Error> {
+        T::from_merged(self.document.clone()).map_err(|error| Error::Merged {
+            layers: self.layers.clone(),
+            source: Box::new(error),
+        })
+    }
+
+    /// Serialize the merged document as `format`, with secrets redacted
+    ///
+    /// See [`dump_effective_config`](crate::dump_effective_config) for how
+    /// secrets are recognized.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap_adapters::prelude::*;
+    ///
+    /// let layered = Layered::<JsonOf<serde_json::Value>>::new()
+    ///     .with_value("defaults", serde_json::json!({"user": "admin", "token": "abc"}))
+    ///     .with_value("user", serde_json::json!({"user": "alice"}));
+    ///
+    /// let dump = layered.dump(DumpFormat::Json)?;
+    /// assert_eq!(
+    ///     serde_json::from_str::<serde_json::Value>(&dump)?,
+    ///     serde_json::json!({"user": "alice", "token": "[redacted]"}),
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn dump(&self, format: DumpFormat) -> Result<String, Error> {
+        crate::dump_effective_config(&self.document, format)
+    }
+
+    /// Returns the name of the layer that provided the value at the dotted `key_path`
+    ///
+    /// Keys inside a value that isn't a table, such as an array, report the
+    /// layer of that value. Tables themselves have no single source.
+    pub fn source_of(&self, key_path: &str) -> Option<&str> {
+        let mut prefix = key_path;
+        loop {
+            if let Some(layer) = self.provenance.get(prefix) {
+                return Some(layer);
+            }
+            prefix = &prefix[..prefix.rfind('.')?];
+        }
+    }
+
+    /// Iterate over the dotted key paths of all values, with the layer that provided each
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use clap_adapters::prelude::*;
+    ///
+    /// let layered = Layered::<JsonOf<serde_json::Value>>::new()
+    ///     .with_value("defaults", serde_json::json!({"server": {"host": "localhost", "port": 80}}))
+    ///     .with_value("user", serde_json::json!({"server": {"port": 8080}}));
+    ///
+    /// let provenance: Vec<_> = layered.provenance().collect();
+    /// assert_eq!(provenance, [("server.host", "defaults"), ("server.port", "user")]);
+    /// ```
+    pub fn provenance(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.provenance
+            .iter()
+            .map(|(key_path, layer)| (key_path.as_str(), layer.as_str()))
+    }
+}
+
+impl<T: FromMerged> Default for Layered<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for Layered<T> {
+    fn clone(&self) -> Self {
+        Layered {
+            document: self.document.clone(),
+            layers: self.layers.clone(),
+            provenance: self.provenance.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Layered<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Layered")
+            .field("document", &self.document)
+            .field("layers", &self.layers)
+            .field("provenance", &self.provenance)
+            .finish()
+    }
+}
+
+/// Record `layer` as the source of every non-table value in `value`, merged at `key_path`
+fn record(provenance: &mut BTreeMap<String, String>, key_path: String, value: &Value, layer: &str) {
+    match value {
+        Value::Object(entries) => {
+            // A table replacing a value no longer comes from that value's layer
+            provenance.remove(&key_path);
+            for (key, value) in entries {
+                record(provenance, join(&key_path, key), value, layer);
+            }
+        }
+        _ => {
+            // A value replacing a table also replaces everything in it
+            let nested = format!("{key_path}.");
+            provenance.retain(|existing, _| !key_path.is_empty() && !existing.starts_with(&nested));
+            provenance.insert(key_path, layer.to_string());
+        }
+    }
+}
+
+/// Append `key` to the dotted `key_path`
+fn join(key_path: &str, key: &str) -> String {
+    match key_path {
+        "" => key.to_string(),
+        _ => format!("{key_path}.{key}"),
+    }
+}
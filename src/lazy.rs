@@ -0,0 +1,101 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::{fs::PathTo, traits::FromPath, Error};
+
+/// An adapter that checks the path at parse time but defers loading the file
+///
+/// `Lazy<PathTo<T>>` verifies that the user-given path refers to an existing
+/// file while parsing arguments, so that typos are still reported up front,
+/// but only reads and parses the file the first time it is accessed. This is
+/// useful for arguments that are only needed by some subcommands.
+///
+/// Once loaded successfully, the document is cached. If loading fails, the
+/// error is returned and loading is retried on the next access.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Json config that's only loaded when needed
+///     #[clap(long)]
+///     config: Lazy<PathTo<JsonOf<serde_json::Value>>>,
+/// }
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+///
+/// // Parsing only checks that the file exists
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+///
+/// // The file is read on first access
+/// let config = cli.config.get()?;
+/// assert_eq!(config.data.0, serde_json::json!({"hello":"world"}));
+///
+/// // Missing files are still rejected while parsing
+/// let missing_path = config_dir.path().join("missing.json");
+/// let missing_path_string = missing_path.display().to_string();
+/// assert!(Cli::try_parse_from(["app", "--config", &missing_path_string]).is_err());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Lazy<T> {
+    path: PathBuf,
+    value: OnceLock<T>,
+}
+
+impl<T: FromPath> Lazy<PathTo<T>> {
+    /// Check that `path` points to a file, deferring loading it until first access
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+        let metadata = std::fs::metadata(&path).map_err(|error| Error::io(&path, error))?;
+        if !metadata.is_file() {
+            return Err(Error::NotAFile { path });
+        }
+
+        let item = Lazy {
+            path,
+            value: OnceLock::new(),
+        };
+        Ok(item)
+    }
+
+    /// Returns the path given as an argument by the user
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the loaded document, loading it on first access
+    pub fn get(&self) -> Result<&PathTo<T>, Error> {
+        if let Some(path_to) = self.value.get() {
+            return Ok(path_to);
+        }
+
+        let path_to = PathTo::load(self.path.clone())?;
+        Ok(self.value.get_or_init(|| path_to))
+    }
+
+    /// Returns the owned document, loading it if it hasn't been accessed yet
+    pub fn into_inner(self) -> Result<PathTo<T>, Error> {
+        match self.value.into_inner() {
+            Some(path_to) => Ok(path_to),
+            None => PathTo::load(self.path),
+        }
+    }
+}
+
+impl<T: FromPath> std::str::FromStr for Lazy<PathTo<T>> {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
@@ -12,13 +12,141 @@
 //!
 //! - `Vec<u8>`
 //! - `String`
-//! - `JsonOf<T>` (where `T: serde::DeserializeOwned`)
-//! - `TomlOf<T>` (where `T: serde::DeserializeOwned`)
-//! - `YamlOf<T>` (where `T: serde::DeserializeOwned`)
+//! - `bytes::Bytes` (with the `bytes` feature)
+//! - `serde_json::Value`
+//! - Integers, floats, `bool` and IP/socket addresses, parsed from the
+//!   file's contents with surrounding whitespace ignored
+//! - `JsonOf<T>` (where `T: serde::DeserializeOwned`, with the `json` feature)
+//! - `TomlOf<T>` (where `T: serde::DeserializeOwned`, with the `toml` feature)
+//! - `YamlOf<T>` (where `T: serde::DeserializeOwned`, with the `yaml` feature)
+//!
+//! The format features are enabled by default through the `full` feature.
+//! CLIs that only need e.g. `PathTo<String>` may disable default features
+//! to avoid compiling the Toml and Yaml parsers.
+//!
+//! Yaml is parsed with the deprecated `serde_yaml` by default. Enable the
+//! `yaml-ng` feature, with or instead of `yaml`, to parse it with the
+//! maintained `serde_yaml_ng` fork.
+//!
+//! Types that need to see the path itself, e.g. to pick a format by file
+//! extension, may implement [`FromPath`] instead. `AnyOf<T>` does exactly
+//! that, using the parsers in the [`FormatRegistry`], to which applications
+//! may add their own formats.
+//!
+//! With the `async` feature, `PathTo::load_async` loads documents through
+//! the [`AsyncFromReader`] trait without blocking a tokio runtime.
+//!
+//! For Json arrays too large to hold in memory, use `JsonSeqOf<T>`, which
+//! checks every item while parsing and then reads them back one at a time.
+//! With the `parquet` feature, `PathTo<ParquetOf<T>>` deserializes each row
+//! of a Parquet file as a `T`.
+//!
+//! To load a whole directory of config fragments (e.g. `conf.d/`), use
+//! `DirOf<T>`, which deep-merges every matching file in the directory into a
+//! single `T`.
+//!
+//! For secret mounts with one file per key, as in Kubernetes, `SecretDirOf<T>`
+//! reads each file into a map of file names to contents.
+//!
+//! For files holding secrets, wrap the adapter as `Private<PathTo<T>>` to
+//! refuse loading files that are accessible by other users on Unix.
+//! Secrets passed as inherited file descriptors, as systemd does, can be
+//! loaded from `fd:N` arguments with `FdTo<T>` on Unix.
+//! Under systemd, `CredentialOf<T>` instead takes the name of a credential
+//! and loads it from `$CREDENTIALS_DIRECTORY`.
+//! With the `encrypted` feature, `EncryptedOf<T, K>` decrypts AES-256-GCM
+//! encrypted files with a key taken from an environment variable.
+//!
+//! Runtime knobs share one syntax: wrap an adapter as `WithOptions<T>` to
+//! accept options appended to the argument, as in
+//! `config.yaml?max_size=1M&interval=30s`. Each adapter takes the options it
+//! understands through the [`FromOptions`] trait.
+//!
+//! For a flag that accepts either a URL or a local path, use `Either<A, B>`,
+//! which parses URLs as `A` and anything else as `B`.
+//!
+//! To fall back through several adapters for the same argument, e.g. from a
+//! network source to a local copy, use `FirstOf<(A, B)>`, which records
+//! which adapter succeeded.
+//!
+//! To check a path at parse time but only load the file when it's first
+//! needed, wrap the adapter as `Lazy<PathTo<T>>`.
+//!
+//! Paths are used verbatim by default. Wrap any adapter as `Expand<T>`
+//! (e.g. `Expand<PathTo<T>>`) to expand a leading `~` and `$VAR`s first.
+//!
+//! To let users tweak single keys from the command line, load the file as
+//! `Overridable<T>` and apply `--set key.path=value` arguments parsed as
+//! `SetOverride`s on top of it. `SetOverride` is also available as `KeyValue`
+//! for any other `key.path=value` arguments.
+//!
+//! For documents with named profiles (e.g. `[profiles.prod]`), load the file
+//! as `ProfileOf<T>` and select a profile given by another flag.
+//! With the `derive` feature, `#[derive(ConfigArgs)]` on a config struct
+//! generates the usual `--config`, `--set` and `--profile` flags for it, with
+//! a `resolve()` method that applies them.
+//!
+//! To ship defaults with the binary, wrap a format adapter as
+//! `WithDefaults<T, D>`, which deep-merges the user's file over a default
+//! document embedded with `include_str!`.
+//!
+//! To merge several sources, such as defaults, system and user files,
+//! environment variables and `--set` overrides, build a `Layered<T>`, which
+//! records which layer provided each key's effective value.
+//!
+//! For a `--dump-config` flag, `dump_effective_config` serializes any config
+//! as Json, Toml or Yaml with the values of secret-looking keys redacted.
+//!
+//! Serde ignores unknown keys by default. Wrap a format adapter as
+//! `DenyUnknown<T>` to reject documents with misspelled keys instead.
+//!
+//! To validate a document into a domain type, parse it as
+//! `ParsedAs<Raw, T>`, which converts the parsed `Raw` with `T::try_from`.
+//!
+//! With the `miette` feature, malformed Json, Toml and Yaml documents are
+//! reported with a snippet of the file pointing at the offending location.
+//!
+//! With the `anyhow` feature, an `anyhow::Error` converts into
+//! [`Error`] with `?`, e.g. in a custom `Reload` or validation hook.
 //!
 //! Additionally, `PathTo` may be wrapped in either `Periodic<T>` or `Reloading<T>`
 //! to gain the ability to automatically _reload_ the file at the user-given path
-//! at a regular interval or when the file is updated, respectively.
+//! at a regular interval or when the file is updated, respectively. On Unix,
+//! `OnSignal<T>` reloads the file when the process receives `SIGHUP`, and
+//! `Refreshing<T>` combines file watching with a periodic reload as a fallback.
+//! `Reloading<DirOf<T>>` watches a whole directory of config fragments.
+//!
+//! With the `cron` feature, `Periodic<T, Cron<C>>` reloads at the times matched
+//! by a cron expression, such as every night at 02:00, instead of at a fixed
+//! interval. Under `WithOptions`, the user may give the expression as the
+//! `cron` option.
+//!
+//! These reloading adapters are built on tokio. For synchronous applications,
+//! the `blocking` feature provides [`blocking::Reloading`], which is powered by
+//! a plain background thread instead.
+//!
+//! The reloading adapters are built on [`ConfigWatcher`], which may also be
+//! used on its own to keep a file up to date when its path doesn't come from
+//! the command line, e.g. from an environment variable.
+//!
+//! With the `sse` feature, `SseSubscribe<T>` subscribes to a Server-Sent
+//! Events stream at a user-given URL, publishing each event's data as a new
+//! document through the same interface.
+//! Likewise, with the `mqtt` feature, `MqttSubscribe<T>` subscribes to an
+//! MQTT topic, starting from the topic's retained message.
+//!
+//! With the `figment` feature, `PathTo<T>` and the reloading adapters are
+//! `figment::Provider`s, so CLI-supplied files can be merged into an
+//! existing Figment pipeline. Likewise, with the `config` feature they are
+//! `config::Source`s, for use in a `config::Config` builder.
+//!
+//! With the `schemars` feature, `PathTo<T>` and the format adapters
+//! implement `schemars::JsonSchema` as the schema of their data, and
+//! `PathTo::<JsonOf<Config>>::schema()` generates the Json Schema that
+//! editors use to complete and validate users' config files.
+//!
+//! To test code that consumes these adapters, the `test-util` feature provides
+//! fixtures in [`test_util`] that serve in-memory documents instead of files.
 //!
 //! # Example
 //!
@@ -54,51 +182,492 @@
 //! # }
 //! ```
 //!
+//! # Falling back to an environment variable
+//!
+//! Adapters are parsed from strings, so clap's `env` attribute works with
+//! every adapter: when the flag isn't given, the path is read from the named
+//! environment variable instead, and loaded and validated exactly as if it
+//! had been passed on the command line. The flag takes precedence when both
+//! are present, and an error is only reported if neither is set.
+//!
+//! ```
+//! # fn main() -> anyhow::Result<()> {
+//! use clap::Parser;
+//! use clap_adapters::prelude::*;
+//!
+//! #[derive(Debug, Parser)]
+//! struct Cli {
+//!     /// Path to a config file of arbitrary Json
+//!     #[clap(long, env = "APP_CONFIG")]
+//!     config: PathTo<JsonOf<serde_json::Value>>,
+//! }
+//!
+//! // Create a config file in a temporary directory
+//! let config_dir = tempfile::tempdir()?;
+//! let config_path = config_dir.path().join("config.json");
+//! std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+//!
+//! // Without --config, the path is taken from $APP_CONFIG
+//! std::env::set_var("APP_CONFIG", &config_path);
+//! let cli = Cli::parse_from(["app"]);
+//! assert_eq!(cli.config.data(), &serde_json::json!({"hello":"world"}));
+//!
+//! // A path from the environment is validated like one from the flag
+//! std::env::set_var("APP_CONFIG", config_dir.path().join("missing.json"));
+//! assert!(Cli::try_parse_from(["app"]).is_err());
+//!
+//! // With neither, clap reports the missing argument
+//! std::env::remove_var("APP_CONFIG");
+//! assert!(Cli::try_parse_from(["app"]).is_err());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # WebAssembly
+//!
+//! The core adapters and formats build for `wasm32-wasip1`. Tokio's `fs` and
+//! `signal` features don't support WebAssembly, so disable default features
+//! and pick the ones needed, e.g.:
+//!
+//! ```toml
+//! clap-adapters = { version = "0.2", default-features = false, features = ["json", "toml", "yaml"] }
+//! ```
+//!
+//! WASI preview 1 has no threads. The `reloading`, `blocking`, `sse` and
+//! `mqtt` features build, but their adapters fail to parse there, reporting
+//! that their background thread couldn't be spawned.
+//!
 //! [`Path`]: std::path
 //! [`FromStr`]: std::str::FromStr
 //! [`FromReader`]: crate::traits::FromReader
+//! [`FromPath`]: crate::traits::FromPath
+//! [`FromOptions`]: crate::traits::FromOptions
+//! [`AsyncFromReader`]: crate::traits::AsyncFromReader
 
 #![warn(missing_docs)]
 
+#[cfg(any(doc, feature = "blocking"))]
+pub mod blocking;
+
+/// Adapter for parsing documents in any registered format
+mod any;
+
+/// Helpers for atomically replacing files
+mod atomic;
+
+/// Adapter for resolving names in the platform's config directory
+mod config_name;
+
+/// `config` crate sources for CLI-supplied files
+#[cfg(feature = "config")]
+mod config_source;
+
+/// Adapter for loading systemd credentials by name
+mod credential;
+
+/// Cron expressions for scheduling periodic reloads
+#[cfg(feature = "cron")]
+mod cron;
+
+/// Adapter for merging documents over embedded defaults
+mod defaults;
+
+/// Adapter for rejecting documents with unknown keys
+mod deny;
+
+/// Diagnostics with source snippets for malformed documents
+#[cfg(feature = "miette")]
+mod diagnostic;
+
+/// Adapter for loading and merging a directory of files
+mod dir;
+
+/// Helpers for dumping effective configs with secrets redacted
+mod dump;
+
+/// Adapter for arguments that may be a URL or a path
+mod either;
+
+/// Adapters for validating paths without reading them
+mod existing;
+
+/// Adapter for expanding `~` and environment variables in paths
+mod expand;
+
+/// Adapter for decrypting AES-256-GCM encrypted documents
+#[cfg(feature = "encrypted")]
+mod encrypted;
+
+/// Adapter for loading inherited file descriptors such as `fd:3`
+#[cfg(unix)]
+mod fd;
+
+/// The error returned when parsing an adapter fails
+mod error;
+
+/// Adapters for falling back through candidate paths or source adapters
+mod first;
+
 /// Adapters for reading file contents from CLI paths
 mod fs;
 
+/// Runtime-agnostic file watching for the notify-based adapters
+#[cfg(any(doc, feature = "reloading", feature = "blocking"))]
+mod fs_watch;
+
+/// Handle for triggering reloads on demand
+#[cfg(any(
+    doc,
+    feature = "periodic",
+    feature = "reloading",
+    feature = "signal",
+    feature = "sse",
+    feature = "mqtt"
+))]
+mod handle;
+
 /// Adapters for parsing JSON documents
+#[cfg(feature = "json")]
 mod json;
 
+/// Adapter for streaming the items of huge Json arrays
+#[cfg(feature = "json")]
+mod json_seq;
+
+/// Adapter for deferring file loading until first access
+mod lazy;
+
+/// Adapter for merging layered sources with provenance tracking
+mod layered;
+
+/// Helpers for deep-merging documents
+mod merge;
+
+/// Adapter for memory-mapping large files
+#[cfg(feature = "mmap")]
+mod mmap;
+
+/// Adapter for configs published to an MQTT topic
+#[cfg(feature = "mqtt")]
+mod mqtt;
+
+/// Adapter for appending `key=value` options to arguments
+mod options;
+
+/// Adapter for validating output destinations
+mod output;
+
+/// Adapter for applying `--set key=value` overrides to documents
+mod overrides;
+
+/// Adapter for deserializing the rows of Parquet files
+#[cfg(feature = "parquet")]
+mod parquet;
+
+/// Adapter for converting parsed documents into validated types
+mod parsed;
+
+/// Platform-specific path normalization
+#[cfg(any(doc, feature = "reloading", feature = "blocking"))]
+mod path;
+
+/// Clap value parser with detailed error messages
+mod parser;
+
 #[cfg(any(doc, feature = "periodic"))]
 mod periodic;
 
+/// Adapter for refusing to load files with unsafe permissions
+mod private;
+
+/// Adapter for selecting a named profile from a document
+mod profile;
+
+/// Figment providers for CLI-supplied files
+#[cfg(feature = "figment")]
+mod provider;
+
+/// Adapter for reloading file contents on change and at a regular interval
+#[cfg(any(doc, all(feature = "reloading", feature = "periodic")))]
+mod refreshing;
+
 /// Adapter for auto-reloading file contents on change
 #[cfg(any(doc, feature = "reloading"))]
 mod reloading;
 
+/// Plumbing shared by the adapters that subscribe to remote sources
+#[cfg(any(feature = "sse", feature = "mqtt"))]
+mod remote;
+
+/// Json Schema generation for config files
+#[cfg(feature = "schemars")]
+mod schema;
+
+/// Adapter for loading directories of secrets with one file per key
+mod secret_dir;
+
+/// Adapter for reloading file contents on Unix signals
+#[cfg(all(unix, any(doc, feature = "signal")))]
+mod signal;
+
+/// Adapter for documents pushed over Server-Sent Events
+#[cfg(feature = "sse")]
+mod sse;
+
+/// Fixtures for testing code that consumes adapters
+#[cfg(any(doc, feature = "test-util"))]
+pub mod test_util;
+
 /// Adapters for parsing TOML documents
+#[cfg(feature = "toml")]
 mod toml;
 
+/// Type-level durations for configuring adapter timing
+mod time;
+
 /// Traits for glueing adapters together
 pub mod traits;
 
+/// Standalone reloading of documents, independent of argument parsing
+#[cfg(any(
+    doc,
+    feature = "periodic",
+    feature = "reloading",
+    feature = "signal",
+    feature = "sse",
+    feature = "mqtt"
+))]
+pub mod watcher;
+
 /// Adapters for parsing YAML documents
+#[cfg(any(feature = "yaml", feature = "yaml-ng"))]
 mod yaml;
 
-pub use {fs::PathTo, json::JsonOf, toml::TomlOf, yaml::YamlOf};
+pub use {
+    any::{AnyOf, FormatRegistry},
+    config_name::ConfigName,
+    credential::CredentialOf,
+    defaults::WithDefaults,
+    deny::{DenyUnknown, UnknownKey, UnknownKeys},
+    dir::DirOf,
+    dump::{dump_effective_config, is_secret_key, redact, DumpFormat, REDACTED},
+    either::Either,
+    error::Error,
+    existing::{DirPath, ExistingFile, ExistingPath},
+    expand::Expand,
+    first::{FirstExisting, FirstOf, Sources},
+    fs::{LoadMetadata, PathTo},
+    layered::Layered,
+    lazy::Lazy,
+    options::{AdapterOptions, WithOptions},
+    output::OutputPathTo,
+    overrides::{KeyValue, Overridable, SetOverride},
+    parsed::ParsedAs,
+    parser::{with_value_hints, AdapterParser},
+    private::Private,
+    profile::ProfileOf,
+    secret_dir::SecretDirOf,
+};
+
+#[cfg(feature = "json")]
+pub use json::JsonOf;
+
+#[cfg(feature = "json")]
+pub use json_seq::{JsonSeqIter, JsonSeqOf};
+
+#[cfg(feature = "toml")]
+pub use toml::TomlOf;
+
+#[cfg(any(feature = "yaml", feature = "yaml-ng"))]
+pub use yaml::{YamlError, YamlOf};
+
+#[cfg(any(
+    doc,
+    feature = "periodic",
+    feature = "reloading",
+    feature = "signal",
+    feature = "sse",
+    feature = "mqtt"
+))]
+pub use handle::{ChangeSignal, ReloadError, ReloadHandle, ReloadStats, Snapshot};
+
+#[cfg(any(
+    doc,
+    feature = "periodic",
+    feature = "reloading",
+    feature = "signal",
+    feature = "sse",
+    feature = "mqtt"
+))]
+pub use watcher::ConfigWatcher;
+
+/// Generates a `<Name>Args` struct of config flags for a config struct `Name`
+///
+/// The generated [`clap::Args`] struct has three flags, to be flattened into
+/// an application's CLI:
+///
+/// - `--config <PATH>`, loaded as a [`ProfileOf`] the config struct, and
+///   completed as a file name
+/// - `--set <KEY=VALUE>`, any number of [`SetOverride`]s
+/// - `--profile <NAME>`, the profile to select, if any
+///
+/// Its `resolve()` method selects the profile, applies the overrides and
+/// converts the result into the config struct. The file is parsed as Json
+/// unless another format is given with `#[config_args(format = "toml")]`
+/// or `"yaml"`.
+///
+/// # Example
+///
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::ConfigArgs;
+///
+/// #[derive(Debug, serde::Deserialize, ConfigArgs)]
+/// struct AppConfig {
+///     host: String,
+///     port: u16,
+/// }
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     #[clap(flatten)]
+///     config: AppConfigArgs,
+/// }
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(
+///     &config_path,
+///     r#"{"host": "localhost", "port": 80, "profiles": {"prod": {"host": "example.com"}}}"#,
+/// )?;
+///
+/// let cli = Cli::parse_from([
+///     "app",
+///     "--config", &config_path_string,
+///     "--profile", "prod",
+///     "--set", "port=443",
+/// ]);
+/// let config = cli.config.resolve()?;
+/// assert_eq!(config.host, "example.com");
+/// assert_eq!(config.port, 443);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "derive")]
+pub use clap_adapters_derive::ConfigArgs;
+
+#[cfg(feature = "miette")]
+pub use diagnostic::ParseDiagnostic;
+
+#[cfg(feature = "encrypted")]
+pub use encrypted::{DecryptError, EncryptedOf};
+
+#[cfg(unix)]
+pub use fd::FdTo;
+
+#[cfg(feature = "mmap")]
+pub use mmap::MmapOf;
+
+#[cfg(feature = "mqtt")]
+pub use mqtt::MqttSubscribe;
+
+#[cfg(feature = "parquet")]
+pub use crate::parquet::ParquetOf;
+
+#[cfg(feature = "cron")]
+pub use crate::cron::{Cron, CronExpression, CronSchedule};
 
 #[cfg(any(doc, feature = "periodic"))]
 pub use periodic::Periodic;
 
+#[cfg(any(doc, all(feature = "reloading", feature = "periodic")))]
+pub use refreshing::Refreshing;
+
 #[cfg(any(doc, feature = "reloading"))]
 pub use reloading::Reloading;
 
+#[cfg(all(unix, any(doc, feature = "signal")))]
+pub use signal::OnSignal;
+
+#[cfg(feature = "sse")]
+pub use sse::SseSubscribe;
+
 /// Convenience import for clap adapter building blocks
 pub mod prelude {
+    pub use crate::any::*;
+    pub use crate::config_name::*;
+    pub use crate::credential::*;
+    #[cfg(feature = "cron")]
+    pub use crate::cron::*;
+    pub use crate::defaults::*;
+    pub use crate::deny::*;
+    pub use crate::dir::*;
+    pub use crate::dump::*;
+    pub use crate::either::*;
+    #[cfg(feature = "encrypted")]
+    pub use crate::encrypted::*;
+    pub use crate::existing::*;
+    pub use crate::expand::*;
+    #[cfg(unix)]
+    pub use crate::fd::*;
+    pub use crate::first::*;
     pub use crate::fs::*;
+    #[cfg(any(
+        doc,
+        feature = "periodic",
+        feature = "reloading",
+        feature = "signal",
+        feature = "sse",
+        feature = "mqtt"
+    ))]
+    pub use crate::handle::*;
+    #[cfg(feature = "json")]
     pub use crate::json::*;
+    #[cfg(feature = "json")]
+    pub use crate::json_seq::*;
+    pub use crate::layered::*;
+    pub use crate::lazy::*;
+    #[cfg(feature = "mmap")]
+    pub use crate::mmap::*;
+    #[cfg(feature = "mqtt")]
+    pub use crate::mqtt::*;
+    pub use crate::options::*;
+    pub use crate::output::*;
+    pub use crate::overrides::*;
+    #[cfg(feature = "parquet")]
+    pub use crate::parquet::*;
+    pub use crate::parsed::*;
+    pub use crate::parser::*;
     #[cfg(any(doc, feature = "periodic"))]
     pub use crate::periodic::*;
+    pub use crate::private::*;
+    pub use crate::profile::*;
+    #[cfg(any(doc, all(feature = "reloading", feature = "periodic")))]
+    pub use crate::refreshing::*;
     #[cfg(any(doc, feature = "reloading"))]
     pub use crate::reloading::*;
+    pub use crate::secret_dir::*;
+    #[cfg(all(unix, any(doc, feature = "signal")))]
+    pub use crate::signal::*;
+    #[cfg(feature = "sse")]
+    pub use crate::sse::*;
+    pub use crate::time::*;
+    #[cfg(feature = "toml")]
     pub use crate::toml::*;
     pub use crate::traits::*;
+    #[cfg(any(
+        doc,
+        feature = "periodic",
+        feature = "reloading",
+        feature = "signal",
+        feature = "sse",
+        feature = "mqtt"
+    ))]
+    pub use crate::watcher::*;
+    #[cfg(any(feature = "yaml", feature = "yaml-ng"))]
     pub use crate::yaml::*;
 }
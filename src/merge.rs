@@ -0,0 +1,22 @@
+use serde_json::Value;
+
+/// Recursively merges `overlay` into `base`
+///
+/// Objects are merged key-by-key, with values from `overlay` taking
+/// precedence. Any other kind of value (including arrays) in `overlay`
+/// replaces the corresponding value in `base` entirely.
+pub(crate) fn deep_merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => deep_merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
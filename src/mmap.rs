@@ -0,0 +1,141 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::parser::AdapterParser;
+use crate::Error;
+
+/// An adapter that memory-maps the file at the user-given path
+///
+/// Unlike loading a `Vec<u8>`, which copies the whole file through a buffer,
+/// [`MmapOf`] maps the file into memory, so only the pages that are actually
+/// read are loaded. This suits large model or asset files. Cloning is cheap,
+/// as clones share the same mapping.
+///
+/// The contents are available as a `&[u8]` via [`Deref`] or
+/// [`MmapOf::as_bytes`], or as a [`bytes::Bytes`] with the `bytes` feature.
+///
+/// # Modifying the mapped file
+///
+/// The mapped memory is the file itself, so it must not be modified in
+/// place or truncated for as long as the mapping is alive, by this or any
+/// other process. Otherwise, the bytes may change underneath the program,
+/// which is undefined behavior, and reading past the end of a truncated file
+/// crashes the process with `SIGBUS`. Replace the file by renaming a new one
+/// over it instead, which leaves the mapped file untouched.
+///
+/// For this reason, `MmapOf` takes the path itself rather than being loaded
+/// through `PathTo`, and can't be wrapped in reloading adapters such as
+/// `Reloading<T>` or `Periodic<T>`, which exist to follow files that change.
+/// Load a `PathTo<Vec<u8>>` instead for files that are rewritten in place.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a model file
+///     #[clap(long)]
+///     model: MmapOf,
+/// }
+///
+/// // Create a model file in a temporary directory
+/// let model_dir = tempfile::tempdir()?;
+/// let model_path = model_dir.path().join("model.bin");
+/// let model_path_string = model_path.display().to_string();
+/// std::fs::write(&model_path, [0xde, 0xad, 0xbe, 0xef])?;
+///
+/// let cli = Cli::parse_from(["app", "--model", &model_path_string]);
+/// assert_eq!(cli.model.path, model_path);
+/// assert_eq!(cli.model.as_bytes(), &[0xde, 0xad, 0xbe, 0xef]);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Deref`]: std::ops::Deref
+/// [`bytes::Bytes`]: https://docs.rs/bytes/latest/bytes/struct.Bytes.html
+#[derive(Clone)]
+pub struct MmapOf {
+    /// The path given as an argument by the user
+    pub path: PathBuf,
+
+    mmap: Arc<memmap2::Mmap>,
+}
+
+impl MmapOf {
+    /// Map the file at `path` into memory
+    ///
+    /// See [Modifying the mapped file](MmapOf#modifying-the-mapped-file) for
+    /// what the file must not undergo while it is mapped.
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+        let file = std::fs::File::open(&path).map_err(|error| Error::io(&path, error))?;
+
+        // SAFETY: The file is never modified or truncated by this crate, which
+        // neither reloads nor remaps it, and applications are documented above
+        // to not modify it in place while it is mapped
+        let mmap = unsafe { memmap2::Mmap::map(&file) }.map_err(|error| Error::io(&path, error))?;
+        Ok(MmapOf {
+            path,
+            mmap: Arc::new(mmap),
+        })
+    }
+
+    /// Returns the path given as an argument by the user
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the contents of the mapped file
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.mmap
+    }
+
+    /// Returns the contents of the mapped file as [`bytes::Bytes`], without copying
+    ///
+    /// The mapping stays alive for as long as the returned `Bytes` (or any
+    /// slice of it) does.
+    #[cfg(feature = "bytes")]
+    pub fn to_bytes(&self) -> bytes::Bytes {
+        bytes::Bytes::from_owner(self.clone())
+    }
+}
+
+impl std::str::FromStr for MmapOf {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::load(s)
+    }
+}
+
+impl clap::builder::ValueParserFactory for MmapOf {
+    type Parser = AdapterParser<Self>;
+    fn value_parser() -> Self::Parser {
+        AdapterParser::with_value_hint(clap::ValueHint::FilePath)
+    }
+}
+
+impl std::ops::Deref for MmapOf {
+    type Target = [u8];
+    fn deref(&self) -> &Self::Target {
+        self.as_bytes()
+    }
+}
+
+impl AsRef<[u8]> for MmapOf {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl std::fmt::Debug for MmapOf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MmapOf")
+            .field("path", &self.path)
+            .field("len", &self.mmap.len())
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,492 @@
+//! Provides the [`MqttSubscribe`] adapter for configs published to an MQTT topic
+
+use std::io::{BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::parser::AdapterParser;
+use crate::remote::{self, subscribe_error, Connection, Subscription};
+use crate::traits::FromReader;
+use crate::watcher::ConfigWatcher;
+use crate::Error;
+
+/// How long to wait for the topic's retained message while parsing the argument
+const FIRST_MESSAGE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long to wait before reconnecting to the broker
+const RETRY: Duration = Duration::from_secs(3);
+
+/// The keep-alive interval announced to the broker, which is pinged twice as often
+const KEEP_ALIVE: Duration = Duration::from_secs(60);
+
+/// The largest packet accepted from the broker, so a misbehaving broker can't exhaust memory
+const MAX_PACKET_SIZE: usize = 16 * 1024 * 1024;
+
+/// Distinguishes the client IDs of subscriptions within this process
+static NEXT_CLIENT: AtomicU64 = AtomicU64::new(0);
+
+/// Given an `mqtt://host[:port]/topic` URL from the user, subscribes to the
+/// topic on the MQTT broker and treats each message as a new document version
+///
+/// The topic's retained message, which brokers deliver upon subscribing, is
+/// awaited while parsing the argument and becomes the initial document, so a
+/// missing or malformed document is reported as a usage error like any other
+/// adapter. Later messages are received on a background thread and published
+/// through the [`ConfigWatcher`] interface, which [`MqttSubscribe`]
+/// dereferences to:
+///
+/// - Use [`ConfigWatcher::get`] to get the document at a given moment
+/// - Use [`ConfigWatcher::receiver`] to get a tokio [`watch::Receiver`]
+/// - Use [`ConfigWatcher::stream`] to get a stream of updated documents
+///
+/// A malformed message is reported like a failed reload, and the previous
+/// document is kept. If the connection drops, it is re-established after 3
+/// seconds, and the retained message is received again.
+///
+/// The topic must name a single topic: wildcards (`+` and `#`) are rejected,
+/// as messages from unrelated topics would replace each other's documents.
+/// Packets of more than 16 MiB are rejected, failing the connection. The port
+/// defaults to 1883. Dropping the last clone of the adapter closes the
+/// connection.
+///
+/// > **Note**: The client speaks MQTT 3.1.1 at QoS 0, without a username,
+/// > password or TLS, as this crate has no MQTT or TLS dependency. Messages
+/// > travel unencrypted, and brokers requiring authentication, as most
+/// > production brokers do, refuse the connection. Reach those through a
+/// > local bridge, or use a full MQTT client crate instead.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use std::io::{Read, Write};
+///
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// URL of an MQTT topic with Json configs
+///     #[clap(long)]
+///     config: MqttSubscribe<JsonOf<serde_json::Value>>,
+/// }
+///
+/// /// Encode a PUBLISH packet, assuming it's shorter than 128 bytes
+/// fn publish(retain: bool, topic: &str, payload: &str) -> Vec<u8> {
+///     let header = if retain { 0x31 } else { 0x30 };
+///     let length = 2 + topic.len() + payload.len();
+///     let mut packet = vec![header, length as u8, 0, topic.len() as u8];
+///     packet.extend_from_slice(topic.as_bytes());
+///     packet.extend_from_slice(payload.as_bytes());
+///     packet
+/// }
+///
+/// // Run a broker with a retained config, which then publishes an update
+/// let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+/// let url = format!("mqtt://{}/app/config", listener.local_addr()?);
+/// std::thread::spawn(move || -> std::io::Result<()> {
+///     let (mut stream, _) = listener.accept()?;
+///     let mut client = stream.try_clone()?;
+///     let mut skip_packet = || -> std::io::Result<()> {
+///         let mut header = [0; 2];
+///         client.read_exact(&mut header)?;
+///         client.read_exact(&mut vec![0; header[1] as usize])
+///     };
+///
+///     skip_packet()?; // CONNECT
+///     stream.write_all(&[0x20, 2, 0, 0])?; // CONNACK
+///     skip_packet()?; // SUBSCRIBE
+///     stream.write_all(&[0x90, 3, 0, 1, 0])?; // SUBACK
+///     stream.write_all(&publish(true, "app/config", r#"{"level": "info"}"#))?;
+///     std::thread::sleep(std::time::Duration::from_millis(100));
+///     stream.write_all(&publish(false, "app/config", r#"{"level": "debug"}"#))?;
+///     std::thread::sleep(std::time::Duration::from_secs(10));
+///     Ok(())
+/// });
+///
+/// let cli = Cli::parse_from(["app", "--config", &url]);
+/// assert_eq!(cli.config.topic, "app/config");
+/// assert_eq!(cli.config.get().0, serde_json::json!({"level": "info"}));
+///
+/// // The next message is published in the background
+/// let mut config_rx = cli.config.receiver();
+/// let runtime = tokio::runtime::Runtime::new()?;
+/// runtime.block_on(config_rx.changed())?;
+/// assert_eq!(cli.config.get().0, serde_json::json!({"level": "debug"}));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`watch::Receiver`]: tokio::sync::watch
+#[must_use = "Dropping the `MqttSubscribe` will close the connection"]
+pub struct MqttSubscribe<T> {
+    /// The URL given as an argument by the user
+    pub url: String,
+
+    /// The topic subscribed to
+    pub topic: String,
+
+    watcher: ConfigWatcher<T>,
+    subscription: Arc<Subscription>,
+}
+
+impl<T> MqttSubscribe<T>
+where
+    T: FromReader + Send + Sync + 'static,
+{
+    /// Subscribe to the topic at `url`, and wait for its retained message
+    pub fn subscribe(url: impl Into<String>) -> Result<Self, Error> {
+        let url = url.into();
+        let broker = Broker::parse(&url)?;
+
+        let connection = Arc::new(Connection::default());
+        let mut messages = broker
+            .connect(&connection)
+            .and_then(|messages| {
+                connection.set_read_timeout(Some(FIRST_MESSAGE_TIMEOUT))?;
+                Ok(messages)
+            })
+            .map_err(|source| subscribe_error(&url, source))?;
+        let message = messages
+            .next_message()
+            .and_then(|message| {
+                message.ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::UnexpectedEof,
+                        "connection closed before the first message",
+                    )
+                })
+            })
+            .map_err(|source| subscribe_error(&url, source))?;
+        connection
+            .set_read_timeout(None)
+            .map_err(|source| subscribe_error(&url, source))?;
+        let document = parse_message::<T>(&url, &message)?;
+
+        let (watcher, publish) = remote::publisher(document, url.clone());
+        let topic = broker.topic.clone();
+        let mut stream = MessageStream {
+            url: url.clone(),
+            broker,
+            connection: connection.clone(),
+        };
+        let subscription = Arc::new(Subscription {
+            connection: connection.clone(),
+        });
+        std::thread::Builder::new()
+            .spawn(move || stream.run(messages, publish))
+            .map_err(|source| subscribe_error(&url, source))?;
+
+        Ok(MqttSubscribe {
+            url,
+            topic,
+            watcher,
+            subscription,
+        })
+    }
+}
+
+impl<T> std::ops::Deref for MqttSubscribe<T> {
+    type Target = ConfigWatcher<T>;
+    fn deref(&self) -> &Self::Target {
+        &self.watcher
+    }
+}
+
+impl<T> Clone for MqttSubscribe<T> {
+    fn clone(&self) -> Self {
+        Self {
+            url: self.url.clone(),
+            topic: self.topic.clone(),
+            watcher: self.watcher.clone(),
+            subscription: self.subscription.clone(),
+        }
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for MqttSubscribe<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MqttSubscribe")
+            .field("url", &self.url)
+            .field("document", &*self.watcher.get())
+            .finish()
+    }
+}
+
+impl<T> clap::builder::ValueParserFactory for MqttSubscribe<T>
+where
+    T: FromReader + Send + Sync + 'static,
+{
+    type Parser = AdapterParser<Self>;
+    fn value_parser() -> Self::Parser {
+        AdapterParser::with_value_hint(clap::ValueHint::Url)
+    }
+}
+
+impl<T> std::str::FromStr for MqttSubscribe<T>
+where
+    T: FromReader + Send + Sync + 'static,
+{
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::subscribe(s)
+    }
+}
+
+/// Parse the payload of a message as a document
+fn parse_message<T: FromReader>(url: &str, payload: &[u8]) -> Result<T, Error> {
+    T::from_reader(&mut &*payload).map_err(|error| Error::parse(PathBuf::from(url), error))
+}
+
+/// Receives messages in the background, reconnecting whenever the connection drops
+struct MessageStream {
+    url: String,
+    broker: Broker,
+    connection: Arc<Connection>,
+}
+
+impl MessageStream {
+    /// Pass each parsed message to `publish` until it returns `false` or the subscription is closed
+    fn run<T, F>(&mut self, mut messages: PacketReader, mut publish: F)
+    where
+        T: FromReader,
+        F: FnMut(Result<T, Error>) -> bool,
+    {
+        loop {
+            let error = loop {
+                match messages.next_message() {
+                    Ok(Some(message)) => {
+                        if !publish(parse_message(&self.url, &message)) {
+                            return;
+                        }
+                    }
+                    Ok(None) => {
+                        break std::io::Error::new(
+                            std::io::ErrorKind::UnexpectedEof,
+                            "connection closed",
+                        )
+                    }
+                    Err(error) => break error,
+                }
+            };
+            if self.connection.is_closed() {
+                return;
+            }
+
+            let mut error = subscribe_error(&self.url, error);
+            messages = loop {
+                if !publish(Err(error)) {
+                    return;
+                }
+                std::thread::sleep(RETRY);
+                if self.connection.is_closed() {
+                    return;
+                }
+
+                match self.broker.connect(&self.connection) {
+                    Ok(messages) => break messages,
+                    Err(source) => error = subscribe_error(&self.url, source),
+                }
+            };
+        }
+    }
+}
+
+/// The parts of an `mqtt://` URL needed to subscribe to it
+struct Broker {
+    host: String,
+    port: u16,
+    topic: String,
+}
+
+impl Broker {
+    fn parse(url: &str) -> Result<Self, Error> {
+        let invalid = |message: &str| {
+            subscribe_error(
+                url,
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, message),
+            )
+        };
+
+        let rest = url
+            .strip_prefix("mqtt://")
+            .ok_or_else(|| invalid("only mqtt:// URLs are supported"))?;
+        let (authority, topic) = rest
+            .split_once('/')
+            .ok_or_else(|| invalid("missing topic"))?;
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => {
+                let port = port.parse().map_err(|_| invalid("invalid port"))?;
+                (host, port)
+            }
+            _ => (authority, 1883),
+        };
+        if host.is_empty() {
+            return Err(invalid("missing host"));
+        }
+        if topic.is_empty() || topic.len() > usize::from(u16::MAX) {
+            return Err(invalid("invalid topic"));
+        }
+        if topic.contains(['+', '#']) {
+            return Err(invalid("wildcard topics are not supported"));
+        }
+
+        Ok(Broker {
+            host: host.to_string(),
+            port,
+            topic: topic.to_string(),
+        })
+    }
+
+    /// Connect and subscribe to the topic, returning a reader of its messages
+    fn connect(&self, connection: &Arc<Connection>) -> std::io::Result<PacketReader> {
+        let mut socket = connection.open(&self.host, self.port)?;
+
+        // CONNECT with a clean session, so no state outlives the connection
+        let client_id = format!(
+            "cfg-{}-{}",
+            std::process::id(),
+            NEXT_CLIENT.fetch_add(1, Ordering::Relaxed)
+        );
+        let mut connect = Vec::new();
+        put_string(&mut connect, "MQTT");
+        connect.push(4); // Protocol level of MQTT 3.1.1
+        connect.push(0x02); // Clean session
+        connect.extend_from_slice(&(KEEP_ALIVE.as_secs() as u16).to_be_bytes());
+        put_string(&mut connect, &client_id);
+        write_packet(&mut socket, 0x10, &connect)?;
+
+        let mut packets = PacketReader {
+            reader: BufReader::new(socket.try_clone()?),
+        };
+        let (header, body) = packets.expect_packet()?;
+        if header >> 4 != 2 || body.len() != 2 {
+            return Err(protocol_error("expected CONNACK"));
+        }
+        if body[1] != 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionRefused,
+                format!("broker refused the connection with code {}", body[1]),
+            ));
+        }
+
+        // SUBSCRIBE to the topic at QoS 0
+        let mut subscribe = vec![0, 1];
+        put_string(&mut subscribe, &self.topic);
+        subscribe.push(0);
+        write_packet(&mut socket, 0x82, &subscribe)?;
+
+        let (header, body) = packets.expect_packet()?;
+        if header >> 4 != 9 || body.len() != 3 {
+            return Err(protocol_error("expected SUBACK"));
+        }
+        if body[2] == 0x80 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                "broker refused the subscription",
+            ));
+        }
+
+        // Ping the broker within the keep-alive interval until the connection is replaced or closed
+        let connection = connection.clone();
+        std::thread::Builder::new().spawn(move || loop {
+            std::thread::sleep(KEEP_ALIVE / 2);
+            if connection.is_closed() || write_packet(&mut socket, 0xC0, &[]).is_err() {
+                return;
+            }
+        })?;
+
+        Ok(packets)
+    }
+}
+
+/// Reads MQTT control packets from a connection
+struct PacketReader {
+    reader: BufReader<TcpStream>,
+}
+
+impl PacketReader {
+    /// Read the payload of the next PUBLISH packet, skipping any others
+    fn next_message(&mut self) -> std::io::Result<Option<Vec<u8>>> {
+        while let Some((header, body)) = self.next_packet()? {
+            if header >> 4 != 3 {
+                continue;
+            }
+
+            let topic_len = body
+                .get(..2)
+                .map(|len| usize::from(u16::from_be_bytes([len[0], len[1]])))
+                .ok_or_else(|| protocol_error("truncated PUBLISH"))?;
+            let qos = (header >> 1) & 0x03;
+            let packet_id_len = if qos > 0 { 2 } else { 0 };
+            let payload = body
+                .get(2 + topic_len + packet_id_len..)
+                .ok_or_else(|| protocol_error("truncated PUBLISH"))?;
+            return Ok(Some(payload.to_vec()));
+        }
+        Ok(None)
+    }
+
+    /// Read the next packet, failing if the connection was closed
+    fn expect_packet(&mut self) -> std::io::Result<(u8, Vec<u8>)> {
+        self.next_packet()?
+            .ok_or_else(|| std::io::ErrorKind::UnexpectedEof.into())
+    }
+
+    /// Read the next packet's fixed header byte and body
+    fn next_packet(&mut self) -> std::io::Result<Option<(u8, Vec<u8>)>> {
+        let mut header = [0];
+        if self.reader.read(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let mut length = 0;
+        for shift in (0..4).map(|index| index * 7) {
+            let mut byte = [0];
+            self.reader.read_exact(&mut byte)?;
+            length |= usize::from(byte[0] & 0x7F) << shift;
+            if byte[0] & 0x80 == 0 {
+                if length > MAX_PACKET_SIZE {
+                    return Err(protocol_error(&format!(
+                        "packet of {length} bytes is larger than the maximum of {MAX_PACKET_SIZE} bytes"
+                    )));
+                }
+                let mut body = vec![0; length];
+                self.reader.read_exact(&mut body)?;
+                return Ok(Some((header[0], body)));
+            }
+        }
+        Err(protocol_error("malformed remaining length"))
+    }
+}
+
+/// Write a packet with the given fixed header byte and body
+fn write_packet(socket: &mut TcpStream, header: u8, body: &[u8]) -> std::io::Result<()> {
+    let mut packet = vec![header];
+    let mut length = body.len();
+    loop {
+        let byte = (length % 128) as u8;
+        length /= 128;
+        if length == 0 {
+            packet.push(byte);
+            break;
+        }
+        packet.push(byte | 0x80);
+    }
+    packet.extend_from_slice(body);
+    socket.write_all(&packet)
+}
+
+/// Append a length-prefixed UTF-8 string
+fn put_string(buffer: &mut Vec<u8>, value: &str) {
+    buffer.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    buffer.extend_from_slice(value.as_bytes());
+}
+
+/// An error for a packet that violates the protocol
+fn protocol_error(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+}
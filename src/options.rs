@@ -0,0 +1,236 @@
+use std::time::Duration;
+
+use crate::{traits::FromOptions, Error};
+
+/// An adapter that accepts `key=value` options appended to the argument
+///
+/// The argument is split at its last `?` into the value handed to the inner
+/// adapter and a query of `&`-separated options, as in
+/// `config.yaml?format=yaml&interval=30s&max_size=1M`. Each adapter takes
+/// the options it understands via [`FromOptions`]. Options understood by the
+/// built-in adapters:
+///
+/// - `max_size` (`PathTo`): refuse files larger than e.g. `512K` or `1M`
+/// - `format` (`AnyOf`): parse as the given format instead of by extension
+/// - `interval` and `jitter` (`Periodic`): override the type-level timing
+/// - `cron` (`Periodic`, with the `cron` feature): reload on a cron schedule
+///
+/// Arguments without a `?` are passed through unchanged, with no options, as
+/// are arguments whose query is not made only of options understood by the
+/// adapter. This keeps paths containing a `?`, such as `/srv/cfg?v2/app.json`,
+/// working.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a config in any format, with options
+///     #[clap(long)]
+///     config: WithOptions<PathTo<AnyOf<serde_json::Value>>>,
+/// }
+///
+/// // Create a Yaml config file without an extension
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config");
+/// std::fs::write(&config_path, "hello: world\n")?;
+///
+/// let argument = format!("{}?format=yaml&max_size=1K", config_path.display());
+/// let cli = Cli::parse_from(["app", "--config", &argument]);
+/// assert_eq!(cli.config.path, config_path);
+/// assert_eq!(cli.config.data.0, serde_json::json!({"hello":"world"}));
+///
+/// // With an option that no adapter understands, the whole argument is the path
+/// let argument = format!("{}?format=yaml&colour=blue", config_path.display());
+/// let error = Cli::try_parse_from(["app", "--config", &argument]).unwrap_err();
+/// assert!(error.to_string().contains("colour=blue"));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct WithOptions<T>(pub T);
+
+impl<T> std::ops::Deref for WithOptions<T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> std::ops::DerefMut for WithOptions<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T: FromOptions> std::str::FromStr for WithOptions<T> {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (value, mut options) = AdapterOptions::split(s, T::takes_option)?;
+        let inner = T::from_options(value, &mut options)?;
+        options.finish()?;
+        Ok(WithOptions(inner))
+    }
+}
+
+/// The `key=value` options appended to an argument, see [`WithOptions`]
+///
+/// Adapters remove the options they understand with the `take` methods, so
+/// that [`AdapterOptions::finish`] can reject any that remain.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use std::time::Duration;
+///
+/// use clap_adapters::prelude::*;
+///
+/// let is_known = |key: &str| matches!(key, "interval" | "max_size" | "format");
+/// let (path, mut options) = AdapterOptions::split("config.json?interval=30s&max_size=1M", is_known)?;
+/// assert_eq!(path, "config.json");
+/// assert_eq!(options.take_duration("interval")?, Some(Duration::from_secs(30)));
+/// assert_eq!(options.take_size("max_size")?, Some(1024 * 1024));
+/// assert_eq!(options.take("format"), None);
+/// options.finish()?;
+///
+/// // A `?` that isn't followed by known options is part of the path
+/// let (path, options) = AdapterOptions::split("/srv/cfg?v2/app.json", is_known)?;
+/// assert_eq!(path, "/srv/cfg?v2/app.json");
+/// assert!(options.is_empty());
+///
+/// let (path, mut options) = AdapterOptions::split("/srv/cfg?v2/app.json?format=json", is_known)?;
+/// assert_eq!(path, "/srv/cfg?v2/app.json");
+/// assert_eq!(options.take("format").as_deref(), Some("json"));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AdapterOptions {
+    entries: Vec<(String, String)>,
+}
+
+impl AdapterOptions {
+    /// Split `input` at its last `?` into the value and its options
+    ///
+    /// The query after the `?` is only taken as options if each of them is
+    /// a `key=value` pair whose key satisfies `is_known`. Otherwise, the
+    /// whole input is the value, with no options.
+    pub fn split(input: &str, is_known: impl Fn(&str) -> bool) -> Result<(&str, Self), Error> {
+        let unsplit = Ok((input, Self::default()));
+        let Some((value, query)) = input.rsplit_once('?') else {
+            return unsplit;
+        };
+
+        let mut entries: Vec<(String, String)> = Vec::new();
+        for option in query.split('&').filter(|option| !option.is_empty()) {
+            let Some((key, value)) = option.split_once('=') else {
+                return unsplit;
+            };
+            if !is_known(key) {
+                return unsplit;
+            }
+            if entries.iter().any(|(existing, _)| existing == key) {
+                return Err(invalid(option, "the option is given more than once"));
+            }
+            entries.push((key.to_string(), value.to_string()));
+        }
+        Ok((value, Self { entries }))
+    }
+
+    /// Returns true if no options remain
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Remove and return the value of the option `key`
+    pub fn take(&mut self, key: &str) -> Option<String> {
+        let index = self.entries.iter().position(|(name, _)| name == key)?;
+        Some(self.entries.remove(index).1)
+    }
+
+    /// Remove and parse the option `key` as a duration such as `500ms` or `30s`
+    ///
+    /// The units `ms`, `s`, `m`, `h` and `d` are supported, and a bare number
+    /// is taken as seconds.
+    pub fn take_duration(&mut self, key: &str) -> Result<Option<Duration>, Error> {
+        let Some(value) = self.take(key) else {
+            return Ok(None);
+        };
+        let (number, unit) = split_unit(&value);
+        let seconds_per_unit = match unit {
+            "ms" => 0.001,
+            "" | "s" => 1.0,
+            "m" => 60.0,
+            "h" => 60.0 * 60.0,
+            "d" => 24.0 * 60.0 * 60.0,
+            _ => return Err(invalid_value(key, &value, DURATION_EXPECTED)),
+        };
+        number
+            .parse::<f64>()
+            .ok()
+            .and_then(|number| Duration::try_from_secs_f64(number * seconds_per_unit).ok())
+            .map(Some)
+            .ok_or_else(|| invalid_value(key, &value, DURATION_EXPECTED))
+    }
+
+    /// Remove and parse the option `key` as a size in bytes, such as `512K` or `1M`
+    ///
+    /// The suffixes `K`, `M` and `G` are powers of 1024, optionally followed
+    /// by `B` or `iB`, and a bare number is taken as bytes.
+    pub fn take_size(&mut self, key: &str) -> Result<Option<u64>, Error> {
+        let Some(value) = self.take(key) else {
+            return Ok(None);
+        };
+        let (number, unit) = split_unit(&value);
+        let unit = unit.strip_suffix("iB").unwrap_or(unit);
+        let unit = unit.strip_suffix('B').unwrap_or(unit);
+        let multiplier: u64 = match unit.to_ascii_uppercase().as_str() {
+            "" => 1,
+            "K" => 1 << 10,
+            "M" => 1 << 20,
+            "G" => 1 << 30,
+            _ => return Err(invalid_value(key, &value, "expected a size such as 1M")),
+        };
+        number
+            .parse::<u64>()
+            .ok()
+            .and_then(|number| number.checked_mul(multiplier))
+            .map(Some)
+            .ok_or_else(|| invalid_value(key, &value, "expected a size such as 1M"))
+    }
+
+    /// Returns an error if any option has not been taken by an adapter
+    pub fn finish(self) -> Result<(), Error> {
+        match self.entries.into_iter().next() {
+            Some((key, _)) => Err(Error::UnknownOption { key }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The reason given for durations that can't be parsed
+const DURATION_EXPECTED: &str = "expected a duration such as 30s";
+
+/// Split `value` into its leading number and trailing unit
+fn split_unit(value: &str) -> (&str, &str) {
+    let index = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    value.split_at(index)
+}
+
+fn invalid(input: &str, reason: &str) -> Error {
+    Error::InvalidOption {
+        input: input.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+fn invalid_value(key: &str, value: &str, reason: &str) -> Error {
+    invalid(&format!("{key}={value}"), reason)
+}
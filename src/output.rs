@@ -0,0 +1,166 @@
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use crate::traits::ToWriter;
+use crate::Error;
+
+/// An adapter for output destinations, the counterpart to [`PathTo`]
+///
+/// Rather than loading a file, [`OutputPathTo`] checks while parsing that the
+/// file can be written: its parent directory must exist and be writable, and
+/// the path must not point to a directory. An existing file is left alone
+/// until it is written.
+///
+/// By default, a missing parent directory is an error. Use
+/// `OutputPathTo<T, CreateMissing>` to create it (and its ancestors) instead.
+///
+/// - Use [`OutputPathTo::save`] to atomically write a document of type `T`
+/// - Use [`OutputPathTo::write_atomic`] to atomically write raw bytes
+/// - Use [`OutputPathTo::create`] to stream output into the file
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to write a Json report to
+///     #[clap(long)]
+///     output: OutputPathTo<JsonOf<serde_json::Value>, CreateMissing>,
+/// }
+///
+/// // Write to a directory that doesn't exist yet
+/// let output_dir = tempfile::tempdir()?;
+/// let output_path = output_dir.path().join("reports/report.json");
+/// let output_path_string = output_path.display().to_string();
+///
+/// let cli = Cli::parse_from(["app", "--output", &output_path_string]);
+/// cli.output.save(&JsonOf(serde_json::json!({"hello":"world"})))?;
+///
+/// let saved: PathTo<JsonOf<serde_json::Value>> = output_path_string.parse()?;
+/// assert_eq!(saved.data.0, serde_json::json!({"hello":"world"}));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`PathTo`]: crate::PathTo
+pub struct OutputPathTo<T, P: ParentDir = MustExist> {
+    /// The path given as an argument by the user
+    pub path: PathBuf,
+
+    marker: PhantomData<fn() -> (T, P)>,
+}
+
+impl<T, P: ParentDir> OutputPathTo<T, P> {
+    /// Returns the path given as an argument by the user
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Create or truncate the file, returning a buffered writer to it
+    pub fn create(&self) -> Result<std::io::BufWriter<std::fs::File>, Error> {
+        let file =
+            std::fs::File::create(&self.path).map_err(|error| Error::io(&self.path, error))?;
+        Ok(std::io::BufWriter::new(file))
+    }
+
+    /// Replace the file's contents with `contents` all at once
+    ///
+    /// The contents are written to a temporary file in the same directory,
+    /// which is then renamed over the path, so that readers never observe a
+    /// partially written file.
+    pub fn write_atomic(&self, contents: impl AsRef<[u8]>) -> Result<(), Error> {
+        use std::io::Write;
+
+        crate::atomic::write(&self.path, |writer| {
+            writer
+                .write_all(contents.as_ref())
+                .map_err(|error| Error::io(&self.path, error))
+        })
+    }
+}
+
+impl<T: ToWriter, P: ParentDir> OutputPathTo<T, P> {
+    /// Atomically replace the file's contents with `document`
+    ///
+    /// See [`OutputPathTo::write_atomic`] for how the file is replaced.
+    pub fn save(&self, document: &T) -> Result<(), Error> {
+        crate::atomic::write(&self.path, |writer| {
+            document
+                .to_writer(writer)
+                .map_err(|error| Error::serialize(self.path.clone(), error))
+        })
+    }
+}
+
+impl<T, P: ParentDir> std::str::FromStr for OutputPathTo<T, P> {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let path = PathBuf::from(s);
+        if path.is_dir() {
+            return Err(Error::NotAFile { path });
+        }
+
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        if P::CREATE && !parent.exists() {
+            std::fs::create_dir_all(parent).map_err(|error| Error::io(parent, error))?;
+        }
+
+        let metadata = std::fs::metadata(parent).map_err(|error| Error::io(parent, error))?;
+        if !metadata.is_dir() {
+            return Err(Error::NotADirectory {
+                path: parent.to_path_buf(),
+            });
+        }
+        crate::existing::ensure_writable(parent)?;
+
+        let item = OutputPathTo {
+            path,
+            marker: PhantomData,
+        };
+        Ok(item)
+    }
+}
+
+impl<T, P: ParentDir> Clone for OutputPathTo<T, P> {
+    fn clone(&self) -> Self {
+        OutputPathTo {
+            path: self.path.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T, P: ParentDir> std::fmt::Debug for OutputPathTo<T, P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OutputPathTo")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+/// Trait for type markers configuring how [`OutputPathTo`] treats a missing parent directory
+pub trait ParentDir {
+    /// Whether a missing parent directory is created while parsing
+    const CREATE: bool;
+}
+
+/// Requires the parent directory of the output path to exist
+#[derive(Debug, Clone, Copy)]
+pub enum MustExist {}
+impl ParentDir for MustExist {
+    const CREATE: bool = false;
+}
+
+/// Creates the parent directory of the output path, and its ancestors, if missing
+#[derive(Debug, Clone, Copy)]
+pub enum CreateMissing {}
+impl ParentDir for CreateMissing {
+    const CREATE: bool = true;
+}
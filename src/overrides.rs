@@ -0,0 +1,244 @@
+use std::marker::PhantomData;
+use std::path::PathBuf;
+
+use crate::{traits::FromMerged, Error};
+
+/// An adapter that applies `--set key=value` overrides on top of a document
+///
+/// The document at the user-given path is kept as a value tree, so that
+/// [`SetOverride`]s parsed from other arguments can be deep-applied to it
+/// before it's converted into `T`. This lets users tweak a single key
+/// without editing the file. Since the document is only converted once the
+/// overrides are applied, an override may also supply a key that `T`
+/// requires but the file lacks.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Clone, serde::Deserialize)]
+/// struct Config {
+///     host: String,
+///     port: u16,
+/// }
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Json config
+///     #[clap(long)]
+///     config: Overridable<JsonOf<Config>>,
+///
+///     /// Override a key in the config, e.g. `--set port=8080`
+///     #[clap(long = "set")]
+///     overrides: Vec<SetOverride>,
+/// }
+///
+/// // Create a config file without a port in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, r#"{"host": "localhost"}"#)?;
+///
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string, "--set", "port=8080"]);
+/// let config = cli.config.with_overrides(&cli.overrides)?;
+/// assert_eq!(config.0.host, "localhost");
+/// assert_eq!(config.0.port, 8080);
+///
+/// // Without the override, the port is missing
+/// assert!(cli.config.resolve().is_err());
+/// # Ok(())
+/// # }
+/// ```
+pub struct Overridable<T> {
+    /// The path given as an argument by the user
+    pub path: PathBuf,
+
+    /// The document as a value tree, as loaded from the file
+    pub document: serde_json::Value,
+
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: FromMerged> Overridable<T> {
+    /// Load the file at `path`, keeping its value tree for applying overrides
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+        let file = std::fs::File::open(&path).map_err(|error| Error::io(&path, error))?;
+        let mut reader = std::io::BufReader::new(file);
+        let document =
+            T::value_from_reader(&mut reader).map_err(|error| Error::parse(path.clone(), error))?;
+        let item = Overridable {
+            path,
+            document,
+            marker: PhantomData,
+        };
+        Ok(item)
+    }
+
+    /// Convert the document into `T` without any overrides
+    pub fn resolve(&self) -> Result<T, Error> {
+        self.with_overrides(&[])
+    }
+
+    /// Apply `overrides` in order on top of the document, then convert it into `T`
+    ///
+    /// If the overridden document fails to convert into `T`, the error is
+    /// reported against the file's path.
+    pub fn with_overrides(&self, overrides: &[SetOverride]) -> Result<T, Error> {
+        let mut document = self.document.clone();
+        for set_override in overrides {
+            set_override.apply(&mut document);
+        }
+        T::from_merged(document).map_err(|error| Error::parse(self.path.clone(), error))
+    }
+}
+
+impl<T: FromMerged> std::str::FromStr for Overridable<T> {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::load(s)
+    }
+}
+
+impl<T> Clone for Overridable<T> {
+    fn clone(&self) -> Self {
+        Overridable {
+            path: self.path.clone(),
+            document: self.document.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Overridable<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Overridable")
+            .field("path", &self.path)
+            .field("document", &self.document)
+            .finish()
+    }
+}
+
+/// A `key.path=value` argument, e.g. for overriding a single key in a document
+///
+/// The key is split on `.` into nested keys. The value is parsed as Json if
+/// possible (e.g. `8080`, `true` or `["a", "b"]`), and is otherwise taken as
+/// a plain string, so `--set host=example.com` needs no quoting. A value that
+/// starts like a Json string, array or object must be valid Json, so that a
+/// typo isn't silently taken as a string.
+///
+/// `SetOverride` is useful on its own as well, available under the name
+/// [`KeyValue`]. Use [`SetOverride::value_as`] to convert the value to a
+/// concrete type.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Set a key to a value, e.g. `--set server.port=8080`
+///     #[clap(long = "set")]
+///     settings: Vec<KeyValue>,
+/// }
+///
+/// let cli = Cli::parse_from(["app", "--set", "server.port=8080", "--set", "server.host=example.com"]);
+/// assert_eq!(cli.settings[0].key, ["server", "port"]);
+/// assert_eq!(cli.settings[0].key_path(), "server.port");
+/// assert_eq!(cli.settings[0].value_as::<u16>()?, 8080);
+/// assert_eq!(cli.settings[1].value, serde_json::json!("example.com"));
+///
+/// // Malformed arguments are rejected with a reason
+/// let error = Cli::try_parse_from(["app", "--set", "server.port"]).unwrap_err();
+/// assert!(error.to_string().contains("expected key=value"));
+/// let error = Cli::try_parse_from(["app", "--set", r#"tags=["a""#]).unwrap_err();
+/// assert!(error.to_string().contains("invalid Json value"));
+///
+/// // So are values of the wrong type
+/// assert!(cli.settings[1].value_as::<u16>().is_err());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetOverride {
+    /// The nested keys leading to the overridden value
+    pub key: Vec<String>,
+
+    /// The value to set
+    pub value: serde_json::Value,
+}
+
+/// A `key.path=value` argument, see [`SetOverride`]
+pub type KeyValue = SetOverride;
+
+impl SetOverride {
+    /// Returns the key with its parts joined by `.`, as given by the user
+    pub fn key_path(&self) -> String {
+        self.key.join(".")
+    }
+
+    /// Convert the value to `T`
+    pub fn value_as<T: serde::de::DeserializeOwned>(&self) -> Result<T, Error> {
+        T::deserialize(&self.value).map_err(|source| Error::OverrideValue {
+            key: self.key_path(),
+            source,
+        })
+    }
+
+    /// Set this override's value in `document`, creating missing tables along the way
+    ///
+    /// Tables are merged as with [`DirOf`], so setting a table value only
+    /// replaces the keys it contains.
+    ///
+    /// [`DirOf`]: crate::DirOf
+    pub fn apply(&self, document: &mut serde_json::Value) {
+        let overlay = self
+            .key
+            .iter()
+            .rev()
+            .fold(self.value.clone(), |value, key| {
+                serde_json::Value::Object([(key.clone(), value)].into_iter().collect())
+            });
+        crate::merge::deep_merge(document, overlay);
+    }
+}
+
+impl clap::builder::ValueParserFactory for SetOverride {
+    type Parser = crate::AdapterParser<Self>;
+    fn value_parser() -> Self::Parser {
+        crate::AdapterParser::new()
+    }
+}
+
+impl std::str::FromStr for SetOverride {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: String| Error::InvalidOverride {
+            input: s.to_string(),
+            reason,
+        };
+
+        let (key, value) = s
+            .split_once('=')
+            .ok_or_else(|| invalid("expected key=value".to_string()))?;
+        let key: Vec<String> = key.split('.').map(str::to_string).collect();
+        if key.iter().any(|part| part.is_empty()) {
+            return Err(invalid("key must not contain empty parts".to_string()));
+        }
+
+        let value = match serde_json::from_str(value) {
+            Ok(value) => value,
+            Err(error) if value.trim_start().starts_with(['"', '[', '{']) => {
+                return Err(invalid(format!("invalid Json value: {error}")));
+            }
+            Err(_) => serde_json::Value::String(value.to_string()),
+        };
+        Ok(SetOverride { key, value })
+    }
+}
@@ -0,0 +1,111 @@
+use std::path::Path;
+
+use ::parquet::file::reader::{FileReader, SerializedFileReader};
+use serde::de::DeserializeOwned;
+
+use crate::traits::FromPath;
+use crate::Error;
+
+/// An adapter for deserializing the rows of a Parquet file
+///
+/// Each row is converted to a Json object keyed by column name, and then
+/// deserialized as `T`, so `T` is typically a struct with a field per
+/// column. Load it as `PathTo<ParquetOf<T>>`, which reports a row that
+/// fails to deserialize against the file's path, along with its index.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use std::sync::Arc;
+///
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+/// use parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+/// use parquet::file::writer::SerializedFileWriter;
+/// use parquet::schema::parser::parse_message_type;
+///
+/// #[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+/// struct User {
+///     id: i64,
+///     name: String,
+/// }
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Parquet file of users
+///     #[clap(long)]
+///     users: PathTo<ParquetOf<User>>,
+/// }
+///
+/// // Create a Parquet file in a temporary directory
+/// let data_dir = tempfile::tempdir()?;
+/// let users_path = data_dir.path().join("users.parquet");
+/// let users_path_string = users_path.display().to_string();
+/// let schema = parse_message_type("message user { REQUIRED INT64 id; REQUIRED BINARY name (UTF8); }")?;
+/// let file = std::fs::File::create(&users_path)?;
+/// let mut writer = SerializedFileWriter::new(file, Arc::new(schema), Default::default())?;
+/// let mut row_group = writer.next_row_group()?;
+/// let mut column = row_group.next_column()?.expect("the id column");
+/// column.typed::<Int64Type>().write_batch(&[1, 2], None, None)?;
+/// column.close()?;
+/// let mut column = row_group.next_column()?.expect("the name column");
+/// let names = [ByteArray::from("alice"), ByteArray::from("bob")];
+/// column.typed::<ByteArrayType>().write_batch(&names, None, None)?;
+/// column.close()?;
+/// row_group.close()?;
+/// writer.close()?;
+///
+/// let cli = Cli::parse_from(["app", "--users", &users_path_string]);
+/// assert_eq!(cli.users.len(), 2);
+/// assert_eq!(cli.users[1], User { id: 2, name: "bob".to_string() });
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParquetOf<T>(pub Vec<T>);
+
+impl<T> ParquetOf<T> {
+    /// Returns the deserialized rows
+    pub fn into_inner(self) -> Vec<T> {
+        self.0
+    }
+}
+
+impl<T> std::ops::Deref for ParquetOf<T> {
+    type Target = Vec<T>;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> std::ops::DerefMut for ParquetOf<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T: DeserializeOwned> FromPath for ParquetOf<T> {
+    fn from_path(path: &Path) -> Result<Self, Error> {
+        let file = std::fs::File::open(path).map_err(|error| Error::io(path, error))?;
+        let parse_error = |error| Error::parse(path.to_path_buf(), error);
+        let reader = SerializedFileReader::new(file).map_err(parse_error)?;
+
+        let mut rows = Vec::new();
+        for (index, row) in reader.get_row_iter(None).map_err(parse_error)?.enumerate() {
+            let value = row.map_err(parse_error)?.to_json_value();
+            let row = serde_json::from_value(value)
+                .map_err(|source| Error::parse(path.to_path_buf(), RowError { index, source }))?;
+            rows.push(row);
+        }
+        Ok(ParquetOf(rows))
+    }
+}
+
+/// A row of a Parquet file that could not be deserialized
+#[derive(Debug, thiserror::Error)]
+#[error("row {index}: {source}")]
+struct RowError {
+    index: usize,
+    source: serde_json::Error,
+}
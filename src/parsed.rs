@@ -0,0 +1,108 @@
+use std::marker::PhantomData;
+
+use crate::traits::{invalid_data, FromReader};
+
+/// An adapter that parses a document as `Raw`, then converts it into `T`
+///
+/// This keeps a serde-shaped `Raw` type separate from a validated domain
+/// type `T`, which is built via `T::try_from(raw)`. If the conversion fails,
+/// its error is reported just like a parse error. The converted value is
+/// available as `.0`, or through [`Deref`].
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Clone, serde::Deserialize)]
+/// struct RawConfig {
+///     workers: i64,
+/// }
+///
+/// #[derive(Debug, Clone)]
+/// struct Config {
+///     workers: u32,
+/// }
+///
+/// impl TryFrom<JsonOf<RawConfig>> for Config {
+///     type Error = String;
+///     fn try_from(raw: JsonOf<RawConfig>) -> Result<Self, Self::Error> {
+///         let workers = u32::try_from(raw.0.workers)
+///             .map_err(|_| format!("workers must be positive, got {}", raw.0.workers))?;
+///         Ok(Config { workers })
+///     }
+/// }
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Json config
+///     #[clap(long)]
+///     config: PathTo<ParsedAs<JsonOf<RawConfig>, Config>>,
+/// }
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, r#"{"workers": 4}"#)?;
+///
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+/// assert_eq!(cli.config.workers, 4);
+///
+/// // A document that fails to convert is rejected
+/// std::fs::write(&config_path, r#"{"workers": -1}"#)?;
+/// let result = Cli::try_parse_from(["app", "--config", &config_path_string]);
+/// assert!(result.unwrap_err().to_string().contains("workers must be positive"));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Deref`]: std::ops::Deref
+pub struct ParsedAs<Raw, T>(pub T, PhantomData<fn() -> Raw>);
+
+impl<Raw, T> ParsedAs<Raw, T> {
+    /// Wrap an already converted value
+    pub fn new(value: T) -> Self {
+        ParsedAs(value, PhantomData)
+    }
+
+    /// Returns the converted value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<Raw, T> FromReader for ParsedAs<Raw, T>
+where
+    Raw: FromReader,
+    T: TryFrom<Raw>,
+    T::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    type Error = std::io::Error;
+    fn from_reader(reader: &mut impl std::io::BufRead) -> Result<Self, Self::Error> {
+        let raw = Raw::from_reader(reader).map_err(invalid_data)?;
+        let value = T::try_from(raw).map_err(invalid_data)?;
+        Ok(ParsedAs::new(value))
+    }
+}
+
+impl<Raw, T> std::ops::Deref for ParsedAs<Raw, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<Raw, T: Clone> Clone for ParsedAs<Raw, T> {
+    fn clone(&self) -> Self {
+        ParsedAs::new(self.0.clone())
+    }
+}
+
+impl<Raw, T: std::fmt::Debug> std::fmt::Debug for ParsedAs<Raw, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ParsedAs").field(&self.0).finish()
+    }
+}
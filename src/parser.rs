@@ -0,0 +1,211 @@
+//! Provides the [`AdapterParser`] for reporting adapter errors through clap
+
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::error::Error as _;
+use std::ffi::OsStr;
+use std::marker::PhantomData;
+use std::sync::{OnceLock, RwLock};
+
+use clap::builder::TypedValueParser;
+use clap::error::ErrorKind;
+use clap::{Command, ValueHint};
+
+use crate::Error;
+
+/// A clap value parser for adapters, with detailed error messages
+///
+/// When an adapter fails to parse, the error names the argument, the path
+/// given by the user, the format it failed to parse as, and each underlying
+/// cause on its own line. With the `miette` feature, documents that fail to
+/// parse are shown with a snippet pointing at the error instead.
+///
+/// `PathTo`, `Reloading` and `Periodic` use this parser automatically. Other
+/// adapters may opt in with `#[clap(value_parser = AdapterParser::<T>::new())]`.
+///
+/// The parsers of path-based adapters also record a shell completion hint,
+/// which [`with_value_hints`] applies to the arguments of a command.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a config file of arbitrary Json
+///     #[clap(long)]
+///     config: PathTo<JsonOf<serde_json::Value>>,
+/// }
+///
+/// // Create a malformed config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, "{not json")?;
+///
+/// let error = Cli::try_parse_from(["app", "--config", &config_path_string]).unwrap_err();
+/// let message = error.to_string();
+/// assert!(message.contains("--config"));
+/// assert!(message.contains(&config_path_string));
+/// assert!(message.contains("as Json"));
+/// # Ok(())
+/// # }
+/// ```
+pub struct AdapterParser<T>(PhantomData<fn() -> T>);
+
+impl<T> AdapterParser<T> {
+    /// Create a value parser for the adapter `T`
+    pub fn new() -> Self {
+        AdapterParser(PhantomData)
+    }
+}
+
+impl<T: 'static> AdapterParser<T> {
+    /// Create a value parser for the adapter `T`, whose arguments complete as `hint`
+    ///
+    /// The hint is recorded for `T` and applied by [`with_value_hints`].
+    pub fn with_value_hint(hint: ValueHint) -> Self {
+        if hint != ValueHint::Unknown {
+            value_hints()
+                .write()
+                .expect("value hints lock poisoned")
+                .insert(TypeId::of::<T>(), hint);
+        }
+        Self::new()
+    }
+}
+
+/// Set the shell completion hints of the adapters' arguments in `command`
+///
+/// Clap only infers completion hints for `PathBuf` arguments, so completions
+/// generated for adapters would otherwise treat them as opaque strings. This
+/// gives every argument parsed by a path-based adapter, such as `PathTo`,
+/// `Reloading` or `Periodic<PathTo<T>>`, the hint to complete file or
+/// directory names, in `command` and all of its subcommands. Arguments with
+/// an explicit `value_hint` are left unchanged.
+///
+/// Call this on the command given to a completion generator such as
+/// `clap_complete::generate`.
+///
+/// # Example
+///
+/// ```
+/// use clap::{CommandFactory, Parser, ValueHint};
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Json config
+///     #[clap(long)]
+///     config: PathTo<JsonOf<serde_json::Value>>,
+///
+///     /// Path to a directory of Json config fragments, watched for changes
+///     #[clap(long)]
+///     config_dir: Reloading<DirOf<JsonOf<serde_json::Value>>>,
+///
+///     /// The name of the application
+///     #[clap(long)]
+///     name: String,
+/// }
+///
+/// let command = with_value_hints(Cli::command());
+/// let hint_of = |id: &str| {
+///     let arg = command.get_arguments().find(|arg| arg.get_id() == id);
+///     arg.map(|arg| arg.get_value_hint())
+/// };
+/// assert_eq!(hint_of("config"), Some(ValueHint::FilePath));
+/// assert_eq!(hint_of("config_dir"), Some(ValueHint::DirPath));
+/// assert_eq!(hint_of("name"), Some(ValueHint::Unknown));
+/// ```
+pub fn with_value_hints(command: Command) -> Command {
+    command
+        .mut_args(|arg| {
+            if arg.get_value_hint() != ValueHint::Unknown {
+                return arg;
+            }
+            let value_type = arg.get_value_parser().type_id();
+            let hint = (value_hints().read().expect("value hints lock poisoned"))
+                .iter()
+                .find(|(type_id, _)| value_type == **type_id)
+                .map(|(_, hint)| *hint);
+            match hint {
+                Some(hint) => arg.value_hint(hint),
+                None => arg,
+            }
+        })
+        .mut_subcommands(with_value_hints)
+}
+
+/// The completion hints recorded by [`AdapterParser::with_value_hint`], by adapter type
+fn value_hints() -> &'static RwLock<HashMap<TypeId, ValueHint>> {
+    static VALUE_HINTS: OnceLock<RwLock<HashMap<TypeId, ValueHint>>> = OnceLock::new();
+    VALUE_HINTS.get_or_init(Default::default)
+}
+
+impl<T> Default for AdapterParser<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for AdapterParser<T> {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+impl<T> TypedValueParser for AdapterParser<T>
+where
+    T: std::str::FromStr + Clone + Send + Sync + 'static,
+    T::Err: Into<Error>,
+{
+    type Value = T;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        let arg = arg.map_or_else(|| "...".to_string(), ToString::to_string);
+        let Some(value) = value.to_str() else {
+            let message = format!("invalid value for '{arg}': {value:?} is not valid UTF-8\n");
+            return Err(clap::Error::raw(ErrorKind::InvalidUtf8, message).with_cmd(cmd));
+        };
+
+        T::from_str(value).map_err(|error| {
+            let error: Error = error.into();
+            let message = describe(&arg, &error);
+            clap::Error::raw(ErrorKind::ValueValidation, message).with_cmd(cmd)
+        })
+    }
+}
+
+/// Formats `error` for the terminal, with any cause not already shown on its own line
+fn describe(arg: &str, error: &Error) -> String {
+    #[cfg(feature = "miette")]
+    if let Some(diagnostic) = error.diagnostic() {
+        let handler =
+            miette::GraphicalReportHandler::new_themed(miette::GraphicalTheme::unicode_nocolor());
+        let mut rendered = String::new();
+        if handler.render_report(&mut rendered, &diagnostic).is_ok() {
+            return format!("invalid value for '{arg}':\n{rendered}");
+        }
+    }
+
+    let mut message = format!("invalid value for '{arg}': {error}\n");
+    let mut shown = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        let cause_message = cause.to_string();
+        if !shown.contains(&cause_message) {
+            message.push_str(&format!("  caused by: {cause_message}\n"));
+            shown.push_str(&cause_message);
+        }
+        source = cause.source();
+    }
+    message
+}
@@ -0,0 +1,87 @@
+use std::path::{Component, Path, PathBuf, Prefix};
+
+/// Returns `path` without a verbatim (`\\?\`) prefix, where that's lossless
+///
+/// On Windows, [`std::fs::canonicalize`] returns verbatim paths such as
+/// `\\?\C:\config.json` or `\\?\UNC\server\share\config.json`, while users
+/// and filesystem notifications use the plain forms `C:\config.json` and
+/// `\\server\share\config.json`. Normalizing lets these be compared.
+///
+/// Verbatim paths with other prefixes, or whose remaining components would
+/// be interpreted differently without the prefix (e.g. a component named
+/// `..` or containing `/`), are returned unchanged. Paths on other platforms
+/// never have a prefix, so they are always returned unchanged.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut components = path.components();
+    let Some(Component::Prefix(prefix)) = components.next() else {
+        return path.to_path_buf();
+    };
+
+    let mut normalized = match prefix.kind() {
+        Prefix::VerbatimDisk(drive) => PathBuf::from(format!("{}:", char::from(drive))),
+        Prefix::VerbatimUNC(server, share) => {
+            let mut unc = std::ffi::OsString::from(r"\\");
+            unc.push(server);
+            unc.push(r"\");
+            unc.push(share);
+            PathBuf::from(unc)
+        }
+        _ => return path.to_path_buf(),
+    };
+
+    for component in components {
+        match component {
+            Component::RootDir => normalized.push(std::path::MAIN_SEPARATOR_STR),
+            Component::Normal(part) if is_plain(part) => normalized.push(part),
+            _ => return path.to_path_buf(),
+        }
+    }
+    normalized
+}
+
+/// Whether a verbatim component means the same thing in a plain path
+fn is_plain(part: &std::ffi::OsStr) -> bool {
+    part.to_str()
+        .is_some_and(|part| part != "." && part != ".." && !part.contains('/'))
+}
+
+#[cfg(all(test, windows))]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::normalize;
+
+    #[test]
+    fn strips_verbatim_disk_prefix() {
+        assert_eq!(normalize(Path::new(r"\\?\C:\a")), PathBuf::from(r"C:\a"));
+    }
+
+    #[test]
+    fn strips_verbatim_unc_prefix() {
+        assert_eq!(
+            normalize(Path::new(r"\\?\UNC\srv\share\a")),
+            PathBuf::from(r"\\srv\share\a"),
+        );
+    }
+
+    #[test]
+    fn keeps_verbatim_parent_components() {
+        assert_eq!(
+            normalize(Path::new(r"\\?\C:\a\..\b")),
+            PathBuf::from(r"\\?\C:\a\..\b"),
+        );
+    }
+
+    #[test]
+    fn keeps_drive_relative_paths() {
+        assert_eq!(normalize(Path::new(r"C:a")), PathBuf::from(r"C:a"));
+    }
+
+    #[test]
+    fn keeps_plain_unc_paths() {
+        assert_eq!(
+            normalize(Path::new(r"\\srv\share\a")),
+            PathBuf::from(r"\\srv\share\a"),
+        );
+    }
+}
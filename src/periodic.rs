@@ -1,17 +1,36 @@
 //! Provides the [`Periodic`] adapter for loading files at a regular interval
 
-use std::time::Duration;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
-use tokio::sync::watch;
-use tokio_stream::Stream;
-
-use crate::{prelude::FromReader, PathTo};
+use crate::{
+    handle::{skip_unchanged, RunState},
+    options::AdapterOptions,
+    parser::AdapterParser,
+    time::{random_up_to, Minutes, Schedule, Seconds, Time},
+    traits::{FromOptions, Reload},
+    watcher::ConfigWatcher,
+};
 
 /// Given a [`Path`] from the user, provides a utility that reloads the file
 /// at the path at a fixed interval
 ///
-/// - Use [`Periodic::get`] to get the file contents at a given moment
-/// - Use [`Periodic::receiver`] to get a tokio [`watch::Receiver`]
+/// Besides `PathTo<T>`, any adapter implementing [`Reload`] may be reloaded
+/// periodically.
+///
+/// - Use [`ConfigWatcher::get`] to get the file contents at a given moment
+/// - Use [`ConfigWatcher::receiver`] to get a tokio [`watch::Receiver`]
+/// - Use [`ConfigWatcher::reload_now`] to reload the file without waiting for the period
+///
+/// [`Periodic`] dereferences to the [`ConfigWatcher`] it is built on, so all
+/// of the watcher's methods are available.
+///
+/// When a reload fails, it is retried after a short delay that doubles with
+/// each consecutive failure, up to the period. The regular period resumes
+/// after the next successful reload.
 ///
 /// # Example
 ///
@@ -32,6 +51,10 @@ use crate::{prelude::FromReader, PathTo};
 ///     /// Path to a Json config to be reloaded every second
 ///     #[clap(long)]
 ///     secondly_config: Periodic<PathTo<TomlOf<serde_json::Value>>, Seconds<1>>,
+///
+///     /// Path to a Json config to be reloaded every 60 to 70 seconds
+///     #[clap(long)]
+///     jittered_config: Periodic<PathTo<JsonOf<serde_json::Value>>, Minutes<1>, Seconds<10>>,
 /// }
 ///
 /// #[tokio::main]
@@ -41,6 +64,7 @@ use crate::{prelude::FromReader, PathTo};
 ///         "--daily_config=./daily_config.json",
 ///         "--minutely-config=./minutely_config.yaml",
 ///         "--secondly-config=./secondly_config.toml",
+///         "--jittered-config=./jittered_config.json",
 ///     ]);
 ///    
 ///     let current_config = cli.daily_config.get();
@@ -53,125 +77,471 @@ use crate::{prelude::FromReader, PathTo};
 /// }
 /// ```
 ///
-/// > *Note*: [`Periodic`] requires a tokio runtime to be active before calling
-/// > any of the `clap::Parser` functions
+/// When many instances of an application start at the same time, they would
+/// all reload at the same instant. To spread out reloads, specify a jitter
+/// duration `J`: each cycle then sleeps for the period `P` plus a random
+/// duration of up to `J`. For example, `Periodic<T, Minutes<1>, Seconds<10>>`
+/// reloads every 60 to 70 seconds. By default, no jitter is applied.
+///
+/// Under [`WithOptions`], the user may override the period and jitter with
+/// `interval` and `jitter` options:
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use std::time::{Duration, SystemTime};
+///
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Json config, reloaded every minute unless `?interval=` is given
+///     #[clap(long)]
+///     config: WithOptions<Periodic<PathTo<JsonOf<serde_json::Value>>>>,
+/// }
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json");
+/// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+///
+/// let runtime = tokio::runtime::Runtime::new()?;
+/// runtime.block_on(async {
+///     let argument = format!("{}?interval=50ms", config_path.display());
+///     let cli = Cli::parse_from(["app", "--config", &argument]);
+///     let mut config_rx = cli.config.receiver();
+///
+///     // The change is picked up well before a minute has passed
+///     std::fs::write(&config_path, r#"{"hello":"reloaded"}"#)?;
+///     let wait = config_rx.wait_for(|config| config.data.0["hello"] == "reloaded");
+///     tokio::time::timeout(Duration::from_secs(5), wait).await??;
+///
+///     // A zero interval is rejected
+///     let argument = format!("{}?interval=0s", config_path.display());
+///     assert!(Cli::try_parse_from(["app", "--config", &argument]).is_err());
+///     anyhow::Ok(())
+/// })?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// With the `cron` feature, the period `P` may instead be a `Cron<C>`
+/// schedule, reloading at the times matched by a cron expression, and the
+/// user may give an expression such as `?cron=0 2 * * *` in place of
+/// `interval`. Jitter is added after each scheduled time.
+///
+/// The background reload task is spawned onto the tokio runtime that is
+/// active while parsing. If no runtime is active yet (e.g. when calling
+/// `Cli::parse()` at the top of a synchronous `main`), spawning is deferred
+/// until the first call to any of its methods, such as [`ConfigWatcher::get`]
+/// or [`ConfigWatcher::reload_now`], from within a runtime, or until
+/// [`Periodic::start`] is called explicitly.
 ///
 /// [`Path`]: std::path::Path
 /// [`watch::Receiver`]: tokio::sync::watch
+/// [`WithOptions`]: crate::WithOptions
 #[derive(Clone)]
 #[must_use = "Dropping the `Periodic` will cancel the file watch"]
-pub struct Periodic<T, P: Time = Minutes<1>> {
-    reload_rx: watch::Receiver<T>,
-    period: std::marker::PhantomData<P>,
+pub struct Periodic<T, P: Schedule = Minutes<1>, J: Time = Seconds<0>> {
+    watcher: ConfigWatcher<T>,
+    task: Arc<DeferredTask>,
+    period: std::marker::PhantomData<(P, J)>,
 }
 
-impl<T: Clone, P: Time> Periodic<T, P> {
-    /// Get the current value of the inner document
-    pub fn get(&self) -> T {
-        self.reload_rx.borrow().clone()
+/// The background reload task, until it is spawned onto a runtime
+struct DeferredTask {
+    /// Whether the task has been spawned, checked before taking the lock
+    started: AtomicBool,
+    pending: Mutex<Option<PendingTask>>,
+}
+
+/// A reload task that has not been spawned because no runtime was active yet
+type PendingTask = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Returns how long to wait after the given time until the next reload
+type NextDelay = Box<dyn Fn(SystemTime) -> Result<Duration, crate::Error> + Send + Sync>;
+
+impl<T, P: Schedule, J: Time> Periodic<T, P, J> {
+    /// Spawn the background reload task if it hasn't been spawned yet
+    ///
+    /// This is done automatically when parsing within a tokio runtime, or on
+    /// the first call to any other method except [`Periodic::shutdown`] from
+    /// within a runtime, so it only needs to be called to start reloading
+    /// before the adapter is first used.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the task hasn't been spawned yet and no tokio runtime is active.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap::Parser;
+    /// use clap_adapters::prelude::*;
+    ///
+    /// #[derive(Debug, Parser)]
+    /// struct Cli {
+    ///     /// Path to a Json config to be reloaded every minute
+    ///     #[clap(long)]
+    ///     config: Periodic<PathTo<JsonOf<serde_json::Value>>>,
+    /// }
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// let config_path_string = config_path.display().to_string();
+    /// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+    ///
+    /// // Parsing works before any runtime has been started
+    /// let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+    ///
+    /// // Nothing is reloaded until the task is started
+    /// std::fs::write(&config_path, r#"{"hello":"started"}"#)?;
+    /// assert_eq!(cli.config.get().data.0, serde_json::json!({"hello":"world"}));
+    ///
+    /// let runtime = tokio::runtime::Runtime::new()?;
+    /// runtime.block_on(async {
+    ///     // The task reloads the file as soon as it starts
+    ///     cli.config.start();
+    ///     let mut config_rx = cli.config.receiver();
+    ///     config_rx
+    ///         .wait_for(|config| config.data.0 == serde_json::json!({"hello":"started"}))
+    ///         .await?;
+    ///     anyhow::Ok(())
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn start(&self) {
+        let mut pending = self
+            .task
+            .pending
+            .lock()
+            .expect("pending task lock poisoned");
+        if let Some(task) = pending.take() {
+            tokio::spawn(task);
+            self.task.started.store(true, Ordering::Release);
+        }
     }
 
-    /// Get a receiver channel that yields updated documents after filesystem changes
-    pub fn receiver(&self) -> watch::Receiver<T> {
-        self.reload_rx.clone()
+    /// Spawn the background reload task if possible, without panicking
+    fn ensure_started(&self) {
+        if !self.task.started.load(Ordering::Acquire)
+            && tokio::runtime::Handle::try_current().is_ok()
+        {
+            self.start();
+        }
     }
 }
 
-impl<T, P: Time> Periodic<T, P>
-where
-    T: Clone + Send + Sync + 'static,
-{
-    /// Get a stream of document changes
-    pub fn stream(&self) -> impl Stream<Item = T> {
-        tokio_stream::wrappers::WatchStream::new(self.reload_rx.clone())
+impl<T, P: Schedule, J: Time> std::ops::Deref for Periodic<T, P, J> {
+    type Target = ConfigWatcher<T>;
+    fn deref(&self) -> &Self::Target {
+        self.ensure_started();
+        &self.watcher
     }
 }
 
-impl<T, P: Time> std::fmt::Debug for Periodic<PathTo<T>, P>
+impl<T, P: Schedule, J: Time> Periodic<T, P, J> {
+    /// Pause periodic reloads until [`Periodic::resume`] is called
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap::Parser;
+    /// use clap_adapters::prelude::*;
+    ///
+    /// #[derive(Debug, Parser)]
+    /// struct Cli {
+    ///     /// Path to a Json config that's reloaded every minute
+    ///     #[clap(long)]
+    ///     config: Periodic<PathTo<JsonOf<serde_json::Value>>>,
+    /// }
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// let config_path_string = config_path.display().to_string();
+    /// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+    ///
+    /// let runtime = tokio::runtime::Runtime::new()?;
+    /// runtime.block_on(async {
+    ///     let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+    ///
+    ///     // Wait for the first periodic reload, which happens right away but
+    ///     // publishes nothing, as the file is unchanged
+    ///     while cli.config.stats().attempts == 0 {
+    ///         tokio::task::yield_now().await;
+    ///     }
+    ///
+    ///     // While paused, changes to the file are not picked up
+    ///     cli.config.pause();
+    ///     std::fs::write(&config_path, r#"{"hello":"paused"}"#)?;
+    ///     assert_eq!(cli.config.get().data.0, serde_json::json!({"hello":"world"}));
+    ///
+    ///     // Resuming reloads the file right away
+    ///     let mut config_rx = cli.config.receiver();
+    ///     config_rx.mark_unchanged();
+    ///     cli.config.resume();
+    ///     config_rx.changed().await?;
+    ///     assert_eq!(cli.config.get().data.0, serde_json::json!({"hello":"paused"}));
+    ///
+    ///     cli.config.shutdown();
+    ///     anyhow::Ok(())
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn pause(&self) {
+        self.ensure_started();
+        self.watcher.pause();
+    }
+
+    /// Resume periodic reloads after [`Periodic::pause`], reloading right away
+    pub fn resume(&self) {
+        self.ensure_started();
+        // The background task reloads right away once resumed
+        self.watcher.lifecycle().resume();
+    }
+
+    /// Permanently stop the background reload task
+    ///
+    /// Receivers keep observing the last published document.
+    pub fn shutdown(&self) {
+        self.watcher.shutdown();
+    }
+}
+
+impl<T, P: Schedule, J: Time> std::fmt::Debug for Periodic<T, P, J>
 where
     T: std::fmt::Debug,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_tuple("Periodic")
-            .field(&*self.reload_rx.borrow())
+            .field(&*self.watcher.get())
             .finish()
     }
 }
 
-impl<T, P: Time> std::str::FromStr for Periodic<PathTo<T>, P>
+impl<T, P: Schedule, J: Time> clap::builder::ValueParserFactory for Periodic<T, P, J>
 where
-    T: FromReader + Clone + Send + Sync + 'static,
+    T: Reload + std::str::FromStr + Clone + Send + Sync + 'static,
+    T::Err: Into<crate::Error>,
+    P: Clone + Send + Sync + 'static,
+    J: Clone + Send + Sync + 'static,
 {
-    type Err = anyhow::Error;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let path_to = PathTo::<T>::from_str(s)?;
-        let (reload_tx, reload_rx) = watch::channel(path_to.clone());
+    type Parser = AdapterParser<Self>;
+    fn value_parser() -> Self::Parser {
+        AdapterParser::with_value_hint(T::value_hint())
+    }
+}
 
-        tokio::spawn(async move {
-            let path = path_to.path;
-            let reload_tx = reload_tx;
+impl<T, P: Schedule, J: Time> Periodic<T, P, J>
+where
+    T: Reload + Send + Sync + 'static,
+{
+    /// Start periodically reloading an already loaded `document`
+    ///
+    /// This is what parsing the adapter from an argument does, for building
+    /// values outside of clap (e.g. in tests). Reloading begins once the
+    /// adapter is first used within a tokio runtime.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap_adapters::prelude::*;
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+    ///
+    /// let document = PathTo::<JsonOf<serde_json::Value>>::load(&config_path)?;
+    /// let config = Periodic::<_, Seconds<1>>::new(document);
+    /// assert_eq!(config.get().data.0, serde_json::json!({"hello":"world"}));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new(document: T) -> Self {
+        Self::spawn(
+            ConfigWatcher::from_document(document),
+            next_delay::<P>(),
+            J::PERIOD,
+        )
+    }
+}
 
+impl<T, P: Schedule, J: Time> Periodic<T, P, J>
+where
+    T: Send + Sync + 'static,
+{
+    /// Reload `watcher` after every delay given by `next_delay`, plus up to `jitter`
+    fn spawn(watcher: ConfigWatcher<T>, next_delay: NextDelay, jitter: Duration) -> Self {
+        let mut state_rx = watcher.lifecycle().subscribe();
+        let periodic_handle = watcher.reload_handle();
+        let task = async move {
+            let mut failures = 0;
             loop {
-                let data_result = (|| -> anyhow::Result<T> {
-                    let file = std::fs::File::open(&path)?;
-                    let mut reader = std::io::BufReader::new(file);
-                    let data = T::from_reader(&mut reader)?;
-                    Ok(data)
-                })();
-
-                let data = match data_result {
-                    Ok(data) => data,
-                    Err(error) => {
-                        tracing::error!(
-                            error = format!("{error:#}"),
-                            path = %path.display(),
-                            "Failed to reload after time period",
-                        );
+                let state = *state_rx.borrow_and_update();
+                match state {
+                    RunState::Running => {}
+                    RunState::Paused => {
+                        // Wait to be resumed or shut down
+                        if state_rx.changed().await.is_err() {
+                            break;
+                        }
                         continue;
                     }
+                    RunState::Shutdown => break,
+                }
+
+                let result = periodic_handle.reload_now();
+                let period = match next_delay(SystemTime::now()) {
+                    Ok(period) => period,
+                    Err(error) => {
+                        tracing::error!(error = %error, "Failed to schedule the next reload");
+                        break;
+                    }
                 };
 
-                let updated_path_to = PathTo {
-                    path: path.clone(),
-                    data,
+                // Retry failed reloads sooner than the period, backing off exponentially
+                let delay = match result {
+                    Ok(()) => {
+                        failures = 0;
+                        period.saturating_add(random_up_to(jitter))
+                    }
+                    Err(error) => {
+                        periodic_handle.report(&error, "Failed to reload after time period");
+                        failures += 1;
+                        backoff(failures, period)
+                    }
                 };
 
-                reload_tx.send_replace(updated_path_to);
-                tokio::time::sleep(P::PERIOD).await;
+                // Sleep until the next attempt, waking early if paused or shut down
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    changed = state_rx.changed() => {
+                        if changed.is_err() {
+                            break;
+                        }
+                    }
+                }
             }
+        };
+
+        // Spawn right away if possible, or defer until a runtime is available
+        let task = Arc::new(DeferredTask {
+            started: AtomicBool::new(false),
+            pending: Mutex::new(Some(Box::pin(task) as PendingTask)),
         });
 
         let item = Self {
-            reload_rx,
+            watcher,
+            task,
             period: std::marker::PhantomData,
         };
-        Ok(item)
+        item.ensure_started();
+        item
     }
 }
 
-/// Trait for type markers to const-evaluate to a Duration
-pub trait Time {
-    /// The duration between periodic file reloads
-    const PERIOD: Duration;
+impl<T, P: Schedule, J: Time> std::str::FromStr for Periodic<T, P, J>
+where
+    T: Reload + std::str::FromStr + Send + Sync + 'static,
+    T::Err: Into<crate::Error>,
+{
+    type Err = crate::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        P::delay_after(SystemTime::now())?;
+        let document = T::from_str(s).map_err(Into::into)?;
+        Ok(Self::new(document))
+    }
+}
+
+impl<T, P: Schedule, J: Time> FromOptions for Periodic<T, P, J>
+where
+    T: Reload + FromOptions + Send + Sync + 'static,
+{
+    /// Takes `interval` and `jitter` to override `P` and `J`
+    ///
+    /// With the `cron` feature, `cron` may be given instead of `interval`
+    /// to reload at the times matched by a cron expression. The options taken
+    /// by the inner adapter are given to it again on every reload.
+    fn from_options(input: &str, options: &mut AdapterOptions) -> Result<Self, crate::Error> {
+        let interval = options.take_duration("interval")?;
+        if interval == Some(Duration::ZERO) {
+            return Err(crate::Error::InvalidOption {
+                input: "interval=0".to_string(),
+                reason: "the interval must be longer than zero".to_string(),
+            });
+        }
+        #[cfg(feature = "cron")]
+        if let Some(expression) = options.take("cron") {
+            if interval.is_some() {
+                return Err(crate::Error::InvalidOption {
+                    input: format!("cron={expression}"),
+                    reason: "cron and interval can't both be given".to_string(),
+                });
+            }
+            let schedule = crate::cron::CronSchedule::new(&expression)?;
+            let next_delay = Box::new(move |now| schedule.delay_after(now));
+            return Self::from_schedule(input, options, next_delay);
+        }
+        let next_delay: NextDelay = match interval {
+            Some(interval) => Box::new(move |_| Ok(interval)),
+            None => {
+                P::delay_after(SystemTime::now())?;
+                next_delay::<P>()
+            }
+        };
+        Self::from_schedule(input, options, next_delay)
+    }
+    fn takes_option(key: &str) -> bool {
+        matches!(key, "interval" | "jitter")
+            || (cfg!(feature = "cron") && key == "cron")
+            || T::takes_option(key)
+    }
 }
 
-/// Reload the file every `N` seconds
-#[derive(Debug, Clone, Copy)]
-pub enum Seconds<const N: u64> {}
-impl<const N: u64> Time for Seconds<N> {
-    const PERIOD: Duration = Duration::from_secs(N);
+impl<T, P: Schedule, J: Time> Periodic<T, P, J>
+where
+    T: Reload + FromOptions + Send + Sync + 'static,
+{
+    /// Load `input` with the remaining `options`, reloading after every `next_delay`
+    fn from_schedule(
+        input: &str,
+        options: &mut AdapterOptions,
+        next_delay: NextDelay,
+    ) -> Result<Self, crate::Error> {
+        let jitter = options.take_duration("jitter")?.unwrap_or(J::PERIOD);
+
+        let inner_options = options.clone();
+        let document = T::from_options(input, options)?;
+        let location = document.location();
+        let input = input.to_string();
+        let next = move |_: &T| Ok(Some(T::from_options(&input, &mut inner_options.clone())?));
+        let watcher = ConfigWatcher::from_source(document, location, skip_unchanged(next));
+        Ok(Self::spawn(watcher, next_delay, jitter))
+    }
 }
 
-/// Reload the file every `N` minutes
-#[derive(Debug, Clone, Copy)]
-pub enum Minutes<const N: u64> {}
-impl<const N: u64> Time for Minutes<N> {
-    const PERIOD: Duration = Duration::from_secs(60 * N);
+/// Returns the delays between reloads of the schedule `P`
+fn next_delay<P: Schedule>() -> NextDelay {
+    // A function pointer, unlike `P` itself, is always `'static`
+    let delay_after: fn(SystemTime) -> Result<Duration, crate::Error> = P::delay_after;
+    Box::new(delay_after)
 }
 
-/// Reload the file every `N` hours
-#[derive(Debug, Clone, Copy)]
-pub enum Hours<const N: u64> {}
-impl<const N: u64> Time for Hours<N> {
-    const PERIOD: Duration = Duration::from_secs(60 * 60 * N);
+/// The delay before retrying after the first failed reload
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Returns how long to wait after `failures` consecutive failed reloads
+///
+/// The delay doubles with each failure, but never exceeds `max`.
+fn backoff(failures: u32, max: Duration) -> Duration {
+    let factor = 2u32.saturating_pow(failures.saturating_sub(1));
+    INITIAL_BACKOFF.saturating_mul(factor).min(max)
 }
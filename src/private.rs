@@ -0,0 +1,116 @@
+use std::fs::{File, Metadata};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::{
+    fs::{LoadMetadata, PathTo},
+    traits::{FromReader, HashingReader},
+    Error,
+};
+
+/// An adapter that refuses to load files which other users could access
+///
+/// On Unix, `Private<PathTo<T>>` opens the file at the user-given path and
+/// checks the permissions of the opened file before reading from it, so the
+/// file can't be swapped between the check and the read. It fails to parse if the file grants
+/// any permissions to its group or to other users (i.e. `mode & 0o077 != 0`).
+/// This mirrors how OpenSSH treats private key files, so that secrets such as
+/// API tokens are never silently accepted from a file with unsafe permissions.
+///
+/// On other platforms no permission check is performed.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// # #[cfg(unix)]
+/// # {
+/// use std::os::unix::fs::PermissionsExt;
+///
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a file containing an API token
+///     #[clap(long)]
+///     token: Private<PathTo<String>>,
+/// }
+///
+/// // Create a token file in a temporary directory
+/// let secret_dir = tempfile::tempdir()?;
+/// let token_path = secret_dir.path().join("token");
+/// let token_path_string = token_path.display().to_string();
+/// std::fs::write(&token_path, "hunter2")?;
+///
+/// // A file readable only by its owner is accepted
+/// std::fs::set_permissions(&token_path, std::fs::Permissions::from_mode(0o600))?;
+/// let cli = Cli::parse_from(["app", "--token", &token_path_string]);
+/// assert_eq!(cli.token.0.data, "hunter2");
+///
+/// // A world-readable file is rejected
+/// std::fs::set_permissions(&token_path, std::fs::Permissions::from_mode(0o644))?;
+/// let result = Cli::try_parse_from(["app", "--token", &token_path_string]);
+/// assert!(result.is_err());
+/// # }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Private<T>(pub T);
+
+impl<T: FromReader> Private<PathTo<T>> {
+    /// Load the file at `path`, rejecting it if others may access it
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+        let loaded_at = SystemTime::now();
+        let file = File::open(&path).map_err(|error| Error::io(&path, error))?;
+        let file_metadata = file.metadata().map_err(|error| Error::io(&path, error))?;
+        ensure_private(&path, &file_metadata)?;
+
+        let mut reader = HashingReader::new(BufReader::new(file));
+        let data =
+            T::from_reader(&mut reader).map_err(|error| Error::parse(path.clone(), error))?;
+        let hash = reader.finish().map_err(|error| Error::io(&path, error))?;
+        let metadata = LoadMetadata {
+            loaded_at,
+            modified: file_metadata.modified().ok(),
+            len: Some(file_metadata.len()),
+            hash: Some(hash),
+        };
+        Ok(Private(PathTo {
+            path,
+            data,
+            metadata,
+        }))
+    }
+}
+
+impl<T: FromReader> std::str::FromStr for Private<PathTo<T>> {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::load(s)
+    }
+}
+
+/// Returns an error if the opened file at `path` is accessible by its group or others
+#[cfg(unix)]
+fn ensure_private(path: &Path, metadata: &Metadata) -> Result<(), Error> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = metadata.permissions().mode();
+    if mode & 0o077 != 0 {
+        return Err(Error::TooOpen {
+            path: path.to_path_buf(),
+            mode: mode & 0o7777,
+        });
+    }
+    Ok(())
+}
+
+/// Permission bits are not enforced on non-Unix platforms
+#[cfg(not(unix))]
+fn ensure_private(_path: &Path, _metadata: &Metadata) -> Result<(), Error> {
+    Ok(())
+}
@@ -0,0 +1,230 @@
+use std::marker::PhantomData;
+use std::path::PathBuf;
+
+use serde_json::Value;
+
+use crate::{overrides::SetOverride, traits::FromMerged, Error};
+
+/// An adapter for documents with named profiles, such as `dev` and `prod`
+///
+/// The document at the user-given path is kept as a value tree until a
+/// profile is chosen with [`ProfileOf::select`], typically from a second flag
+/// or environment variable. The selected profile's section is then
+/// deep-merged over the document's defaults before converting into `T`.
+///
+/// Where the profiles live is configured by the [`ProfileSection`] marker:
+///
+/// - [`Profiles`] (the default) reads profiles from a `profiles` table, with
+///   the rest of the document as defaults, e.g. `[profiles.prod]` in Toml
+/// - [`TopLevel`] reads profiles from top-level keys, with defaults under the
+///   `default` key, as is common in Yaml
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, serde::Deserialize)]
+/// struct Config {
+///     host: String,
+///     port: u16,
+/// }
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Toml config with profiles
+///     #[clap(long)]
+///     config: ProfileOf<TomlOf<Config>>,
+///
+///     /// The profile to select from the config
+///     #[clap(long, env = "APP_PROFILE")]
+///     profile: Option<String>,
+/// }
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.toml");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, r#"
+/// host = "localhost"
+/// port = 80
+///
+/// [profiles.prod]
+/// host = "example.com"
+/// "#)?;
+///
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string, "--profile", "prod"]);
+/// let config = cli.config.select(cli.profile.as_deref())?;
+/// assert_eq!(config.host, "example.com");
+/// assert_eq!(config.port, 80);
+///
+/// // Without a profile, only the defaults are used
+/// assert_eq!(cli.config.select(None)?.host, "localhost");
+///
+/// // Selecting a missing profile is an error
+/// assert!(cli.config.select(Some("staging")).is_err());
+/// # Ok(())
+/// # }
+/// ```
+pub struct ProfileOf<T, S: ProfileSection = Profiles> {
+    /// The path given as an argument by the user
+    pub path: PathBuf,
+
+    /// The whole document as a value tree, including every profile
+    pub document: Value,
+
+    marker: PhantomData<fn() -> (T, S)>,
+}
+
+impl<T: FromMerged, S: ProfileSection> ProfileOf<T, S> {
+    /// Load the file at `path`, keeping its value tree for selecting profiles
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+        let file = std::fs::File::open(&path).map_err(|error| Error::io(&path, error))?;
+        let mut reader = std::io::BufReader::new(file);
+        let document =
+            T::value_from_reader(&mut reader).map_err(|error| Error::parse(path.clone(), error))?;
+        let item = ProfileOf {
+            path,
+            document,
+            marker: PhantomData,
+        };
+        Ok(item)
+    }
+
+    /// Convert the defaults merged with `profile`'s section, or just the defaults if `None`
+    pub fn select(&self, profile: Option<&str>) -> Result<T, Error> {
+        self.select_with_overrides(profile, &[])
+    }
+
+    /// Like [`ProfileOf::select`], then applies `overrides` on top of the selected profile
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap_adapters::prelude::*;
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// std::fs::write(
+    ///     &config_path,
+    ///     r#"{"port": 80, "profiles": {"prod": {"port": 443}}}"#,
+    /// )?;
+    ///
+    /// let config = ProfileOf::<JsonOf<serde_json::Value>>::load(&config_path)?;
+    /// let overrides = ["port=8443".parse::<SetOverride>()?];
+    /// let selected = config.select_with_overrides(Some("prod"), &overrides)?;
+    /// assert_eq!(selected.0, serde_json::json!({"port": 8443}));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn select_with_overrides(
+        &self,
+        profile: Option<&str>,
+        overrides: &[SetOverride],
+    ) -> Result<T, Error> {
+        let (mut merged, profiles) = self.split();
+        if let Some(profile) = profile {
+            let section = profiles
+                .and_then(|profiles| profiles.get(profile))
+                .ok_or_else(|| Error::UnknownProfile {
+                    path: self.path.clone(),
+                    profile: profile.to_string(),
+                    available: self.profiles(),
+                })?;
+            crate::merge::deep_merge(&mut merged, section.clone());
+        }
+        for set_override in overrides {
+            set_override.apply(&mut merged);
+        }
+        T::from_merged(merged).map_err(|error| Error::parse(self.path.clone(), error))
+    }
+
+    /// Returns the names of the profiles in the document
+    pub fn profiles(&self) -> Vec<String> {
+        let (_, profiles) = self.split();
+        let profiles = profiles.into_iter().flat_map(|profiles| profiles.keys());
+        match S::TABLE {
+            Some(_) => profiles.cloned().collect(),
+            None => profiles
+                .filter(|name| *name != DEFAULT_PROFILE)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Split the document into its defaults and the table of profiles
+    fn split(&self) -> (Value, Option<&serde_json::Map<String, Value>>) {
+        match S::TABLE {
+            Some(table) => {
+                let mut defaults = self.document.clone();
+                if let Value::Object(defaults) = &mut defaults {
+                    defaults.remove(table);
+                }
+                let profiles = self.document.get(table).and_then(Value::as_object);
+                (defaults, profiles)
+            }
+            None => {
+                let defaults = self
+                    .document
+                    .get(DEFAULT_PROFILE)
+                    .cloned()
+                    .unwrap_or_else(|| Value::Object(Default::default()));
+                (defaults, self.document.as_object())
+            }
+        }
+    }
+}
+
+impl<T: FromMerged, S: ProfileSection> std::str::FromStr for ProfileOf<T, S> {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::load(s)
+    }
+}
+
+impl<T, S: ProfileSection> Clone for ProfileOf<T, S> {
+    fn clone(&self) -> Self {
+        ProfileOf {
+            path: self.path.clone(),
+            document: self.document.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T, S: ProfileSection> std::fmt::Debug for ProfileOf<T, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProfileOf")
+            .field("path", &self.path)
+            .field("document", &self.document)
+            .finish()
+    }
+}
+
+/// The top-level key holding the defaults for [`TopLevel`] profiles
+const DEFAULT_PROFILE: &str = "default";
+
+/// Trait for type markers configuring where [`ProfileOf`] finds profiles
+pub trait ProfileSection {
+    /// The table holding the profiles, or `None` for top-level profiles
+    const TABLE: Option<&'static str>;
+}
+
+/// Profiles are read from the `profiles` table, e.g. `[profiles.prod]`
+#[derive(Debug, Clone, Copy)]
+pub enum Profiles {}
+impl ProfileSection for Profiles {
+    const TABLE: Option<&'static str> = Some("profiles");
+}
+
+/// Profiles are top-level keys, with defaults under the `default` key
+#[derive(Debug, Clone, Copy)]
+pub enum TopLevel {}
+impl ProfileSection for TopLevel {
+    const TABLE: Option<&'static str> = None;
+}
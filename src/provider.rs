@@ -0,0 +1,130 @@
+//! Implements [`figment::Provider`] for [`PathTo`] and the reloading adapters
+//!
+//! Each adapter provides its current document to the default profile, with
+//! the file's path as the metadata source, so errors from Figment point back
+//! at the file the user passed on the command line. Reloading adapters
+//! provide whichever document is current when the [`Figment`] is extracted.
+//!
+//! [`Figment`]: figment::Figment
+
+use std::path::Path;
+
+use figment::providers::Serialized;
+use figment::value::{Dict, Map};
+use figment::{Metadata, Profile, Provider, Source};
+use serde::Serialize;
+
+use crate::fs::PathTo;
+
+/// Describes the file at `path` as the source of provided values
+fn file_metadata(path: &Path) -> Metadata {
+    Metadata::from("CLI-supplied file", Source::File(path.to_path_buf()))
+}
+
+/// Merge a CLI-supplied file into a [`Figment`] pipeline
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+/// use figment::Figment;
+/// use figment::providers::Serialized;
+///
+/// #[derive(Debug, serde::Serialize, serde::Deserialize)]
+/// struct Config {
+///     host: String,
+///     port: u16,
+/// }
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Toml config overriding the defaults
+///     #[clap(long)]
+///     config: PathTo<TomlOf<toml::Table>>,
+/// }
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.toml");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, "port = 8080")?;
+///
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+/// let defaults = Config { host: "localhost".to_string(), port: 80 };
+/// let config: Config = Figment::new()
+///     .merge(Serialized::defaults(defaults))
+///     .merge(&cli.config)
+///     .extract()?;
+/// assert_eq!(config.host, "localhost");
+/// assert_eq!(config.port, 8080);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Figment`]: figment::Figment
+impl<T: Serialize> Provider for PathTo<T> {
+    fn metadata(&self) -> Metadata {
+        file_metadata(self.path())
+    }
+
+    fn data(&self) -> Result<Map<Profile, Dict>, figment::Error> {
+        Serialized::defaults(&self.data).data()
+    }
+}
+
+/// Implements [`Provider`] for an adapter reloading a `PathTo<T>`, providing
+/// the document that is current when the [`Figment`] is extracted
+///
+/// [`Figment`]: figment::Figment
+macro_rules! reloading_provider {
+    (impl<$($param:ident: $bound:path),*> $adapter:ty) => {
+        impl<$($param: $bound),*> Provider for $adapter {
+            fn metadata(&self) -> Metadata {
+                file_metadata(self.get().path())
+            }
+
+            fn data(&self) -> Result<Map<Profile, Dict>, figment::Error> {
+                Serialized::defaults(&self.get().data).data()
+            }
+        }
+    };
+}
+
+#[cfg(any(
+    feature = "periodic",
+    feature = "reloading",
+    feature = "signal",
+    feature = "sse",
+    feature = "mqtt"
+))]
+reloading_provider!(impl<T: Serialize> crate::ConfigWatcher<PathTo<T>>);
+
+#[cfg(feature = "reloading")]
+reloading_provider!(
+    impl<T: Serialize, D: crate::time::Time, B: crate::reloading::WatchBackend>
+    crate::reloading::Reloading<PathTo<T>, D, B>
+);
+
+#[cfg(all(feature = "reloading", feature = "periodic"))]
+reloading_provider!(
+    impl<T: Serialize, P: crate::time::Time, D: crate::time::Time>
+    crate::refreshing::Refreshing<PathTo<T>, P, D>
+);
+
+#[cfg(feature = "periodic")]
+reloading_provider!(
+    impl<T: Serialize, P: crate::time::Schedule, J: crate::time::Time>
+    crate::periodic::Periodic<PathTo<T>, P, J>
+);
+
+#[cfg(all(unix, feature = "signal"))]
+reloading_provider!(
+    impl<T: Serialize, S: crate::signal::ReloadSignal> crate::signal::OnSignal<PathTo<T>, S>
+);
+
+#[cfg(feature = "blocking")]
+reloading_provider!(
+    impl<T: Serialize, D: crate::time::Time> crate::blocking::Reloading<PathTo<T>, D>
+);
@@ -0,0 +1,163 @@
+//! Provides the [`Refreshing`] adapter combining filesystem notifications with
+//! a periodic fallback reload
+
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex};
+
+use crate::fs::PathTo;
+use crate::handle::RunState;
+use crate::reloading::Reloading;
+use crate::time::{Millis, Minutes, Time};
+use crate::traits::FromPath;
+
+/// Given a [`Path`] from the user, provides a utility that reloads the file
+/// at the path whenever the file is updated, and additionally at a fixed
+/// interval as a safety net
+///
+/// Filesystem notifications can be missed, e.g. on network filesystems. In
+/// addition to everything [`Reloading`] does, [`Refreshing`] unconditionally
+/// reloads the file every period `P` (five minutes by default), publishing on
+/// the same channel. `D` is the debounce duration for filesystem events.
+///
+/// [`Refreshing`] dereferences to [`Reloading`], and in turn to its
+/// [`ConfigWatcher`], so all of their methods (such as [`ConfigWatcher::get`]
+/// and [`ConfigWatcher::receiver`]) are available.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Json config that's reloaded on change, and at least every minute
+///     #[clap(long)]
+///     config: Refreshing<PathTo<JsonOf<serde_json::Value>>, Minutes<1>>,
+/// }
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+///
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+/// assert_eq!(cli.config.get().data.0, serde_json::json!({"hello":"world"}));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Path`]: std::path::Path
+/// [`ConfigWatcher`]: crate::ConfigWatcher
+/// [`ConfigWatcher::get`]: crate::ConfigWatcher::get
+/// [`ConfigWatcher::receiver`]: crate::ConfigWatcher::receiver
+#[derive(Clone)]
+#[must_use = "Dropping the `Refreshing` will cancel the file watch"]
+pub struct Refreshing<T, P: Time = Minutes<5>, D: Time = Millis<100>> {
+    reloading: Reloading<T, D>,
+    stop_tx: Arc<Mutex<Option<mpsc::Sender<()>>>>,
+    period: std::marker::PhantomData<P>,
+}
+
+impl<T, P: Time, D: Time> Refreshing<T, P, D> {
+    /// Permanently stop watching the file and reloading it periodically
+    ///
+    /// Receivers keep observing the last published document.
+    pub fn shutdown(&self) {
+        self.reloading.shutdown();
+        if let Ok(mut stop_tx) = self.stop_tx.lock() {
+            stop_tx.take();
+        }
+    }
+}
+
+impl<T, P: Time, D: Time> std::ops::Deref for Refreshing<T, P, D> {
+    type Target = Reloading<T, D>;
+    fn deref(&self) -> &Self::Target {
+        &self.reloading
+    }
+}
+
+impl<T, P: Time, D: Time> std::fmt::Debug for Refreshing<PathTo<T>, P, D>
+where
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Refreshing")
+            .field(&*self.reloading.receiver().borrow().document)
+            .finish()
+    }
+}
+
+impl<T, P: Time, D: Time> Refreshing<PathTo<T>, P, D>
+where
+    T: FromPath + Send + Sync + 'static,
+{
+    /// Load the file at `path`, watch it for changes and refresh it every period
+    ///
+    /// This is what parsing the adapter from an argument does, for building
+    /// values outside of clap (e.g. in tests).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap_adapters::prelude::*;
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+    ///
+    /// let config = Refreshing::<PathTo<JsonOf<serde_json::Value>>>::watch(&config_path)?;
+    /// assert_eq!(config.get().data.0, serde_json::json!({"hello":"world"}));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn watch(path: impl Into<PathBuf>) -> Result<Self, crate::Error> {
+        let path = path.into();
+        let reloading = Reloading::<PathTo<T>, D>::watch(path.clone())?;
+
+        // The periodic thread exits once the sender is dropped, which happens
+        // on shutdown or when the last clone of the adapter is dropped
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+        let reload_handle = reloading.reload_handle();
+        let state_rx = reloading.lifecycle().subscribe();
+        let spawned = std::thread::Builder::new().spawn(move || {
+            while let Err(mpsc::RecvTimeoutError::Timeout) = stop_rx.recv_timeout(P::PERIOD) {
+                match *state_rx.borrow() {
+                    RunState::Running => {}
+                    RunState::Paused => continue,
+                    RunState::Shutdown => break,
+                }
+
+                if let Err(error) = reload_handle.reload_now() {
+                    reload_handle.report(&error, "Failed to reload after time period");
+                }
+            }
+        });
+        spawned.map_err(|error| crate::Error::Watch {
+            path,
+            source: notify::Error::io(error),
+        })?;
+
+        let item = Self {
+            reloading,
+            stop_tx: Arc::new(Mutex::new(Some(stop_tx))),
+            period: std::marker::PhantomData,
+        };
+        Ok(item)
+    }
+}
+
+impl<T, P: Time, D: Time> std::str::FromStr for Refreshing<PathTo<T>, P, D>
+where
+    T: FromPath + Send + Sync + 'static,
+{
+    type Err = crate::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::watch(s)
+    }
+}
@@ -1,19 +1,49 @@
 //! Provides the [`Reloading`] adapter for reloading files after fs notifications
 
-use std::sync::Arc;
-
-use notify::{FsEventWatcher, RecursiveMode};
-use tokio::sync::watch;
-use tokio_stream::Stream;
+use std::path::PathBuf;
+use std::time::Duration;
 
+use crate::dir::DirOf;
 use crate::fs::PathTo;
-use crate::traits::FromReader;
+use crate::parser::AdapterParser;
+use crate::time::{Millis, Seconds, Time};
+use crate::traits::{FromMerged, FromPath};
+use crate::watcher::ConfigWatcher;
 
 /// Given a [`Path`] from the user, provides a utility that reloads the file
 /// at the path whenever the file is updated
 ///
-/// - Use [`Reloading::get`] to get the file contents at a given moment
-/// - Use [`Reloading::receiver`] to get a tokio [`watch::Receiver`]
+/// - Use [`ConfigWatcher::get`] to get the file contents at a given moment
+/// - Use [`ConfigWatcher::receiver`] to get a tokio [`watch::Receiver`]
+/// - Use [`ConfigWatcher::reload_now`] to reload the file without a filesystem event
+///
+/// [`Reloading`] dereferences to the [`ConfigWatcher`] it is built on, so all
+/// of the watcher's methods are available.
+///
+/// Editors and atomic writers often emit several filesystem events for a
+/// single save. To avoid reloading the file once per event, events are
+/// debounced: the file is reloaded only after no new events have arrived
+/// for the duration `D`, which defaults to 100 milliseconds.
+///
+/// Rather than watching the file itself, [`Reloading`] watches the directory
+/// containing it and reacts to events for the file's name. This means reloads
+/// keep working when the file is replaced by a rename (as done by many editors
+/// and atomic writers) or removed and re-created. If the path is a symlink, the
+/// directory of its target is watched as well, and a change of the symlink's
+/// target (such as a Kubernetes ConfigMap update) triggers a reload.
+///
+/// `Reloading<DirOf<T>>` watches a directory of config fragments instead,
+/// rebuilding the merged document whenever any file in it changes.
+///
+/// All [`Reloading`] values in a process share a single OS file watcher, so
+/// many reloading arguments do not exhaust the system's watcher limits.
+///
+/// OS notifications are not delivered for network filesystems such as NFS or
+/// SMB mounts. For files on those, select the [`Polling`] backend with the `B`
+/// parameter, e.g. `Reloading<PathTo<T>, Millis<100>, Polling<Seconds<5>>>`,
+/// to check the file for changes at a fixed interval instead. On Windows, files
+/// on UNC shares (`\\server\share\config.toml`) are polled every 2 seconds
+/// even with the default backend.
 ///
 /// # Example
 ///
@@ -27,103 +57,324 @@ use crate::traits::FromReader;
 ///     /// Path to a Json config that's reloaded
 ///     #[clap(long)]
 ///     config: Reloading<PathTo<JsonOf<serde_json::Value>>>,
+///
+///     /// Path to a Json config that's reloaded once writes settle for a second
+///     #[clap(long)]
+///     slow_config: Reloading<PathTo<JsonOf<serde_json::Value>>, Seconds<1>>,
+///
+///     /// Path to a Json config on a network share, checked every 5 seconds
+///     #[clap(long)]
+///     shared_config: Reloading<PathTo<JsonOf<serde_json::Value>>, Millis<100>, Polling<Seconds<5>>>,
 /// }
 ///
-/// let cli = Cli::parse_from(["app", "--config=./config.json"]);
+/// let cli = Cli::parse_from([
+///     "app",
+///     "--config=./config.json",
+///     "--slow-config=./slow_config.json",
+///     "--shared-config=/mnt/share/config.json",
+/// ]);
 /// let current_config = cli.config.get();
 /// let config_rx = cli.config.receiver();
 /// # }
 /// ```
 ///
 /// > **Note**: [`Reloading`] is powered by [`notify`], which has some
-/// [known problems], so check out the caveats if you run into trouble
+/// > [known problems], so check out the caveats if you run into trouble
 ///
 /// [`Path`]: std::path::Path
 /// [`watch::Receiver`]: tokio::sync::watch
 /// [known problems]: https://docs.rs/notify/latest/notify/#known-problems
 #[derive(Clone)]
 #[must_use = "Dropping the `Reloading` will cancel the file watch"]
-pub struct Reloading<T> {
-    reload_rx: watch::Receiver<T>,
-    _watcher: Arc<FsEventWatcher>,
+pub struct Reloading<T, D: Time = Millis<100>, B: WatchBackend = Recommended> {
+    watcher: ConfigWatcher<T>,
+    debounce: std::marker::PhantomData<(D, B)>,
 }
 
-impl<T: Clone> Reloading<T> {
-    /// Get the current value of the inner document
-    pub fn get(&self) -> T {
-        self.reload_rx.borrow().clone()
+impl<T, D: Time, B: WatchBackend> std::ops::Deref for Reloading<T, D, B> {
+    type Target = ConfigWatcher<T>;
+    fn deref(&self) -> &Self::Target {
+        &self.watcher
     }
+}
 
-    /// Get a receiver channel that yields updated documents after filesystem changes
-    pub fn receiver(&self) -> watch::Receiver<T> {
-        self.reload_rx.clone()
+impl<T, D: Time, B: WatchBackend> std::fmt::Debug for Reloading<T, D, B>
+where
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Reloading")
+            .field(&*self.watcher.get())
+            .finish()
     }
 }
 
-impl<T: Clone + Send + Sync + 'static> Reloading<T> {
-    /// Get a stream of document changes
-    pub fn stream(&self) -> impl Stream<Item = T> {
-        tokio_stream::wrappers::WatchStream::new(self.reload_rx.clone())
+impl<T, D: Time, B: WatchBackend> clap::builder::ValueParserFactory for Reloading<PathTo<T>, D, B>
+where
+    T: FromPath + Clone + Send + Sync + 'static,
+    D: Clone + Send + Sync + 'static,
+    B: Clone + Send + Sync + 'static,
+{
+    type Parser = AdapterParser<Self>;
+    fn value_parser() -> Self::Parser {
+        AdapterParser::with_value_hint(clap::ValueHint::FilePath)
     }
 }
 
-impl<T: std::fmt::Debug> std::fmt::Debug for Reloading<PathTo<T>> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_tuple("Reloading")
-            .field(&*self.reload_rx.borrow())
-            .finish()
+impl<T, D: Time, B: WatchBackend> Reloading<PathTo<T>, D, B>
+where
+    T: FromPath + Send + Sync + 'static,
+{
+    /// Load the file at `path` and start watching it for changes
+    ///
+    /// This is what parsing the adapter from an argument does, for building
+    /// values outside of clap (e.g. in tests).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap_adapters::prelude::*;
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+    ///
+    /// let config = Reloading::<PathTo<JsonOf<serde_json::Value>>>::watch(&config_path)?;
+    /// assert_eq!(config.get().data.0, serde_json::json!({"hello":"world"}));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Changes are detected whichever form the path takes, including the
+    /// verbatim paths (`\\?\C:\...`) that Windows APIs return:
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::time::Duration;
+    ///
+    /// use clap_adapters::prelude::*;
+    ///
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = std::fs::canonicalize(config_dir.path())?.join("config.json");
+    /// std::fs::write(&config_path, r#"{"level":"info"}"#)?;
+    ///
+    /// let config = Reloading::<PathTo<JsonOf<serde_json::Value>>>::watch(&config_path)?;
+    /// let mut config_rx = config.receiver();
+    ///
+    /// std::fs::write(&config_path, r#"{"level":"debug"}"#)?;
+    /// let runtime = tokio::runtime::Runtime::new()?;
+    /// runtime.block_on(async {
+    ///     tokio::time::timeout(Duration::from_secs(10), config_rx.changed()).await
+    /// })??;
+    /// assert_eq!(config.get().data.0, serde_json::json!({"level":"debug"}));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn watch(path: impl Into<PathBuf>) -> Result<Self, crate::Error> {
+        let watcher = ConfigWatcher::spawn(path, D::PERIOD, B::POLL_INTERVAL, B::is_relevant)?;
+        let item = Self {
+            watcher,
+            debounce: std::marker::PhantomData,
+        };
+        Ok(item)
     }
 }
 
-impl<T: FromReader + Clone + Send + Sync + 'static> std::str::FromStr for Reloading<PathTo<T>> {
-    type Err = anyhow::Error;
+impl<T, D: Time, B: WatchBackend> std::str::FromStr for Reloading<PathTo<T>, D, B>
+where
+    T: FromPath + Send + Sync + 'static,
+{
+    type Err = crate::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        use notify::Watcher;
-        let path_to = PathTo::<T>::from_str(s)?;
-        let (reload_tx, reload_rx) = watch::channel(path_to.clone());
-
-        let path = path_to.path.clone();
-        let mut watcher = notify::recommended_watcher(move |result| {
-            if let Err(error) = result {
-                tracing::warn!(
-                    error = format!("{error:#}"),
-                    "Notify triggered with error, skipping"
-                );
-            }
-
-            // Attempt to re-open file and read it into our typed format
-            let data_result = (|| -> anyhow::Result<T> {
-                let file = std::fs::File::open(&path)?;
-                let mut reader = std::io::BufReader::new(file);
-                let data = T::from_reader(&mut reader)?;
-                Ok(data)
-            })();
-
-            let data = match data_result {
-                Ok(data) => data,
-                Err(error) => {
-                    tracing::error!(
-                        error = format!("{error:#}"),
-                        path = %path.display(),
-                        "Failed to hotreload after notify",
-                    );
-                    return;
-                }
-            };
-
-            let updated_path_to = PathTo {
-                path: path.clone(),
-                data,
-            };
-
-            reload_tx.send_replace(updated_path_to);
-        })?;
-        watcher.watch(&path_to.path, RecursiveMode::NonRecursive)?;
+        Self::watch(s)
+    }
+}
+
+impl<T, D: Time, B: WatchBackend> clap::builder::ValueParserFactory for Reloading<DirOf<T>, D, B>
+where
+    T: FromMerged + Clone + Send + Sync + 'static,
+    D: Clone + Send + Sync + 'static,
+    B: Clone + Send + Sync + 'static,
+{
+    type Parser = AdapterParser<Self>;
+    fn value_parser() -> Self::Parser {
+        AdapterParser::with_value_hint(clap::ValueHint::DirPath)
+    }
+}
 
+impl<T, D: Time, B: WatchBackend> Reloading<DirOf<T>, D, B>
+where
+    T: FromMerged + Send + Sync + 'static,
+{
+    /// Merge the files in the directory at `path` and watch them for changes
+    ///
+    /// The merged document is rebuilt whenever a file in the directory is
+    /// created, changed or removed. Subdirectories are not watched, as
+    /// [`DirOf`] doesn't read them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::time::Duration;
+    ///
+    /// use clap::Parser;
+    /// use clap_adapters::prelude::*;
+    ///
+    /// #[derive(Debug, Parser)]
+    /// struct Cli {
+    ///     /// Path to a directory of Json config fragments that's reloaded
+    ///     #[clap(long)]
+    ///     config_dir: Reloading<DirOf<JsonOf<serde_json::Value>>>,
+    /// }
+    ///
+    /// // Create a directory with a config fragment
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_dir_string = config_dir.path().display().to_string();
+    /// std::fs::write(config_dir.path().join("00-defaults.json"), r#"{"port": 80}"#)?;
+    ///
+    /// let cli = Cli::parse_from(["app", "--config-dir", &config_dir_string]);
+    /// let mut config_rx = cli.config_dir.receiver_changes_only();
+    ///
+    /// // Adding a fragment rebuilds the merged document
+    /// std::fs::write(config_dir.path().join("10-local.json"), r#"{"port": 8080}"#)?;
+    /// let runtime = tokio::runtime::Runtime::new()?;
+    /// runtime.block_on(async {
+    ///     tokio::time::timeout(Duration::from_secs(10), config_rx.changed()).await
+    /// })??;
+    /// let config = cli.config_dir.get();
+    /// assert_eq!(config.files.len(), 2);
+    /// assert_eq!(config.data.0, serde_json::json!({"port": 8080}));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn watch(path: impl Into<PathBuf>) -> Result<Self, crate::Error> {
+        let watcher = ConfigWatcher::spawn_dir(path, D::PERIOD, B::POLL_INTERVAL, B::is_relevant)?;
         let item = Self {
-            reload_rx,
-            _watcher: Arc::new(watcher),
+            watcher,
+            debounce: std::marker::PhantomData,
         };
         Ok(item)
     }
 }
+
+impl<T, D: Time, B: WatchBackend> std::str::FromStr for Reloading<DirOf<T>, D, B>
+where
+    T: FromMerged + Send + Sync + 'static,
+{
+    type Err = crate::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::watch(s)
+    }
+}
+
+#[cfg(feature = "test-util")]
+impl<T, D: Time> Reloading<T, D>
+where
+    T: Send + Sync + 'static,
+{
+    /// Create an adapter around a `watcher` that doesn't watch a file
+    pub(crate) fn from_watcher(watcher: ConfigWatcher<T>) -> Self {
+        Self {
+            watcher,
+            debounce: std::marker::PhantomData,
+        }
+    }
+}
+
+/// Trait for type markers selecting how [`Reloading`] detects file changes
+///
+/// # Example
+///
+/// By default, only events that may change a file's contents trigger a
+/// reload. A custom backend may override [`WatchBackend::is_relevant`] to
+/// also reload when the file's metadata, such as its permissions, changes:
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use std::time::Duration;
+///
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+/// use notify::event::{EventKind, ModifyKind};
+///
+/// /// OS notifications, also reloading on metadata changes
+/// #[derive(Debug, Clone)]
+/// enum WithMetadata {}
+/// impl WatchBackend for WithMetadata {
+///     const POLL_INTERVAL: Option<Duration> = None;
+///     fn is_relevant(kind: &EventKind) -> bool {
+///         matches!(kind, EventKind::Modify(ModifyKind::Metadata(_))) || Recommended::is_relevant(kind)
+///     }
+/// }
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Json config
+///     #[clap(long)]
+///     config: Reloading<PathTo<JsonOf<serde_json::Value>>, Millis<100>, WithMetadata>,
+/// }
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+///
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+///
+/// // Changing only the permissions reloads the file, though its unchanged
+/// // contents are not published again
+/// #[cfg(unix)]
+/// {
+///     use std::os::unix::fs::PermissionsExt;
+///
+///     let config_rx = cli.config.receiver_changes_only();
+///     std::fs::set_permissions(&config_path, std::fs::Permissions::from_mode(0o600))?;
+///     let runtime = tokio::runtime::Runtime::new()?;
+///     runtime.block_on(async {
+///         let reloaded = async {
+///             while cli.config.stats().attempts == 0 {
+///                 tokio::time::sleep(Duration::from_millis(10)).await;
+///             }
+///         };
+///         tokio::time::timeout(Duration::from_secs(10), reloaded).await
+///     })?;
+///     assert!(!config_rx.has_changed()?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub trait WatchBackend {
+    /// How often to poll the file for changes, or `None` to rely on OS notifications
+    const POLL_INTERVAL: Option<Duration>;
+
+    /// Whether an event of `kind` on the file triggers a reload
+    ///
+    /// By default, creating, removing, renaming and writing to the file do,
+    /// as do events of unknown kind. Accesses and metadata-only changes
+    /// such as permissions are ignored.
+    fn is_relevant(kind: &notify::EventKind) -> bool {
+        crate::fs_watch::changes_contents(kind)
+    }
+}
+
+/// Detects changes with the OS's filesystem notifications, such as inotify
+#[derive(Debug, Clone, Copy)]
+pub enum Recommended {}
+impl WatchBackend for Recommended {
+    const POLL_INTERVAL: Option<Duration> = None;
+}
+
+/// Detects changes by polling the watched directory every `P`
+///
+/// This works on network filesystems such as NFS or SMB mounts, where OS
+/// notifications aren't delivered. Each poll compares the modification time
+/// and contents of the file, without reading other files in its directory.
+#[derive(Debug, Clone, Copy)]
+pub struct Polling<P: Time = Seconds<2>>(std::marker::PhantomData<P>);
+impl<P: Time> WatchBackend for Polling<P> {
+    const POLL_INTERVAL: Option<Duration> = Some(P::PERIOD);
+}
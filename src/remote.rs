@@ -0,0 +1,129 @@
+//! Plumbing shared by the adapters that subscribe to remote sources
+
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::handle::RunState;
+use crate::watcher::ConfigWatcher;
+use crate::Error;
+
+/// How long to wait for the server to accept a connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Create a watcher of `document`, and a function publishing new documents to it
+///
+/// The function returns `false` once the watcher was shut down, so that
+/// the background thread calling it can stop.
+pub(crate) fn publisher<T>(
+    document: T,
+    location: String,
+) -> (
+    ConfigWatcher<T>,
+    impl FnMut(Result<T, Error>) -> bool + Send + 'static,
+)
+where
+    T: Send + Sync + 'static,
+{
+    let (watcher, set_next) = ConfigWatcher::from_pending(document, location);
+    let reload_handle = watcher.reload_handle();
+    let state_rx = watcher.lifecycle().subscribe();
+    let publish = move |result| {
+        if *state_rx.borrow() == RunState::Shutdown {
+            return false;
+        }
+
+        // Documents received while paused are picked up upon resuming
+        set_next(result);
+        if *state_rx.borrow() == RunState::Running {
+            if let Err(error) = reload_handle.reload_now() {
+                reload_handle.report(&error, "Failed to hotreload after update");
+            }
+        }
+        true
+    };
+    (watcher, publish)
+}
+
+/// Wrap an IO error that occurred while subscribing to `url`
+pub(crate) fn subscribe_error(url: &str, source: std::io::Error) -> Error {
+    Error::Subscribe {
+        url: url.to_string(),
+        source,
+    }
+}
+
+/// Closes the connection once the last clone of the adapter is dropped
+pub(crate) struct Subscription {
+    pub(crate) connection: Arc<Connection>,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.connection.close();
+    }
+}
+
+/// The socket of the current connection, shared so it can be closed from another thread
+#[derive(Default)]
+pub(crate) struct Connection {
+    closed: AtomicBool,
+    socket: Mutex<Option<TcpStream>>,
+}
+
+impl Connection {
+    /// Connect to `host` and use the socket as the current connection
+    pub(crate) fn open(&self, host: &str, port: u16) -> std::io::Result<TcpStream> {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let mut last_error = None;
+        for address in (host, port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+                Ok(socket) => {
+                    self.replace(&socket)?;
+                    return Ok(socket);
+                }
+                Err(error) => last_error = Some(error),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "host has no addresses")
+        }))
+    }
+
+    /// Use `socket` as the current connection, unless the subscription was closed
+    fn replace(&self, socket: &TcpStream) -> std::io::Result<()> {
+        let mut current = self.socket.lock().expect("socket lock poisoned");
+        if self.is_closed() {
+            let _ = socket.shutdown(Shutdown::Both);
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionAborted,
+                "subscription was closed",
+            ));
+        }
+        if let Some(previous) = current.replace(socket.try_clone()?) {
+            let _ = previous.shutdown(Shutdown::Both);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        match &*self.socket.lock().expect("socket lock poisoned") {
+            Some(socket) => socket.set_read_timeout(timeout),
+            None => Ok(()),
+        }
+    }
+
+    /// Close the current connection, and refuse to open new ones
+    pub(crate) fn close(&self) {
+        let socket = self.socket.lock().expect("socket lock poisoned");
+        self.closed.store(true, Ordering::SeqCst);
+        if let Some(socket) = &*socket {
+            let _ = socket.shutdown(Shutdown::Both);
+        }
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,94 @@
+//! Implements [`schemars::JsonSchema`] for [`PathTo`] and the format adapters
+//!
+//! Each adapter has the schema of the data it holds, as it serializes as
+//! just that data. This lets applications publish the schema of their config
+//! files, e.g. from a `--config-schema` flag, for editors to offer completion
+//! and validation.
+
+use std::borrow::Cow;
+
+use schemars::{JsonSchema, Schema, SchemaGenerator};
+
+use crate::fs::PathTo;
+
+/// Implements [`JsonSchema`] for an adapter as the schema of its inner `T`
+macro_rules! transparent_schema {
+    ($adapter:ident) => {
+        impl<T: JsonSchema> JsonSchema for $adapter<T> {
+            fn inline_schema() -> bool {
+                T::inline_schema()
+            }
+
+            fn schema_name() -> Cow<'static, str> {
+                T::schema_name()
+            }
+
+            fn schema_id() -> Cow<'static, str> {
+                T::schema_id()
+            }
+
+            fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+                T::json_schema(generator)
+            }
+        }
+    };
+}
+
+transparent_schema!(PathTo);
+
+#[cfg(feature = "json")]
+transparent_schema!(JsonOf);
+#[cfg(feature = "json")]
+use crate::json::JsonOf;
+
+#[cfg(feature = "toml")]
+transparent_schema!(TomlOf);
+#[cfg(feature = "toml")]
+use crate::toml::TomlOf;
+
+#[cfg(any(feature = "yaml", feature = "yaml-ng"))]
+transparent_schema!(YamlOf);
+#[cfg(any(feature = "yaml", feature = "yaml-ng"))]
+use crate::yaml::YamlOf;
+
+impl<T: JsonSchema> PathTo<T> {
+    /// Generate the Json Schema of the documents loaded as `T`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap::Parser;
+    /// use clap_adapters::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, serde::Deserialize, schemars::JsonSchema)]
+    /// struct Config {
+    ///     /// The port to listen on
+    ///     port: u16,
+    /// }
+    ///
+    /// #[derive(Debug, Parser)]
+    /// struct Cli {
+    ///     /// Path to a Json config
+    ///     #[clap(long, required_unless_present = "config_schema")]
+    ///     config: Option<PathTo<JsonOf<Config>>>,
+    ///
+    ///     /// Print the Json Schema of the config file and exit
+    ///     #[clap(long)]
+    ///     config_schema: bool,
+    /// }
+    ///
+    /// let cli = Cli::parse_from(["app", "--config-schema"]);
+    /// if cli.config_schema {
+    ///     let schema = PathTo::<JsonOf<Config>>::schema();
+    ///     let schema = serde_json::to_value(&schema)?;
+    ///     assert_eq!(schema["title"], "Config");
+    ///     assert_eq!(schema["properties"]["port"]["description"], "The port to listen on");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn schema() -> Schema {
+        schemars::schema_for!(T)
+    }
+}
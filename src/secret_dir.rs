@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::Error;
+
+/// An adapter for loading a directory of secrets with one file per key
+///
+/// Kubernetes secret volumes and similar mounts expose each key of a secret
+/// as a file named after the key. Given a directory path from the user,
+/// `SecretDirOf<T>` reads every regular file in it into a map from file name
+/// to contents, and deserializes that map into `T`, which is a
+/// `HashMap<String, String>` by default. Hidden files, such as the `..data`
+/// entries Kubernetes uses for atomic updates, are skipped.
+///
+/// Secret files often end with a newline added by whoever created them. With
+/// `N` set to [`TrimNewlines`], trailing newlines are removed from each value;
+/// by default ([`KeepNewlines`]) values are kept exactly as stored.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Clone, serde::Deserialize)]
+/// struct Database {
+///     username: String,
+///     password: String,
+/// }
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a directory with a file per secret
+///     #[clap(long)]
+///     secrets: SecretDirOf,
+///
+///     /// Path to the mounted database secret
+///     #[clap(long)]
+///     database: SecretDirOf<Database, TrimNewlines>,
+/// }
+///
+/// // Create a directory with a file per key, as a secret mount would
+/// let secret_dir = tempfile::tempdir()?;
+/// let secret_dir_string = secret_dir.path().display().to_string();
+/// std::fs::write(secret_dir.path().join("username"), "admin\n")?;
+/// std::fs::write(secret_dir.path().join("password"), "hunter2\n")?;
+///
+/// let cli = Cli::parse_from([
+///     "app",
+///     "--secrets",
+///     &secret_dir_string,
+///     "--database",
+///     &secret_dir_string,
+/// ]);
+/// assert_eq!(cli.secrets.data["password"], "hunter2\n");
+/// assert_eq!(cli.database.data.password, "hunter2");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SecretDirOf<T = HashMap<String, String>, N: Newlines = KeepNewlines> {
+    /// The directory path given as an argument by the user
+    pub path: PathBuf,
+
+    /// The data deserialized from the files in the directory
+    pub data: T,
+
+    marker: std::marker::PhantomData<N>,
+}
+
+impl<T, N: Newlines> SecretDirOf<T, N>
+where
+    T: serde::de::DeserializeOwned,
+{
+    /// Read every file in the directory at `path` and deserialize them into `T`
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+
+        let mut secrets = serde_json::Map::new();
+        let entries = std::fs::read_dir(&path).map_err(|error| Error::io(&path, error))?;
+        for entry in entries {
+            let file_path = entry.map_err(|error| Error::io(&path, error))?.path();
+            let Some(key) = file_path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if key.starts_with('.') || !file_path.is_file() {
+                continue;
+            }
+
+            let contents = std::fs::read_to_string(&file_path)
+                .map_err(|error| Error::io(&file_path, error))?;
+            let value = match N::TRIM {
+                true => contents.trim_end_matches(['\n', '\r']).to_string(),
+                false => contents,
+            };
+            secrets.insert(key.to_string(), serde_json::Value::String(value));
+        }
+
+        let data = serde_json::from_value(serde_json::Value::Object(secrets))
+            .map_err(|error| Error::parse(path.clone(), error))?;
+        let item = SecretDirOf {
+            path,
+            data,
+            marker: std::marker::PhantomData,
+        };
+        Ok(item)
+    }
+}
+
+impl<T, N: Newlines> std::str::FromStr for SecretDirOf<T, N>
+where
+    T: serde::de::DeserializeOwned,
+{
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::load(s)
+    }
+}
+
+/// Trait for type markers selecting how [`SecretDirOf`] treats trailing newlines
+pub trait Newlines {
+    /// Whether trailing newlines are removed from each value
+    const TRIM: bool;
+}
+
+/// Keeps each secret exactly as stored in its file
+#[derive(Debug, Clone, Copy)]
+pub enum KeepNewlines {}
+impl Newlines for KeepNewlines {
+    const TRIM: bool = false;
+}
+
+/// Removes trailing newlines (`\n` and `\r\n`) from each secret
+#[derive(Debug, Clone, Copy)]
+pub enum TrimNewlines {}
+impl Newlines for TrimNewlines {
+    const TRIM: bool = true;
+}
@@ -0,0 +1,217 @@
+//! Provides the [`OnSignal`] adapter for reloading files when the process is signalled
+
+use std::path::PathBuf;
+
+use tokio::signal::unix::SignalKind;
+
+use crate::{fs::PathTo, handle::RunState, traits::FromPath, watcher::ConfigWatcher};
+
+/// Given a [`Path`] from the user, provides a utility that reloads the file
+/// at the path whenever the process receives a Unix signal
+///
+/// By convention, daemons re-read their configuration upon receiving
+/// `SIGHUP`, which is the default signal `S`. Use [`User1`] or [`User2`] to
+/// reload on `SIGUSR1` or `SIGUSR2` instead.
+///
+/// - Use [`ConfigWatcher::get`] to get the file contents at a given moment
+/// - Use [`ConfigWatcher::receiver`] to get a tokio [`watch::Receiver`]
+/// - Use [`ConfigWatcher::reload_now`] to reload the file without waiting for a signal
+///
+/// [`OnSignal`] dereferences to the [`ConfigWatcher`] it is built on, so all
+/// of the watcher's methods are available.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Json config that's reloaded on SIGHUP
+///     #[clap(long)]
+///     config: OnSignal<PathTo<JsonOf<serde_json::Value>>>,
+/// }
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+///
+/// let runtime = tokio::runtime::Runtime::new()?;
+/// runtime.block_on(async {
+///     let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+///     let mut config_rx = cli.config.receiver();
+///     assert_eq!(cli.config.get().data.0, serde_json::json!({"hello":"world"}));
+///
+///     // Update the file, then ask ourselves to reload it
+///     std::fs::write(&config_path, r#"{"hello":"signal"}"#)?;
+///     std::process::Command::new("kill")
+///         .args(["-HUP", &std::process::id().to_string()])
+///         .status()?;
+///
+///     config_rx.changed().await?;
+///     assert_eq!(cli.config.get().data.0, serde_json::json!({"hello":"signal"}));
+///     anyhow::Ok(())
+/// })?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// > *Note*: [`OnSignal`] requires a tokio runtime to be active before calling
+/// > any of the `clap::Parser` functions, and fails to parse otherwise
+///
+/// [`Path`]: std::path::Path
+/// [`watch::Receiver`]: tokio::sync::watch
+#[derive(Clone)]
+#[must_use = "Dropping the `OnSignal` will stop reloading on signals"]
+pub struct OnSignal<T, S: ReloadSignal = Hangup> {
+    watcher: ConfigWatcher<T>,
+    signal: std::marker::PhantomData<S>,
+}
+
+impl<T, S: ReloadSignal> std::ops::Deref for OnSignal<T, S> {
+    type Target = ConfigWatcher<T>;
+    fn deref(&self) -> &Self::Target {
+        &self.watcher
+    }
+}
+
+impl<T, S: ReloadSignal> std::fmt::Debug for OnSignal<PathTo<T>, S>
+where
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("OnSignal")
+            .field(&*self.watcher.get())
+            .finish()
+    }
+}
+
+impl<T, S: ReloadSignal> OnSignal<PathTo<T>, S>
+where
+    T: FromPath + Send + Sync + 'static,
+{
+    /// Load the file at `path` and reload it whenever signal `S` is received
+    ///
+    /// This is what parsing the adapter from an argument does, for building
+    /// values outside of clap (e.g. in tests). Must be called within a tokio
+    /// runtime, and returns [`Error::Signal`] otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap_adapters::prelude::*;
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+    ///
+    /// // Without a runtime, the signal can't be listened for
+    /// assert!(OnSignal::<PathTo<JsonOf<serde_json::Value>>, User1>::listen(&config_path).is_err());
+    ///
+    /// let runtime = tokio::runtime::Runtime::new()?;
+    /// runtime.block_on(async {
+    ///     let config = OnSignal::<PathTo<JsonOf<serde_json::Value>>, User1>::listen(&config_path)?;
+    ///     assert_eq!(config.get().data.0, serde_json::json!({"hello":"world"}));
+    ///     anyhow::Ok(())
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Error::Signal`]: crate::Error::Signal
+    pub fn listen(path: impl Into<PathBuf>) -> Result<Self, crate::Error> {
+        let path_to = PathTo::<T>::load(path)?;
+
+        // Registering the handler and spawning the listener panic without a runtime
+        if tokio::runtime::Handle::try_current().is_err() {
+            let source = std::io::Error::other("no tokio runtime is active to listen for signals");
+            return Err(crate::Error::Signal { source });
+        }
+
+        // Register the handler up front so that failures surface while parsing
+        let mut signal = tokio::signal::unix::signal(S::KIND)
+            .map_err(|source| crate::Error::Signal { source })?;
+
+        let watcher = ConfigWatcher::from_document(path_to);
+        let mut state_rx = watcher.lifecycle().subscribe();
+        let signal_handle = watcher.reload_handle();
+        tokio::spawn(async move {
+            loop {
+                if *state_rx.borrow_and_update() == RunState::Shutdown {
+                    break;
+                }
+
+                tokio::select! {
+                    received = signal.recv() => {
+                        if received.is_none() {
+                            break;
+                        }
+                    }
+                    changed = state_rx.changed() => {
+                        if changed.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                }
+
+                if *state_rx.borrow() != RunState::Running {
+                    continue;
+                }
+
+                if let Err(error) = signal_handle.reload_now() {
+                    signal_handle.report(&error, "Failed to reload after signal");
+                }
+            }
+        });
+
+        let item = Self {
+            watcher,
+            signal: std::marker::PhantomData,
+        };
+        Ok(item)
+    }
+}
+
+impl<T, S: ReloadSignal> std::str::FromStr for OnSignal<PathTo<T>, S>
+where
+    T: FromPath + Send + Sync + 'static,
+{
+    type Err = crate::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::listen(s)
+    }
+}
+
+/// Trait for type markers to const-evaluate to a Unix signal
+pub trait ReloadSignal {
+    /// The signal that triggers a reload
+    const KIND: SignalKind;
+}
+
+/// Reload the file on `SIGHUP`
+#[derive(Debug, Clone, Copy)]
+pub enum Hangup {}
+impl ReloadSignal for Hangup {
+    const KIND: SignalKind = SignalKind::hangup();
+}
+
+/// Reload the file on `SIGUSR1`
+#[derive(Debug, Clone, Copy)]
+pub enum User1 {}
+impl ReloadSignal for User1 {
+    const KIND: SignalKind = SignalKind::user_defined1();
+}
+
+/// Reload the file on `SIGUSR2`
+#[derive(Debug, Clone, Copy)]
+pub enum User2 {}
+impl ReloadSignal for User2 {
+    const KIND: SignalKind = SignalKind::user_defined2();
+}
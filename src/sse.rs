@@ -0,0 +1,505 @@
+//! Provides the [`SseSubscribe`] adapter for configs pushed over Server-Sent Events
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::parser::AdapterParser;
+use crate::remote::{self, subscribe_error, Connection, Subscription};
+use crate::traits::FromReader;
+use crate::watcher::ConfigWatcher;
+use crate::Error;
+
+/// How long to wait for the first document while parsing the argument
+const FIRST_EVENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long to wait before reconnecting, unless the server sends a `retry:` field
+const DEFAULT_RETRY: Duration = Duration::from_secs(3);
+
+/// The longest status, header or chunk-size line accepted from the server
+const MAX_HEADER_LINE: u64 = 8 * 1024;
+
+/// The most data accepted for a single event, so a misbehaving server can't exhaust memory
+const MAX_EVENT_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Given an `http://` URL from the user, subscribes to the Server-Sent Events
+/// stream at the URL and treats each event's data as a new document version
+///
+/// The first event is awaited while parsing the argument, so a missing or
+/// malformed document is reported as a usage error like any other adapter.
+/// Later events are received on a background thread and published through
+/// the [`ConfigWatcher`] interface, which [`SseSubscribe`] dereferences to:
+///
+/// - Use [`ConfigWatcher::get`] to get the document at a given moment
+/// - Use [`ConfigWatcher::receiver`] to get a tokio [`watch::Receiver`]
+/// - Use [`ConfigWatcher::stream`] to get a stream of updated documents
+///
+/// A malformed event is reported like a failed reload, and the previous
+/// document is kept. If the connection drops, it is re-established after the
+/// delay given by the server's last `retry:` field (3 seconds by default),
+/// sending the last event ID so the server can resume the stream.
+///
+/// Events of more than 16 MiB are rejected, failing the connection.
+///
+/// > **Note**: Only plain `http://` URLs are supported, as this crate has no
+/// > TLS dependency. Events travel unencrypted and the server is not
+/// > authenticated, so don't subscribe to streams carrying secrets over an
+/// > untrusted network; put a TLS-terminating proxy on localhost instead.
+///
+/// Dropping the last clone of the adapter closes the connection.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use std::io::{BufRead, BufReader, Write};
+///
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// URL of a Server-Sent Events stream of Json configs
+///     #[clap(long)]
+///     config: SseSubscribe<JsonOf<serde_json::Value>>,
+/// }
+///
+/// // Serve a stream with two versions of the config
+/// let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+/// let url = format!("http://{}/config", listener.local_addr()?);
+/// std::thread::spawn(move || -> std::io::Result<()> {
+///     let (mut stream, _) = listener.accept()?;
+///     let mut request = BufReader::new(stream.try_clone()?);
+///     let mut line = String::new();
+///     while request.read_line(&mut line)? > 2 {
+///         line.clear();
+///     }
+///     stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\r\n")?;
+///     stream.write_all(b"data: {\"level\": \"info\"}\n\n")?;
+///     std::thread::sleep(std::time::Duration::from_millis(100));
+///     stream.write_all(b"data: {\"level\": \"debug\"}\n\n")?;
+///     std::thread::sleep(std::time::Duration::from_secs(10));
+///     Ok(())
+/// });
+///
+/// let cli = Cli::parse_from(["app", "--config", &url]);
+/// assert_eq!(cli.config.get().0, serde_json::json!({"level": "info"}));
+///
+/// // The next event is published in the background
+/// let mut config_rx = cli.config.receiver();
+/// let runtime = tokio::runtime::Runtime::new()?;
+/// runtime.block_on(config_rx.changed())?;
+/// assert_eq!(cli.config.get().0, serde_json::json!({"level": "debug"}));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`watch::Receiver`]: tokio::sync::watch
+#[must_use = "Dropping the `SseSubscribe` will close the connection"]
+pub struct SseSubscribe<T> {
+    /// The URL given as an argument by the user
+    pub url: String,
+
+    watcher: ConfigWatcher<T>,
+    subscription: Arc<Subscription>,
+}
+
+impl<T> SseSubscribe<T>
+where
+    T: FromReader + Send + Sync + 'static,
+{
+    /// Connect to the stream at `url`, and wait for its first document
+    pub fn subscribe(url: impl Into<String>) -> Result<Self, Error> {
+        let url = url.into();
+        let endpoint = Endpoint::parse(&url)?;
+
+        let connection = Arc::new(Connection::default());
+        let mut events = endpoint
+            .connect(None, &connection)
+            .and_then(|events| {
+                connection.set_read_timeout(Some(FIRST_EVENT_TIMEOUT))?;
+                Ok(events)
+            })
+            .map_err(|source| subscribe_error(&url, source))?;
+        let data = events
+            .next_event()
+            .and_then(|data| {
+                data.ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::UnexpectedEof,
+                        "stream ended before the first event",
+                    )
+                })
+            })
+            .map_err(|source| subscribe_error(&url, source))?;
+        connection
+            .set_read_timeout(None)
+            .map_err(|source| subscribe_error(&url, source))?;
+        let document = parse_event::<T>(&url, &data)?;
+
+        let (watcher, publish) = remote::publisher(document, url.clone());
+        let mut stream = EventStream {
+            url: url.clone(),
+            endpoint,
+            connection: connection.clone(),
+            last_event_id: events.last_event_id.take(),
+            retry: events.retry.take().unwrap_or(DEFAULT_RETRY),
+        };
+        let subscription = Arc::new(Subscription {
+            connection: connection.clone(),
+        });
+        std::thread::Builder::new()
+            .spawn(move || stream.run(events, publish))
+            .map_err(|source| subscribe_error(&url, source))?;
+
+        Ok(SseSubscribe {
+            url,
+            watcher,
+            subscription,
+        })
+    }
+}
+
+impl<T> std::ops::Deref for SseSubscribe<T> {
+    type Target = ConfigWatcher<T>;
+    fn deref(&self) -> &Self::Target {
+        &self.watcher
+    }
+}
+
+impl<T> Clone for SseSubscribe<T> {
+    fn clone(&self) -> Self {
+        Self {
+            url: self.url.clone(),
+            watcher: self.watcher.clone(),
+            subscription: self.subscription.clone(),
+        }
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for SseSubscribe<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SseSubscribe")
+            .field("url", &self.url)
+            .field("document", &*self.watcher.get())
+            .finish()
+    }
+}
+
+impl<T> clap::builder::ValueParserFactory for SseSubscribe<T>
+where
+    T: FromReader + Send + Sync + 'static,
+{
+    type Parser = AdapterParser<Self>;
+    fn value_parser() -> Self::Parser {
+        AdapterParser::with_value_hint(clap::ValueHint::Url)
+    }
+}
+
+impl<T> std::str::FromStr for SseSubscribe<T>
+where
+    T: FromReader + Send + Sync + 'static,
+{
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::subscribe(s)
+    }
+}
+
+/// Parse the data of an event as a document
+fn parse_event<T: FromReader>(url: &str, data: &str) -> Result<T, Error> {
+    T::from_reader(&mut data.as_bytes()).map_err(|error| Error::parse(PathBuf::from(url), error))
+}
+
+/// Receives events in the background, reconnecting whenever the connection drops
+struct EventStream {
+    url: String,
+    endpoint: Endpoint,
+    connection: Arc<Connection>,
+    last_event_id: Option<String>,
+    retry: Duration,
+}
+
+impl EventStream {
+    /// Pass each parsed event to `publish` until it returns `false` or the subscription is closed
+    fn run<T, F>(&mut self, mut events: EventReader, mut publish: F)
+    where
+        T: FromReader,
+        F: FnMut(Result<T, Error>) -> bool,
+    {
+        loop {
+            let error = loop {
+                let next = events.next_event();
+                if let Some(last_event_id) = events.last_event_id.take() {
+                    self.last_event_id = Some(last_event_id);
+                }
+                if let Some(retry) = events.retry.take() {
+                    self.retry = retry;
+                }
+
+                match next {
+                    Ok(Some(data)) => {
+                        if !publish(parse_event(&self.url, &data)) {
+                            return;
+                        }
+                    }
+                    Ok(None) => {
+                        break std::io::Error::new(
+                            std::io::ErrorKind::UnexpectedEof,
+                            "stream ended",
+                        )
+                    }
+                    Err(error) => break error,
+                }
+            };
+            if self.connection.is_closed() {
+                return;
+            }
+
+            let mut error = subscribe_error(&self.url, error);
+            events = loop {
+                if !publish(Err(error)) {
+                    return;
+                }
+                std::thread::sleep(self.retry);
+                if self.connection.is_closed() {
+                    return;
+                }
+
+                match self
+                    .endpoint
+                    .connect(self.last_event_id.as_deref(), &self.connection)
+                {
+                    Ok(events) => break events,
+                    Err(source) => error = subscribe_error(&self.url, source),
+                }
+            };
+        }
+    }
+}
+
+/// The parts of an `http://` URL needed to request it
+struct Endpoint {
+    authority: String,
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl Endpoint {
+    fn parse(url: &str) -> Result<Self, Error> {
+        let invalid = |message: &str| {
+            subscribe_error(
+                url,
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, message),
+            )
+        };
+
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| invalid("only http:// URLs are supported"))?;
+        let (authority, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => {
+                let port = port.parse().map_err(|_| invalid("invalid port"))?;
+                (host, port)
+            }
+            _ => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(invalid("missing host"));
+        }
+
+        Ok(Endpoint {
+            authority: authority.to_string(),
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+
+    /// Request the event stream, returning a reader of its events
+    fn connect(
+        &self,
+        last_event_id: Option<&str>,
+        connection: &Connection,
+    ) -> std::io::Result<EventReader> {
+        let mut socket = connection.open(&self.host, self.port)?;
+
+        let mut request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nAccept: text/event-stream\r\nCache-Control: no-cache\r\n",
+            self.path, self.authority,
+        );
+        if let Some(last_event_id) = last_event_id {
+            request.push_str(&format!("Last-Event-ID: {last_event_id}\r\n"));
+        }
+        request.push_str("\r\n");
+        socket.write_all(request.as_bytes())?;
+
+        let mut reader = BufReader::new(socket);
+        let mut status = String::new();
+        read_line(&mut reader, &mut status, MAX_HEADER_LINE)?;
+        let status_code = status.split_whitespace().nth(1);
+        if status_code != Some("200") {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("unexpected response '{}'", status.trim()),
+            ));
+        }
+
+        let mut chunked = false;
+        loop {
+            let mut header = String::new();
+            if read_line(&mut reader, &mut header, MAX_HEADER_LINE)? == 0 {
+                return Err(std::io::ErrorKind::UnexpectedEof.into());
+            }
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("transfer-encoding")
+                    && value.to_ascii_lowercase().contains("chunked")
+                {
+                    chunked = true;
+                }
+            }
+        }
+
+        let body: Box<dyn BufRead + Send> = if chunked {
+            Box::new(BufReader::new(ChunkedReader {
+                inner: reader,
+                remaining: 0,
+                done: false,
+            }))
+        } else {
+            Box::new(reader)
+        };
+        Ok(EventReader {
+            body,
+            last_event_id: None,
+            retry: None,
+        })
+    }
+}
+
+/// Decodes a response body sent with `Transfer-Encoding: chunked`
+struct ChunkedReader<R> {
+    inner: R,
+    remaining: usize,
+    done: bool,
+}
+
+impl<R: BufRead> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.remaining == 0 {
+            let mut line = String::new();
+            if read_line(&mut self.inner, &mut line, MAX_HEADER_LINE)? == 0 {
+                return Err(std::io::ErrorKind::UnexpectedEof.into());
+            }
+            let size = line.trim().split(';').next().unwrap_or_default();
+            let size = usize::from_str_radix(size, 16).map_err(|_| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid chunk size")
+            })?;
+            if size == 0 {
+                self.done = true;
+                return Ok(0);
+            }
+            self.remaining = size;
+        }
+
+        let limit = buf.len().min(self.remaining);
+        let read = self.inner.read(&mut buf[..limit])?;
+        if read == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        self.remaining -= read;
+        if self.remaining == 0 {
+            // Each chunk is followed by a line break
+            read_line(&mut self.inner, &mut String::new(), MAX_HEADER_LINE)?;
+        }
+        Ok(read)
+    }
+}
+
+/// Read a line into `line` like [`BufRead::read_line`], failing if it exceeds `max` bytes
+fn read_line(reader: &mut impl BufRead, line: &mut String, max: u64) -> std::io::Result<usize> {
+    let read = reader.take(max).read_line(line)?;
+    if read as u64 == max && !line.ends_with('\n') {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("line longer than {max} bytes"),
+        ));
+    }
+    Ok(read)
+}
+
+/// Parses events from the body of an event stream
+struct EventReader {
+    body: Box<dyn BufRead + Send>,
+
+    /// The ID of the last dispatched event, if the server set a new one
+    last_event_id: Option<String>,
+
+    /// The reconnection delay, if the server set a new one
+    retry: Option<Duration>,
+}
+
+impl EventReader {
+    /// Read the data of the next event that has any, or `None` once the stream ends
+    ///
+    /// `id` and `retry` fields are recorded even for events without data.
+    fn next_event(&mut self) -> std::io::Result<Option<String>> {
+        let mut data = String::new();
+        let mut has_data = false;
+        let mut id = None;
+        loop {
+            let mut line = String::new();
+            let remaining = MAX_EVENT_SIZE.saturating_sub(data.len() as u64).max(1);
+            if read_line(&mut self.body, &mut line, remaining)? == 0 {
+                return Ok(None);
+            }
+            let line = line.trim_end_matches(['\r', '\n']);
+
+            if line.is_empty() {
+                if id.is_some() {
+                    self.last_event_id = id.take();
+                }
+                if has_data {
+                    return Ok(Some(data));
+                }
+                continue;
+            }
+            if line.starts_with(':') {
+                continue;
+            }
+
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "data" => {
+                    if has_data {
+                        data.push('\n');
+                    }
+                    data.push_str(value);
+                    has_data = true;
+                }
+                "id" if !value.contains('\0') => id = Some(value.to_string()),
+                "retry" => {
+                    if let Ok(millis) = value.parse() {
+                        self.retry = Some(Duration::from_millis(millis));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! Provides fixtures for testing code that consumes adapters
+//!
+//! The helpers in this module build adapters around in-memory values, so that
+//! downstream crates can exercise their hot-reload handling without touching
+//! the real filesystem. Enable the `test-util` feature to use them, typically
+//! from `[dev-dependencies]`.
+
+use std::sync::Arc;
+
+use crate::fs::PathTo;
+use crate::handle::{ReloadError, ReloadHandle};
+use crate::reloading::Reloading;
+use crate::watcher::ConfigWatcher;
+use crate::Error;
+
+/// The path reported by fake adapters, which never exists on disk
+pub const FAKE_PATH: &str = "<fake>";
+
+/// Wrap `data` in a [`PathTo`] as if it were loaded from a file
+///
+/// # Example
+///
+/// ```
+/// use clap_adapters::prelude::*;
+/// use clap_adapters::test_util::{fake_path_to, FAKE_PATH};
+///
+/// let config = fake_path_to(JsonOf(serde_json::json!({"hello":"world"})));
+/// assert_eq!(config.path(), std::path::Path::new(FAKE_PATH));
+/// assert_eq!(config.data.0, serde_json::json!({"hello":"world"}));
+/// ```
+pub fn fake_path_to<T>(data: T) -> PathTo<T> {
+    PathTo::from_parts(FAKE_PATH, data)
+}
+
+/// Create a [`Reloading`] adapter serving `data`, and a handle for updating it
+///
+/// Updates made through the [`UpdateHandle`] are validated and published
+/// exactly like documents reloaded after a filesystem event, so subscribers,
+/// validators, error hooks and stats all behave as they do in production.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap_adapters::prelude::*;
+/// use clap_adapters::test_util::fake_reloading;
+///
+/// let (config, updates) = fake_reloading(JsonOf(serde_json::json!({"hello":"world"})));
+/// let mut config_rx = config.receiver();
+///
+/// updates.update(JsonOf(serde_json::json!({"hello":"test"})))?;
+/// assert!(config_rx.has_changed()?);
+/// assert_eq!(config.get().data.0, serde_json::json!({"hello":"test"}));
+///
+/// // Simulate a reload that fails, e.g. because the file became malformed
+/// let error = updates.fail("malformed document");
+/// assert_eq!(error.error.to_string(), "malformed document");
+/// assert_eq!(config.get().data.0, serde_json::json!({"hello":"test"}));
+/// assert_eq!(config.stats().failures, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub fn fake_reloading<T>(data: T) -> (Reloading<PathTo<T>>, UpdateHandle<T>)
+where
+    T: Send + Sync + 'static,
+{
+    let (watcher, set_next) =
+        ConfigWatcher::from_pending(fake_path_to(data), FAKE_PATH.to_string());
+    let reloading = Reloading::from_watcher(watcher);
+
+    let update_handle = UpdateHandle {
+        set_next: Arc::new(set_next),
+        reload_handle: reloading.reload_handle(),
+    };
+    (reloading, update_handle)
+}
+
+/// Sets the result of a fake adapter's next reload
+type SetNext<T> = Arc<dyn Fn(Result<PathTo<T>, Error>) + Send + Sync>;
+
+/// A handle for publishing updates to an adapter created by [`fake_reloading`]
+pub struct UpdateHandle<T> {
+    set_next: SetNext<T>,
+    reload_handle: ReloadHandle,
+}
+
+impl<T> UpdateHandle<T> {
+    /// Publish `data` as if the file had been changed and reloaded
+    ///
+    /// Returns an error if the adapter's validators rejected the update.
+    pub fn update(&self, data: T) -> Result<(), ReloadError> {
+        self.reload(Ok(fake_path_to(data)))
+    }
+
+    /// Fail a reload as if the file could not be loaded, returning the reported error
+    pub fn fail(&self, error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> ReloadError {
+        self.reload(Err(Error::Custom(error.into())))
+            .expect_err("a failed load is always reported")
+    }
+
+    /// Reload the adapter with `result`, reporting failures like a background reload
+    fn reload(&self, result: Result<PathTo<T>, Error>) -> Result<(), ReloadError> {
+        (self.set_next)(result);
+        let result = self.reload_handle.reload_now();
+        if let Err(error) = &result {
+            self.reload_handle
+                .report(error, "Failed to hotreload after notify");
+        }
+        result
+    }
+}
+
+impl<T> Clone for UpdateHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            set_next: self.set_next.clone(),
+            reload_handle: self.reload_handle.clone(),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for UpdateHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UpdateHandle").finish_non_exhaustive()
+    }
+}
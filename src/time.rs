@@ -0,0 +1,75 @@
+//! Type-level durations used to configure the timing of adapters
+
+use std::time::{Duration, SystemTime};
+
+use crate::Error;
+
+/// Trait for type markers to const-evaluate to a Duration
+pub trait Time {
+    /// The duration represented by the marker type
+    const PERIOD: Duration;
+}
+
+/// A duration of `N` milliseconds
+#[derive(Debug, Clone, Copy)]
+pub enum Millis<const N: u64> {}
+impl<const N: u64> Time for Millis<N> {
+    const PERIOD: Duration = Duration::from_millis(N);
+}
+
+/// A duration of `N` seconds
+#[derive(Debug, Clone, Copy)]
+pub enum Seconds<const N: u64> {}
+impl<const N: u64> Time for Seconds<N> {
+    const PERIOD: Duration = Duration::from_secs(N);
+}
+
+/// A duration of `N` minutes
+#[derive(Debug, Clone, Copy)]
+pub enum Minutes<const N: u64> {}
+impl<const N: u64> Time for Minutes<N> {
+    const PERIOD: Duration = Duration::from_secs(60 * N);
+}
+
+/// A duration of `N` hours
+#[derive(Debug, Clone, Copy)]
+pub enum Hours<const N: u64> {}
+impl<const N: u64> Time for Hours<N> {
+    const PERIOD: Duration = Duration::from_secs(60 * 60 * N);
+}
+
+/// Trait for type markers that decide when `Periodic` reloads next
+///
+/// Every [`Time`] marker is a schedule that waits its duration between
+/// reloads. With the `cron` feature, `Cron<C>` reloads at the times matched
+/// by a cron expression instead.
+pub trait Schedule {
+    /// Returns how long to wait after `now` until the next reload
+    ///
+    /// Fails if the schedule is invalid or has no further reload time.
+    fn delay_after(now: SystemTime) -> Result<Duration, Error>;
+}
+
+impl<T: Time> Schedule for T {
+    fn delay_after(_now: SystemTime) -> Result<Duration, Error> {
+        Ok(T::PERIOD)
+    }
+}
+
+/// Returns a pseudo-random duration between zero and `max` (inclusive)
+#[cfg(feature = "periodic")]
+pub(crate) fn random_up_to(max: Duration) -> Duration {
+    let max_nanos = max.as_nanos();
+    if max_nanos == 0 {
+        return Duration::ZERO;
+    }
+
+    use std::hash::{BuildHasher, Hasher};
+
+    // Each `RandomState` is randomly seeded, which is plenty for spreading out timers
+    let random = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish();
+    let nanos = u128::from(random) % (max_nanos + 1);
+    Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+}
@@ -1,10 +1,59 @@
-use serde::de::DeserializeOwned;
+use serde::{de::DeserializeOwned, Serialize};
 
-use crate::traits::FromReader;
+use crate::traits::{FromMerged, FromReader, ToWriter};
 
 /// An adapter for deserializing a Toml document from a buffered reader
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
 pub struct TomlOf<T>(pub T);
+
+impl<T> std::ops::Deref for TomlOf<T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> std::ops::DerefMut for TomlOf<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T> AsRef<T> for TomlOf<T> {
+    fn as_ref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> TomlOf<T> {
+    /// Returns a reference to the inner TOML datatype
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use clap_adapters::prelude::*;
+    ///
+    /// let mut document = TomlOf(vec![1, 2]);
+    /// document.data_mut().push(3);
+    /// assert_eq!(document.data(), &[1, 2, 3]);
+    /// assert_eq!(document.into_inner(), vec![1, 2, 3]);
+    /// ```
+    pub fn data(&self) -> &T {
+        &self.0
+    }
+
+    /// Returns a mutable reference to the inner TOML datatype
+    pub fn data_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+
+    /// Returns the inner TOML datatype
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
 impl<T: DeserializeOwned> FromReader for TomlOf<T> {
     type Error = std::io::Error;
     fn from_reader(reader: &mut impl std::io::BufRead) -> Result<Self, Self::Error> {
@@ -15,6 +64,32 @@ impl<T: DeserializeOwned> FromReader for TomlOf<T> {
     }
 }
 
+impl<T: Serialize> ToWriter for TomlOf<T> {
+    type Error = std::io::Error;
+    fn to_writer(&self, writer: &mut impl std::io::Write) -> Result<(), Self::Error> {
+        let string = toml::to_string_pretty(&self.0)
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))?;
+        writer.write_all(string.as_bytes())
+    }
+}
+
+impl<T: DeserializeOwned> FromMerged for TomlOf<T> {
+    const EXTENSIONS: &'static [&'static str] = &["toml"];
+    type Error = std::io::Error;
+    fn value_from_reader(
+        reader: &mut impl std::io::BufRead,
+    ) -> Result<serde_json::Value, Self::Error> {
+        let string = String::from_reader(reader)?;
+        toml::from_str(&string)
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))
+    }
+    fn from_merged(value: serde_json::Value) -> Result<Self, Self::Error> {
+        let toml = serde_json::from_value::<T>(value)
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))?;
+        Ok(TomlOf(toml))
+    }
+}
+
 impl<T> crate::fs::PathTo<TomlOf<T>> {
     /// Returns a reference to the inner Toml datatype
     ///
@@ -53,6 +128,38 @@ impl<T> crate::fs::PathTo<TomlOf<T>> {
         &self.data.0
     }
 
+    /// Returns a mutable reference to the inner TOML datatype
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap::Parser;
+    /// use clap_adapters::prelude::*;
+    ///
+    /// #[derive(Debug, Parser)]
+    /// struct Cli {
+    ///     #[clap(long)]
+    ///     config: PathTo<TomlOf<serde_json::Value>>,
+    /// }
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.toml");
+    /// let config_path_string = config_path.display().to_string();
+    /// std::fs::write(&config_path, r#"hello = "world""#)?;
+    ///
+    /// // Parse our CLI, then override a value
+    /// let mut cli = Cli::parse_from(["app", "--config", &config_path_string]);
+    /// cli.config.data_mut()["hello"] = serde_json::json!("there");
+    /// assert_eq!(cli.config.data(), &serde_json::json!({"hello":"there"}));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn data_mut(&mut self) -> &mut T {
+        &mut self.data.0
+    }
+
     /// Returns the owned inner datatype parsed from Toml
     ///
     /// # Example
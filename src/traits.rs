@@ -1,3 +1,7 @@
+use std::path::Path;
+
+use crate::{AdapterOptions, Error};
+
 /// Any type that can construct itself from a buffered reader
 pub trait FromReader: Sized {
     /// The kind of error that may occur during construction
@@ -7,6 +11,168 @@ pub trait FromReader: Sized {
     fn from_reader(reader: &mut impl std::io::BufRead) -> Result<Self, Self::Error>;
 }
 
+/// Report `error` as invalid data, unless it already is an IO error
+///
+/// Used by wrapping adapters, whose `FromReader::Error` is an IO error, to
+/// pass on the errors of the adapter they wrap.
+pub(crate) fn invalid_data(
+    error: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+) -> std::io::Error {
+    match error.into().downcast::<std::io::Error>() {
+        Ok(error) => *error,
+        Err(error) => std::io::Error::new(std::io::ErrorKind::InvalidData, error),
+    }
+}
+
+/// Any adapter that can load a fresh copy of itself from the same source
+///
+/// This is what allows reloading adapters such as `Periodic<T>` to work with
+/// any source of documents, rather than only with `PathTo<T>`.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// /// An adapter that reads the value of an environment variable
+/// #[derive(Debug, Clone)]
+/// struct EnvVar {
+///     name: String,
+///     value: String,
+/// }
+///
+/// impl std::str::FromStr for EnvVar {
+///     type Err = clap_adapters::Error;
+///     fn from_str(name: &str) -> Result<Self, Self::Err> {
+///         let value = std::env::var(name).map_err(|error| clap_adapters::Error::Custom(error.into()))?;
+///         Ok(EnvVar { name: name.to_string(), value })
+///     }
+/// }
+///
+/// impl Reload for EnvVar {
+///     fn location(&self) -> String {
+///         format!("${}", self.name)
+///     }
+///
+///     fn reload(&self) -> Result<Self, clap_adapters::Error> {
+///         self.name.parse()
+///     }
+///
+///     fn reload_if_changed(&self) -> Result<Option<Self>, clap_adapters::Error> {
+///         let reloaded = self.reload()?;
+///         Ok((reloaded.value != self.value).then_some(reloaded))
+///     }
+/// }
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Name of an environment variable to re-read every minute
+///     #[clap(long)]
+///     greeting: Periodic<EnvVar>,
+/// }
+///
+/// std::env::set_var("MY_APP_GREETING", "hello");
+///
+/// let runtime = tokio::runtime::Runtime::new()?;
+/// runtime.block_on(async {
+///     let cli = Cli::parse_from(["app", "--greeting", "MY_APP_GREETING"]);
+///
+///     let mut greeting_rx = cli.greeting.receiver();
+///     greeting_rx.mark_unchanged();
+///
+///     // Reloading an unchanged variable publishes nothing
+///     cli.greeting.reload_now()?;
+///     assert!(!greeting_rx.has_changed()?);
+///
+///     std::env::set_var("MY_APP_GREETING", "howdy");
+///     cli.greeting.reload_now()?;
+///     assert!(greeting_rx.has_changed()?);
+///     assert_eq!(cli.greeting.get().value, "howdy");
+///     anyhow::Ok(())
+/// })?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait Reload: Sized {
+    /// A human-readable description of the source, such as a file path
+    fn location(&self) -> String;
+
+    /// How the adapter loads a fresh copy of itself from its source
+    fn reload(&self) -> Result<Self, Error>;
+
+    /// Load a fresh copy only if the source changed since `self` was loaded
+    ///
+    /// Returning `Ok(None)` means the source is unchanged, in which case
+    /// reloading adapters don't publish an update. Sources that can detect
+    /// changes cheaply (e.g. a conditional HTTP request using an `ETag`)
+    /// should override this. By default, the source is always reloaded.
+    fn reload_if_changed(&self) -> Result<Option<Self>, Error> {
+        self.reload().map(Some)
+    }
+
+    /// A hash of the raw content the document was loaded from, if it was hashed
+    ///
+    /// The hash is taken from the same bytes that were parsed. Reloading
+    /// adapters don't publish a reloaded document whose hash equals that of
+    /// the current one, starting with the document loaded while parsing, so
+    /// that rewriting identical bytes publishes nothing. By default, no hash
+    /// is taken and every reload is published.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap::Parser;
+    /// use clap_adapters::prelude::*;
+    ///
+    /// #[derive(Debug, Parser)]
+    /// struct Cli {
+    ///     /// Path to a Json config to re-read every minute
+    ///     #[clap(long)]
+    ///     config: Periodic<PathTo<JsonOf<serde_json::Value>>>,
+    /// }
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// let config_path_string = config_path.display().to_string();
+    /// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+    ///
+    /// let runtime = tokio::runtime::Runtime::new()?;
+    /// runtime.block_on(async {
+    ///     let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+    ///
+    ///     let mut config_rx = cli.config.receiver();
+    ///     config_rx.mark_unchanged();
+    ///
+    ///     // Rewriting identical bytes publishes nothing
+    ///     std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+    ///     cli.config.reload_now()?;
+    ///     assert!(!config_rx.has_changed()?);
+    ///
+    ///     std::fs::write(&config_path, r#"{"hello":"there"}"#)?;
+    ///     cli.config.reload_now()?;
+    ///     assert!(config_rx.has_changed()?);
+    ///     anyhow::Ok(())
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn source_hash(&self) -> Option<u64> {
+        None
+    }
+
+    /// How shells should complete the argument the adapter is parsed from
+    ///
+    /// Reloading adapters such as `Periodic<T>` take the hint of `T`, see
+    /// [`with_value_hints`](crate::with_value_hints). By default, no hint is given.
+    fn value_hint() -> clap::ValueHint {
+        clap::ValueHint::Unknown
+    }
+}
+
 impl FromReader for Vec<u8> {
     type Error = std::io::Error;
     fn from_reader(reader: &mut impl std::io::BufRead) -> Result<Self, Self::Error> {
@@ -24,3 +190,439 @@ impl FromReader for String {
         Ok(string)
     }
 }
+
+/// Loads an arbitrary Json document, without wrapping it in [`JsonOf`]
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a config file of arbitrary Json
+///     #[clap(long)]
+///     config: PathTo<serde_json::Value>,
+///
+///     /// Path to a file containing the port to listen on
+///     #[clap(long)]
+///     port: PathTo<u16>,
+/// }
+///
+/// // Create a config file and a port file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json");
+/// let port_path = config_dir.path().join("port");
+/// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+/// std::fs::write(&port_path, "8080\n")?;
+///
+/// let cli = Cli::parse_from([
+///     "app",
+///     "--config",
+///     &config_path.display().to_string(),
+///     "--port",
+///     &port_path.display().to_string(),
+/// ]);
+/// assert_eq!(cli.config.data, serde_json::json!({"hello":"world"}));
+/// assert_eq!(cli.port.data, 8080);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`JsonOf`]: crate::JsonOf
+impl FromReader for serde_json::Value {
+    type Error = serde_json::Error;
+    fn from_reader(reader: &mut impl std::io::BufRead) -> Result<Self, Self::Error> {
+        serde_json::from_reader(reader)
+    }
+}
+
+/// Implements [`FromReader`] for types parsed from a file's contents via [`FromStr`]
+///
+/// Surrounding whitespace, such as a trailing newline, is ignored.
+///
+/// [`FromStr`]: std::str::FromStr
+macro_rules! from_trimmed_str {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            /// Parses the file's contents, ignoring surrounding whitespace
+            impl FromReader for $ty {
+                type Error = std::io::Error;
+                fn from_reader(reader: &mut impl std::io::BufRead) -> Result<Self, Self::Error> {
+                    let string = String::from_reader(reader)?;
+                    string
+                        .trim()
+                        .parse()
+                        .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))
+                }
+            }
+        )*
+    };
+}
+
+from_trimmed_str!(
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+    bool,
+    std::net::IpAddr,
+    std::net::Ipv4Addr,
+    std::net::Ipv6Addr,
+    std::net::SocketAddr,
+);
+
+/// Loads binary payloads that can be shared cheaply, e.g. across tasks
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a binary payload
+///     #[clap(long)]
+///     payload: PathTo<bytes::Bytes>,
+/// }
+///
+/// // Create a payload file in a temporary directory
+/// let payload_dir = tempfile::tempdir()?;
+/// let payload_path = payload_dir.path().join("payload.bin");
+/// let payload_path_string = payload_path.display().to_string();
+/// std::fs::write(&payload_path, [0xde, 0xad, 0xbe, 0xef])?;
+///
+/// let cli = Cli::parse_from(["app", "--payload", &payload_path_string]);
+/// let shared = cli.payload.data.clone(); // Shares the buffer instead of copying it
+/// assert_eq!(&shared[..], &[0xde, 0xad, 0xbe, 0xef]);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "bytes")]
+impl FromReader for bytes::Bytes {
+    type Error = std::io::Error;
+    fn from_reader(reader: &mut impl std::io::BufRead) -> Result<Self, Self::Error> {
+        Vec::<u8>::from_reader(reader).map(bytes::Bytes::from)
+    }
+}
+
+/// Any type that can write itself to a writer, mirroring [`FromReader`]
+pub trait ToWriter {
+    /// The kind of error that may occur during writing
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// How the type writes itself to a writer
+    fn to_writer(&self, writer: &mut impl std::io::Write) -> Result<(), Self::Error>;
+}
+
+impl ToWriter for Vec<u8> {
+    type Error = std::io::Error;
+    fn to_writer(&self, writer: &mut impl std::io::Write) -> Result<(), Self::Error> {
+        writer.write_all(self)
+    }
+}
+
+impl ToWriter for String {
+    type Error = std::io::Error;
+    fn to_writer(&self, writer: &mut impl std::io::Write) -> Result<(), Self::Error> {
+        writer.write_all(self.as_bytes())
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl ToWriter for bytes::Bytes {
+    type Error = std::io::Error;
+    fn to_writer(&self, writer: &mut impl std::io::Write) -> Result<(), Self::Error> {
+        writer.write_all(self)
+    }
+}
+
+/// Any type that can construct itself from the file at a path
+///
+/// Unlike [`FromReader`], implementors see the path itself, which allows
+/// e.g. choosing a format by file extension or resolving includes relative
+/// to the file. Every [`FromReader`] type implements this trait by reading
+/// the file's contents, so `PathTo<T>` accepts both kinds of types.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use std::path::Path;
+///
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// /// A Json or Toml document, depending on the file extension
+/// #[derive(Debug, Clone)]
+/// struct AnyConfig(serde_json::Value);
+///
+/// impl FromPath for AnyConfig {
+///     fn from_path(path: &Path) -> Result<Self, clap_adapters::Error> {
+///         let value = match path.extension().and_then(|extension| extension.to_str()) {
+///             Some("toml") => TomlOf::<serde_json::Value>::from_path(path)?.0,
+///             _ => JsonOf::<serde_json::Value>::from_path(path)?.0,
+///         };
+///         Ok(AnyConfig(value))
+///     }
+/// }
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     /// Path to a Json or Toml config
+///     #[clap(long)]
+///     config: PathTo<AnyConfig>,
+/// }
+///
+/// // Create a Toml config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.toml");
+/// let config_path_string = config_path.display().to_string();
+/// std::fs::write(&config_path, r#"hello = "toml""#)?;
+///
+/// let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+/// assert_eq!(cli.config.data.0, serde_json::json!({"hello":"toml"}));
+/// # Ok(())
+/// # }
+/// ```
+pub trait FromPath: Sized {
+    /// How the type constructs itself from the file at `path`
+    fn from_path(path: &Path) -> Result<Self, Error>;
+
+    /// Like [`FromPath::from_path`], taking any options the type understands
+    ///
+    /// Options are given to `PathTo<T>` under [`WithOptions`]. By default, no
+    /// options are taken.
+    ///
+    /// [`WithOptions`]: crate::WithOptions
+    fn from_path_with_options(path: &Path, options: &mut AdapterOptions) -> Result<Self, Error> {
+        let _ = options;
+        Self::from_path(path)
+    }
+
+    /// Returns true if [`FromPath::from_path_with_options`] takes the option `key`
+    ///
+    /// By default, no options are taken.
+    fn takes_option(key: &str) -> bool {
+        let _ = key;
+        false
+    }
+
+    /// Like [`FromPath::from_path_with_options`], also hashing the bytes that were parsed
+    ///
+    /// `PathTo<T>` keeps the hash as its [`Reload::source_hash`], so that
+    /// reloading a file rewritten with identical bytes publishes nothing. By
+    /// default, no hash is returned and every reload is published.
+    fn from_path_hashed(
+        path: &Path,
+        options: &mut AdapterOptions,
+    ) -> Result<(Self, Option<u64>), Error> {
+        Ok((Self::from_path_with_options(path, options)?, None))
+    }
+}
+
+impl<T: FromReader> FromPath for T {
+    fn from_path(path: &Path) -> Result<Self, Error> {
+        let file = std::fs::File::open(path).map_err(|error| Error::io(path, error))?;
+        let mut reader = std::io::BufReader::new(file);
+        T::from_reader(&mut reader).map_err(|error| Error::parse(path.to_path_buf(), error))
+    }
+
+    fn from_path_hashed(
+        path: &Path,
+        _options: &mut AdapterOptions,
+    ) -> Result<(Self, Option<u64>), Error> {
+        let file = std::fs::File::open(path).map_err(|error| Error::io(path, error))?;
+        let mut reader = HashingReader::new(std::io::BufReader::new(file));
+        let data =
+            T::from_reader(&mut reader).map_err(|error| Error::parse(path.to_path_buf(), error))?;
+        let hash = reader.finish().map_err(|error| Error::io(path, error))?;
+        Ok((data, Some(hash)))
+    }
+}
+
+/// A reader hashing the bytes read through it, so a document is hashed while it is parsed
+pub(crate) struct HashingReader<R> {
+    inner: R,
+    hasher: std::collections::hash_map::DefaultHasher,
+}
+
+impl<R: std::io::BufRead> HashingReader<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        HashingReader {
+            inner,
+            hasher: Default::default(),
+        }
+    }
+
+    /// Hash any bytes the parser left unread, and return the hash of the whole input
+    pub(crate) fn finish(mut self) -> std::io::Result<u64> {
+        use std::hash::Hasher;
+
+        std::io::copy(&mut self, &mut std::io::sink())?;
+        Ok(self.hasher.finish())
+    }
+}
+
+impl<R: std::io::BufRead> std::io::Read for HashingReader<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        use std::hash::Hasher;
+
+        let len = self.inner.read(buffer)?;
+        self.hasher.write(&buffer[..len]);
+        Ok(len)
+    }
+}
+
+impl<R: std::io::BufRead> std::io::BufRead for HashingReader<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        use std::hash::Hasher;
+
+        // The bytes being consumed are still buffered, so this does no I/O
+        if let Ok(buffer) = self.inner.fill_buf() {
+            self.hasher.write(&buffer[..amount.min(buffer.len())]);
+        }
+        self.inner.consume(amount);
+    }
+}
+
+/// Any type that can construct itself from an asynchronous buffered reader
+///
+/// This is the async counterpart of [`FromReader`], for loading documents
+/// from tokio-native or network-backed sources without blocking the runtime.
+/// Every [`FromReader`] type implements this trait by reading the whole
+/// input before parsing it.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap_adapters::prelude::*;
+///
+/// let runtime = tokio::runtime::Runtime::new()?;
+/// runtime.block_on(async {
+///     let mut reader = tokio::io::BufReader::new(&br#"{"hello":"async"}"#[..]);
+///     let config = JsonOf::<serde_json::Value>::from_async_reader(&mut reader).await?;
+///     assert_eq!(config.0, serde_json::json!({"hello":"async"}));
+///     anyhow::Ok(())
+/// })?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(any(doc, feature = "async"))]
+pub trait AsyncFromReader: Sized {
+    /// The kind of error that may occur during construction
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// How the type constructs itself from an asynchronous buffered reader
+    fn from_async_reader(
+        reader: &mut (impl tokio::io::AsyncBufRead + Unpin + Send),
+    ) -> impl std::future::Future<Output = Result<Self, Self::Error>> + Send;
+}
+
+#[cfg(any(doc, feature = "async"))]
+impl<T: FromReader> AsyncFromReader for T {
+    type Error = std::io::Error;
+    async fn from_async_reader(
+        reader: &mut (impl tokio::io::AsyncBufRead + Unpin + Send),
+    ) -> Result<Self, Self::Error> {
+        use tokio::io::AsyncReadExt;
+
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer).await?;
+        T::from_reader(&mut buffer.as_slice())
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))
+    }
+}
+
+/// Any adapter that can construct itself from an argument with options
+///
+/// This is how adapters under [`WithOptions`] take the `key=value` options
+/// appended to an argument. Implementations remove the options they
+/// understand from `options` and hand the rest down to the adapter they wrap.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap::Parser;
+/// use clap_adapters::prelude::*;
+///
+/// /// A greeting, optionally in upper case
+/// #[derive(Debug, Clone)]
+/// struct Greeting(String);
+///
+/// impl FromOptions for Greeting {
+///     fn from_options(input: &str, options: &mut AdapterOptions) -> Result<Self, clap_adapters::Error> {
+///         match options.take("case").as_deref() {
+///             Some("upper") => Ok(Greeting(input.to_uppercase())),
+///             _ => Ok(Greeting(input.to_string())),
+///         }
+///     }
+///
+///     fn takes_option(key: &str) -> bool {
+///         key == "case"
+///     }
+/// }
+///
+/// #[derive(Debug, Parser)]
+/// struct Cli {
+///     #[clap(long)]
+///     greeting: WithOptions<Greeting>,
+/// }
+///
+/// let cli = Cli::parse_from(["app", "--greeting", "hello?case=upper"]);
+/// assert_eq!(cli.greeting.0 .0, "HELLO");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`WithOptions`]: crate::WithOptions
+pub trait FromOptions: Sized {
+    /// How the adapter constructs itself from `input`, taking from `options`
+    fn from_options(input: &str, options: &mut AdapterOptions) -> Result<Self, Error>;
+
+    /// Returns true if the adapter, or any adapter it wraps, takes the option `key`
+    ///
+    /// An argument whose query has any other option is handed to the adapter
+    /// whole, as its `?` is then taken to be part of a path.
+    fn takes_option(key: &str) -> bool;
+}
+
+/// Any document format whose files can be deep-merged into a single value
+///
+/// Documents are first parsed into an intermediate [`serde_json::Value`] tree,
+/// merged, and then converted into the final type.
+pub trait FromMerged: Sized {
+    /// File extensions (without the leading `.`) recognized as this format
+    const EXTENSIONS: &'static [&'static str];
+
+    /// The kind of error that may occur during parsing or conversion
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// How a single document is parsed into a mergeable value tree
+    fn value_from_reader(
+        reader: &mut impl std::io::BufRead,
+    ) -> Result<serde_json::Value, Self::Error>;
+
+    /// How the type constructs itself from the merged value tree
+    fn from_merged(value: serde_json::Value) -> Result<Self, Self::Error>;
+}
@@ -0,0 +1,849 @@
+//! Provides the [`ConfigWatcher`], which keeps a document up to date
+//! independently of argument parsing
+//!
+//! The reloading adapters are built on [`ConfigWatcher`]. Use it directly when
+//! the path to watch comes from somewhere other than the command line, such as
+//! an environment variable or service discovery.
+
+#[cfg(any(doc, feature = "reloading"))]
+use std::path::PathBuf;
+use std::sync::Arc;
+#[cfg(any(feature = "test-util", feature = "sse", feature = "mqtt"))]
+use std::sync::Mutex;
+#[cfg(any(doc, feature = "reloading"))]
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tokio_stream::{Stream, StreamExt};
+
+#[cfg(any(doc, feature = "reloading"))]
+use crate::dir::DirOf;
+use crate::fs::{LoadMetadata, PathTo};
+#[cfg(any(doc, feature = "reloading"))]
+use crate::fs_watch::{changes_contents, FileWatcher};
+#[cfg(any(doc, feature = "reloading"))]
+use crate::handle::RunState;
+use crate::handle::{
+    ChangeSignal, Lifecycle, ReloadError, ReloadHandle, ReloadStats, Snapshot, Validators,
+};
+#[cfg(any(feature = "periodic", feature = "reloading", feature = "signal"))]
+use crate::traits::Reload;
+#[cfg(any(doc, feature = "reloading"))]
+use crate::traits::{FromMerged, FromPath};
+
+/// Keeps the latest version of a document, publishing each reloaded version
+///
+/// - Use [`ConfigWatcher::get`] to get the document at a given moment
+/// - Use [`ConfigWatcher::receiver`] to get a tokio [`watch::Receiver`]
+/// - Use [`ConfigWatcher::stream`] to get a stream of updated documents
+///
+/// [`ConfigWatcher::new`] loads a file and reloads it whenever it changes,
+/// exactly like the [`Reloading`] adapter, which is built on it.
+///
+/// # Example
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use clap_adapters::prelude::*;
+///
+/// // Create a config file in a temporary directory
+/// let config_dir = tempfile::tempdir()?;
+/// let config_path = config_dir.path().join("config.json");
+/// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+///
+/// // Watch a path that didn't come from the command line
+/// std::env::set_var("MY_APP_CONFIG", &config_path);
+/// let config_path = std::env::var("MY_APP_CONFIG")?;
+/// let config = ConfigWatcher::<PathTo<JsonOf<serde_json::Value>>>::new(config_path)?;
+/// assert_eq!(config.get().data.0, serde_json::json!({"hello":"world"}));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`watch::Receiver`]: tokio::sync::watch
+/// [`Reloading`]: crate::Reloading
+#[must_use = "Dropping the `ConfigWatcher` will cancel the file watch"]
+pub struct ConfigWatcher<T> {
+    reload_rx: watch::Receiver<Snapshot<T>>,
+    reload_handle: ReloadHandle,
+    validators: Validators<T>,
+    lifecycle: Lifecycle,
+    #[cfg(any(doc, feature = "reloading"))]
+    file_watcher: Option<Arc<FileWatcher>>,
+}
+
+impl<T> ConfigWatcher<T> {
+    /// Get a shared handle to the current value of the inner document
+    ///
+    /// This is a cheap reference-count bump; the document itself is not cloned.
+    pub fn get(&self) -> Arc<T> {
+        self.reload_rx.borrow().document.clone()
+    }
+
+    /// Get the current document along with its generation and load time
+    pub fn snapshot(&self) -> Snapshot<T> {
+        self.reload_rx.borrow().clone()
+    }
+
+    /// Get a receiver channel that yields [`Snapshot`]s of updated documents after filesystem changes
+    pub fn receiver(&self) -> watch::Receiver<Snapshot<T>> {
+        self.reload_rx.clone()
+    }
+
+    /// Get a receiver channel whose current document is already marked as seen
+    ///
+    /// Unlike [`ConfigWatcher::receiver`], `changed()` only completes for
+    /// documents published after this call.
+    pub fn receiver_changes_only(&self) -> watch::Receiver<Snapshot<T>> {
+        let mut reload_rx = self.reload_rx.clone();
+        reload_rx.mark_unchanged();
+        reload_rx
+    }
+
+    /// Get a signal that fires whenever a new document is published
+    ///
+    /// This suits select-loops that only need to know that the document
+    /// changed, and then read it with [`ConfigWatcher::get`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap_adapters::prelude::*;
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// std::fs::write(&config_path, r#"{"level":"info"}"#)?;
+    ///
+    /// let runtime = tokio::runtime::Runtime::new()?;
+    /// runtime.block_on(async {
+    ///     let config = ConfigWatcher::<PathTo<JsonOf<serde_json::Value>>>::new(&config_path)?;
+    ///     let mut config_changed = config.change_signal();
+    ///
+    ///     std::fs::write(&config_path, r#"{"level":"debug"}"#)?;
+    ///     config.reload_now()?;
+    ///
+    ///     tokio::select! {
+    ///         _ = config_changed.changed() => {
+    ///             assert_eq!(config.get().data.0, serde_json::json!({"level":"debug"}));
+    ///         }
+    ///         _ = tokio::time::sleep(std::time::Duration::from_secs(5)) => {
+    ///             panic!("the change was not signaled");
+    ///         }
+    ///     }
+    ///     anyhow::Ok(())
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn change_signal(&self) -> ChangeSignal<T> {
+        ChangeSignal::new(self.reload_rx.clone())
+    }
+
+    /// Wait until a document newer than the current one is published
+    ///
+    /// Changes published before this call don't complete the future. To not
+    /// miss changes between calls, e.g. in a loop, keep a
+    /// [`ConfigWatcher::change_signal`] instead.
+    pub fn changed(&self) -> impl std::future::Future<Output = ()> + 'static
+    where
+        T: 'static,
+    {
+        let mut change_signal = self.change_signal();
+        async move { change_signal.changed().await }
+    }
+
+    /// Immediately re-read the file and publish it, returning any load error
+    pub fn reload_now(&self) -> Result<(), ReloadError> {
+        self.reload_handle.reload_now()
+    }
+
+    /// Get the error from the most recent reload, if it failed
+    ///
+    /// This is cleared once a subsequent reload succeeds.
+    pub fn last_error(&self) -> Option<ReloadError> {
+        self.reload_handle.error_receiver().borrow().clone()
+    }
+
+    /// Get counters describing the reload activity so far
+    pub fn stats(&self) -> ReloadStats {
+        self.reload_handle.stats()
+    }
+
+    /// Install a closure that receives failures of background reloads
+    ///
+    /// By default, failures of reloads triggered by file changes are logged with
+    /// `tracing`. Installing a hook replaces that logging, e.g. to report
+    /// failures through your own logging or metrics. Failures of explicit
+    /// calls such as [`ConfigWatcher::reload_now`] are returned to the caller instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap_adapters::prelude::*;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+    ///
+    /// let config = ConfigWatcher::<PathTo<JsonOf<serde_json::Value>>>::new(&config_path)?;
+    /// let failures = Arc::new(AtomicUsize::new(0));
+    /// let hook_failures = failures.clone();
+    /// config.on_error(move |_error| {
+    ///     hook_failures.fetch_add(1, Ordering::SeqCst);
+    /// });
+    ///
+    /// // Break the file while paused; resuming reloads it in the background
+    /// config.pause();
+    /// std::fs::write(&config_path, "{not json")?;
+    /// config.resume();
+    /// assert!(failures.load(Ordering::SeqCst) >= 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_error<F>(&self, hook: F)
+    where
+        F: Fn(&ReloadError) + Send + Sync + 'static,
+    {
+        self.reload_handle.set_error_hook(Box::new(hook));
+    }
+
+    /// Register a validation closure that every reloaded document must pass
+    ///
+    /// If a reloaded document fails validation, it is not published: receivers
+    /// keep observing the previous document, and the rejection is reported as
+    /// a [`ReloadError`]. The current document is checked immediately, and any
+    /// validation error is returned, but the validator is registered regardless.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap_adapters::prelude::*;
+    ///
+    /// #[derive(Debug, Clone, serde::Deserialize)]
+    /// struct Config {
+    ///     workers: u32,
+    /// }
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// std::fs::write(&config_path, r#"{"workers": 4}"#)?;
+    ///
+    /// let config = ConfigWatcher::<PathTo<JsonOf<Config>>>::new(&config_path)?;
+    /// config.validate_with(|config| {
+    ///     if config.data.0.workers == 0 {
+    ///         return Err("workers must be positive".into());
+    ///     }
+    ///     Ok(())
+    /// })?;
+    ///
+    /// // An invalid edit is rejected, and the previous config is kept
+    /// std::fs::write(&config_path, r#"{"workers": 0}"#)?;
+    /// assert!(config.reload_now().is_err());
+    /// assert_eq!(config.get().data.0.workers, 4);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn validate_with<F>(&self, validator: F) -> Result<(), crate::Error>
+    where
+        F: Fn(&T) -> Result<(), Box<dyn std::error::Error + Send + Sync>> + Send + Sync + 'static,
+    {
+        let result =
+            validator(&self.reload_rx.borrow()).map_err(|source| crate::Error::Rejected { source });
+        self.validators
+            .write()
+            .expect("validators lock poisoned")
+            .push(Box::new(validator));
+        result
+    }
+
+    /// Get a cloneable handle for triggering reloads from elsewhere
+    pub fn reload_handle(&self) -> ReloadHandle {
+        self.reload_handle.clone()
+    }
+
+    /// Ignore filesystem changes until [`ConfigWatcher::resume`] is called
+    pub fn pause(&self) {
+        self.lifecycle.pause();
+    }
+
+    /// Resume reloading after [`ConfigWatcher::pause`]
+    ///
+    /// The file is reloaded right away to pick up any changes made while paused.
+    pub fn resume(&self) {
+        if !self.lifecycle.resume() {
+            return;
+        }
+
+        if let Err(error) = self.reload_handle.reload_now() {
+            self.reload_handle
+                .report(&error, "Failed to hotreload after resuming");
+        }
+    }
+
+    /// Permanently stop watching the file
+    ///
+    /// Receivers keep observing the last published document.
+    pub fn shutdown(&self) {
+        self.lifecycle.shutdown();
+        #[cfg(any(doc, feature = "reloading"))]
+        if let Some(file_watcher) = &self.file_watcher {
+            file_watcher.stop();
+        }
+    }
+}
+
+impl<T> ConfigWatcher<T>
+where
+    T: Send + Sync + 'static,
+{
+    /// Get a stream of document changes, starting with the current document
+    pub fn stream(&self) -> impl Stream<Item = Arc<T>> {
+        tokio_stream::wrappers::WatchStream::new(self.reload_rx.clone())
+            .map(|snapshot| snapshot.document)
+    }
+
+    /// Get a stream of documents published after this call
+    ///
+    /// Unlike [`ConfigWatcher::stream`], the current document is not yielded,
+    /// to react only to changes after startup.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap_adapters::prelude::*;
+    /// use tokio_stream::StreamExt;
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// std::fs::write(&config_path, r#"{"level":"info"}"#)?;
+    ///
+    /// let runtime = tokio::runtime::Runtime::new()?;
+    /// runtime.block_on(async {
+    ///     let config = ConfigWatcher::<PathTo<JsonOf<serde_json::Value>>>::new(&config_path)?;
+    ///     let mut updates = Box::pin(config.stream_changes_only());
+    ///
+    ///     std::fs::write(&config_path, r#"{"level":"debug"}"#)?;
+    ///     config.reload_now()?;
+    ///
+    ///     // The first item is the update, not the document loaded at startup
+    ///     let update = updates.next().await.expect("an update");
+    ///     assert_eq!(update.data.0, serde_json::json!({"level":"debug"}));
+    ///     anyhow::Ok(())
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stream_changes_only(&self) -> impl Stream<Item = Arc<T>> {
+        tokio_stream::wrappers::WatchStream::from_changes(self.reload_rx.clone())
+            .map(|snapshot| snapshot.document)
+    }
+
+    /// Get a stream of `(previous, current)` document pairs for each change
+    ///
+    /// Only changes occurring after this call are yielded, the first
+    /// `previous` being the document that is current at the time of the call.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap_adapters::prelude::*;
+    /// use tokio_stream::StreamExt;
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// std::fs::write(&config_path, r#"{"level":"info"}"#)?;
+    ///
+    /// let runtime = tokio::runtime::Runtime::new()?;
+    /// runtime.block_on(async {
+    ///     let config = ConfigWatcher::<PathTo<JsonOf<serde_json::Value>>>::new(&config_path)?;
+    ///     let mut changes = Box::pin(config.changes());
+    ///
+    ///     std::fs::write(&config_path, r#"{"level":"debug"}"#)?;
+    ///     config.reload_now()?;
+    ///
+    ///     let (previous, current) = changes.next().await.expect("a change");
+    ///     assert_eq!(previous.data.0, serde_json::json!({"level":"info"}));
+    ///     assert_eq!(current.data.0, serde_json::json!({"level":"debug"}));
+    ///     anyhow::Ok(())
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn changes(&self) -> impl Stream<Item = (Arc<T>, Arc<T>)> {
+        let mut reload_rx = self.reload_rx.clone();
+        let mut previous = reload_rx.borrow_and_update().document.clone();
+        tokio_stream::wrappers::WatchStream::from_changes(reload_rx).map(move |current| {
+            let current = current.document;
+            let previous = std::mem::replace(&mut previous, current.clone());
+            (previous, current)
+        })
+    }
+
+    /// Get a stream of errors from failed reloads
+    ///
+    /// Only failures occurring after this call are yielded.
+    pub fn errors(&self) -> impl Stream<Item = ReloadError> {
+        let error_rx = self.reload_handle.error_receiver();
+        tokio_stream::wrappers::WatchStream::from_changes(error_rx).filter_map(|error| error)
+    }
+
+    /// Wait for the next reload to fail, returning its error
+    ///
+    /// Use this to crash instead of running with a stale document: select on
+    /// it alongside your application's main future, and exit when it
+    /// completes. Only failures occurring after this call complete the future.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap_adapters::prelude::*;
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// let config_path_string = config_path.display().to_string();
+    /// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+    ///
+    /// let runtime = tokio::runtime::Runtime::new()?;
+    /// runtime.block_on(async {
+    ///     let config = ConfigWatcher::<PathTo<JsonOf<serde_json::Value>>>::new(&config_path)?;
+    ///     let fatal = config.fatal();
+    ///
+    ///     // Break the config file; the app would now shut down
+    ///     std::fs::write(&config_path, "{not json")?;
+    ///     let _ = config.reload_now();
+    ///
+    ///     let error = fatal.await;
+    ///     assert_eq!(error.location, config_path_string);
+    ///     anyhow::Ok(())
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn fatal(&self) -> impl std::future::Future<Output = ReloadError> + Send {
+        self.reload_handle.next_error()
+    }
+
+    /// Spawn a task that invokes `callback` with each updated document
+    ///
+    /// The callback is not invoked for the document that is current at the
+    /// time of registration. The task ends once the adapter is dropped, or
+    /// may be cancelled early via the returned [`JoinHandle`].
+    ///
+    /// > *Note*: This requires a tokio runtime to be active
+    ///
+    /// [`JoinHandle`]: tokio::task::JoinHandle
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap_adapters::prelude::*;
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+    ///
+    /// let runtime = tokio::runtime::Runtime::new()?;
+    /// runtime.block_on(async {
+    ///     let config = ConfigWatcher::<PathTo<JsonOf<serde_json::Value>>>::new(&config_path)?;
+    ///
+    ///     // Forward each new config to a channel
+    ///     let (config_tx, mut config_rx) = tokio::sync::mpsc::unbounded_channel();
+    ///     config.on_change(move |config| {
+    ///         let _ = config_tx.send(config.data.0.clone());
+    ///     });
+    ///
+    ///     std::fs::write(&config_path, r#"{"hello":"callback"}"#)?;
+    ///     config.reload_now()?;
+    ///
+    ///     let config = config_rx.recv().await;
+    ///     assert_eq!(config, Some(serde_json::json!({"hello":"callback"})));
+    ///     anyhow::Ok(())
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_change<F>(&self, callback: F) -> tokio::task::JoinHandle<()>
+    where
+        F: Fn(&T) + Send + 'static,
+    {
+        let mut reload_rx = self.reload_rx.clone();
+        reload_rx.mark_unchanged();
+        tokio::spawn(async move {
+            while reload_rx.changed().await.is_ok() {
+                let document = reload_rx.borrow_and_update().document.clone();
+                callback(&document);
+            }
+        })
+    }
+
+    /// Get a receiver of one part of the document, notified only when that part changes
+    ///
+    /// `project` is applied to every published document, and receivers are
+    /// only notified when its result differs from the previous one. This lets
+    /// a component interested in e.g. the log level ignore unrelated edits.
+    /// The projecting task ends with the watcher, or with the first change
+    /// after every receiver is dropped.
+    ///
+    /// > *Note*: This requires a tokio runtime to be active
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap_adapters::prelude::*;
+    ///
+    /// #[derive(Debug, serde::Deserialize)]
+    /// struct Config {
+    ///     level: String,
+    ///     port: u16,
+    /// }
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// std::fs::write(&config_path, r#"{"level":"info","port":80}"#)?;
+    ///
+    /// let runtime = tokio::runtime::Runtime::new()?;
+    /// runtime.block_on(async {
+    ///     let config = ConfigWatcher::<PathTo<JsonOf<Config>>>::new(&config_path)?;
+    ///     let mut level_rx = config.map(|config| config.level.clone());
+    ///     assert_eq!(*level_rx.borrow(), "info");
+    ///
+    ///     // Changing another field doesn't notify the receiver
+    ///     std::fs::write(&config_path, r#"{"level":"info","port":8080}"#)?;
+    ///     config.reload_now()?;
+    ///
+    ///     std::fs::write(&config_path, r#"{"level":"debug","port":8080}"#)?;
+    ///     config.reload_now()?;
+    ///
+    ///     level_rx.changed().await?;
+    ///     assert_eq!(*level_rx.borrow_and_update(), "debug");
+    ///     anyhow::Ok(())
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn map<U, F>(&self, project: F) -> watch::Receiver<U>
+    where
+        U: PartialEq + Send + Sync + 'static,
+        F: Fn(&T) -> U + Send + 'static,
+    {
+        let mut reload_rx = self.reload_rx.clone();
+        let initial = project(&reload_rx.borrow_and_update().document);
+        let (projection_tx, projection_rx) = watch::channel(initial);
+        tokio::spawn(async move {
+            while reload_rx.changed().await.is_ok() && !projection_tx.is_closed() {
+                let projection = project(&reload_rx.borrow_and_update().document);
+                projection_tx.send_if_modified(|current| {
+                    let modified = *current != projection;
+                    if modified {
+                        *current = projection;
+                    }
+                    modified
+                });
+            }
+        });
+        projection_rx
+    }
+}
+
+impl<T> ConfigWatcher<T> {
+    /// Get the lifecycle controlling this watcher's background work
+    #[cfg(any(
+        feature = "periodic",
+        feature = "signal",
+        feature = "sse",
+        feature = "mqtt"
+    ))]
+    pub(crate) fn lifecycle(&self) -> &Lifecycle {
+        &self.lifecycle
+    }
+}
+
+impl<T> ConfigWatcher<PathTo<T>> {
+    /// Get when the current document was loaded, and the file's modification time and length then
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap::Parser;
+    /// use clap_adapters::prelude::*;
+    ///
+    /// #[derive(Debug, Parser)]
+    /// struct Cli {
+    ///     /// Path to a Json config to watch for changes
+    ///     #[clap(long)]
+    ///     config: Reloading<PathTo<JsonOf<serde_json::Value>>>,
+    /// }
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// let config_path_string = config_path.display().to_string();
+    /// std::fs::write(&config_path, r#"{"hello":"world"}"#)?;
+    ///
+    /// let cli = Cli::parse_from(["app", "--config", &config_path_string]);
+    /// let metadata = cli.config.metadata();
+    /// assert_eq!(metadata.len, Some(17));
+    /// println!("config loaded {:?} ago from {}", metadata.age(), config_path.display());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn metadata(&self) -> LoadMetadata {
+        self.reload_rx.borrow().document.metadata
+    }
+}
+
+#[cfg(any(feature = "periodic", feature = "reloading", feature = "signal"))]
+impl<T> ConfigWatcher<T>
+where
+    T: Reload + Send + Sync + 'static,
+{
+    /// Create a watcher of `document` that reloads only when asked to
+    pub(crate) fn from_document(document: T) -> Self {
+        let (reload_tx, reload_rx) = watch::channel(Snapshot::new(document));
+        let validators = Validators::default();
+        let reload_handle = ReloadHandle::new(reload_tx, validators.clone());
+        Self {
+            reload_rx,
+            reload_handle,
+            validators,
+            lifecycle: Lifecycle::new(),
+            #[cfg(any(doc, feature = "reloading"))]
+            file_watcher: None,
+        }
+    }
+}
+
+#[cfg(any(
+    feature = "test-util",
+    feature = "periodic",
+    feature = "sse",
+    feature = "mqtt"
+))]
+impl<T> ConfigWatcher<T>
+where
+    T: Send + Sync + 'static,
+{
+    /// Create a watcher that publishes documents produced by `next` when asked to reload
+    pub(crate) fn from_source<F>(document: T, location: String, next: F) -> Self
+    where
+        F: Fn(&T) -> Result<Option<T>, crate::Error> + Send + Sync + 'static,
+    {
+        let (reload_tx, reload_rx) = watch::channel(Snapshot::new(document));
+        let validators = Validators::default();
+        let reload_handle =
+            ReloadHandle::with_source(reload_tx, validators.clone(), location, next);
+        Self {
+            reload_rx,
+            reload_handle,
+            validators,
+            lifecycle: Lifecycle::new(),
+            #[cfg(any(doc, feature = "reloading"))]
+            file_watcher: None,
+        }
+    }
+
+    /// Create a watcher of `document`, and a function setting the result of its next reload
+    ///
+    /// Each result handed to the function is published (or reported, if it is
+    /// an error) by the next reload, such as [`ConfigWatcher::reload_now`].
+    /// Reloads without a new result publish nothing.
+    #[cfg(any(feature = "test-util", feature = "sse", feature = "mqtt"))]
+    pub(crate) fn from_pending(
+        document: T,
+        location: String,
+    ) -> (
+        Self,
+        impl Fn(Result<T, crate::Error>) + Send + Sync + 'static,
+    ) {
+        let pending: Arc<Mutex<Option<Result<T, crate::Error>>>> = Arc::default();
+        let source = pending.clone();
+        let watcher = Self::from_source(document, location, move |_| {
+            source
+                .lock()
+                .expect("pending document lock poisoned")
+                .take()
+                .transpose()
+        });
+        let set_next = move |result| {
+            *pending.lock().expect("pending document lock poisoned") = Some(result);
+        };
+        (watcher, set_next)
+    }
+}
+
+#[cfg(any(doc, feature = "reloading"))]
+impl<T> ConfigWatcher<PathTo<T>>
+where
+    T: FromPath + Send + Sync + 'static,
+{
+    /// Load the file at `path` and start watching it for changes
+    ///
+    /// Filesystem events are debounced for 100 milliseconds, see
+    /// [`ConfigWatcher::with_debounce`].
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self, crate::Error> {
+        Self::with_debounce(path, Duration::from_millis(100))
+    }
+
+    /// Load the file at `path` and start watching it for changes
+    ///
+    /// The file is reloaded only after no new filesystem events have arrived
+    /// for the `debounce` duration.
+    pub fn with_debounce(
+        path: impl Into<PathBuf>,
+        debounce: Duration,
+    ) -> Result<Self, crate::Error> {
+        Self::spawn(path, debounce, None, changes_contents)
+    }
+
+    /// Load the file at `path` and check it for changes every `poll_interval`
+    ///
+    /// Polling works where the OS doesn't deliver filesystem notifications,
+    /// such as on NFS or SMB mounts. Filesystem events are debounced for 100
+    /// milliseconds, as with [`ConfigWatcher::new`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use std::time::Duration;
+    ///
+    /// use clap_adapters::prelude::*;
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.json");
+    /// std::fs::write(&config_path, r#"{"level":"info"}"#)?;
+    ///
+    /// let config = ConfigWatcher::<PathTo<JsonOf<serde_json::Value>>>::with_poll_interval(
+    ///     &config_path,
+    ///     Duration::from_millis(50),
+    /// )?;
+    /// let mut config_rx = config.receiver();
+    ///
+    /// std::fs::write(&config_path, r#"{"level":"debug"}"#)?;
+    /// let runtime = tokio::runtime::Runtime::new()?;
+    /// runtime.block_on(async {
+    ///     tokio::time::timeout(Duration::from_secs(10), config_rx.changed()).await
+    /// })??;
+    /// assert_eq!(config.get().data.0, serde_json::json!({"level":"debug"}));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_poll_interval(
+        path: impl Into<PathBuf>,
+        poll_interval: Duration,
+    ) -> Result<Self, crate::Error> {
+        Self::spawn(
+            path,
+            Duration::from_millis(100),
+            Some(poll_interval),
+            changes_contents,
+        )
+    }
+
+    /// Load the file at `path` and start watching it with the given backend
+    ///
+    /// Only events for which `is_relevant` returns true trigger a reload.
+    pub(crate) fn spawn(
+        path: impl Into<PathBuf>,
+        debounce: Duration,
+        poll_interval: Option<Duration>,
+        is_relevant: fn(&notify::EventKind) -> bool,
+    ) -> Result<Self, crate::Error> {
+        let path_to = PathTo::<T>::load(path)?;
+        let path = path_to.path.clone();
+        Self::watching(path_to, |on_change| {
+            FileWatcher::spawn(&path, debounce, poll_interval, is_relevant, on_change)
+        })
+    }
+}
+
+#[cfg(any(doc, feature = "reloading"))]
+impl<T> ConfigWatcher<DirOf<T>>
+where
+    T: FromMerged + Send + Sync + 'static,
+{
+    /// Merge the files in the directory at `path` and rebuild the merged
+    /// document whenever any of them changes
+    pub(crate) fn spawn_dir(
+        path: impl Into<PathBuf>,
+        debounce: Duration,
+        poll_interval: Option<Duration>,
+        is_relevant: fn(&notify::EventKind) -> bool,
+    ) -> Result<Self, crate::Error> {
+        let dir = DirOf::<T>::load(path)?;
+        let path = dir.path.clone();
+        Self::watching(dir, |on_change| {
+            FileWatcher::spawn_dir(&path, debounce, poll_interval, is_relevant, on_change)
+        })
+    }
+}
+
+#[cfg(any(doc, feature = "reloading"))]
+impl<T> ConfigWatcher<T>
+where
+    T: Reload + Send + Sync + 'static,
+{
+    /// Watch `document`, reloading it whenever the watch started by `spawn_watch` fires
+    fn watching<W>(document: T, spawn_watch: W) -> Result<Self, crate::Error>
+    where
+        W: FnOnce(Box<dyn Fn() + Send>) -> Result<FileWatcher, crate::Error>,
+    {
+        let mut watcher = Self::from_document(document);
+
+        let notify_handle = watcher.reload_handle.clone();
+        let state_rx = watcher.lifecycle.subscribe();
+        let file_watcher = spawn_watch(Box::new(move || {
+            // Changes made while paused are picked up upon resuming
+            if *state_rx.borrow() != RunState::Running {
+                return;
+            }
+
+            if let Err(error) = notify_handle.reload_now() {
+                notify_handle.report(&error, "Failed to hotreload after notify");
+            }
+        }))?;
+        watcher.file_watcher = Some(Arc::new(file_watcher));
+        Ok(watcher)
+    }
+}
+
+impl<T> Clone for ConfigWatcher<T> {
+    fn clone(&self) -> Self {
+        Self {
+            reload_rx: self.reload_rx.clone(),
+            reload_handle: self.reload_handle.clone(),
+            validators: self.validators.clone(),
+            lifecycle: self.lifecycle.clone(),
+            #[cfg(any(doc, feature = "reloading"))]
+            file_watcher: self.file_watcher.clone(),
+        }
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for ConfigWatcher<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ConfigWatcher")
+            .field(&*self.reload_rx.borrow().document)
+            .finish()
+    }
+}
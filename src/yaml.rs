@@ -1,14 +1,136 @@
-use serde::de::DeserializeOwned;
+use serde::{de::DeserializeOwned, Serialize};
 
-use crate::prelude::FromReader;
+use crate::prelude::{FromMerged, FromReader, ToWriter};
+
+/// The Yaml implementation, `serde_yaml` unless the `yaml-ng` feature selects `serde_yaml_ng`
+#[cfg(not(feature = "yaml-ng"))]
+pub(crate) use serde_yaml as backend;
+#[cfg(feature = "yaml-ng")]
+pub(crate) use serde_yaml_ng as backend;
 
 /// An adapter for deserializing a Yaml document from a buffered reader
-#[derive(Debug, Clone)]
+///
+/// Yaml is parsed with `serde_yaml` by default. As `serde_yaml` is no longer
+/// maintained, enabling the `yaml-ng` feature switches to its maintained
+/// fork `serde_yaml_ng` instead, without changes to this type or to its
+/// [`YamlError`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
 pub struct YamlOf<T>(pub T);
+
+impl<T> std::ops::Deref for YamlOf<T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> std::ops::DerefMut for YamlOf<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T> AsRef<T> for YamlOf<T> {
+    fn as_ref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> YamlOf<T> {
+    /// Returns a reference to the inner YAML datatype
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use clap_adapters::prelude::*;
+    ///
+    /// let mut document = YamlOf(vec![1, 2]);
+    /// document.data_mut().push(3);
+    /// assert_eq!(document.data(), &[1, 2, 3]);
+    /// assert_eq!(document.into_inner(), vec![1, 2, 3]);
+    /// ```
+    pub fn data(&self) -> &T {
+        &self.0
+    }
+
+    /// Returns a mutable reference to the inner YAML datatype
+    pub fn data_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+
+    /// Returns the inner YAML datatype
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+/// An error reading or writing a Yaml document
+///
+/// This wraps the error of whichever Yaml implementation is selected, so that
+/// enabling the `yaml-ng` feature anywhere in the dependency graph doesn't
+/// change the error type seen by other crates.
+#[derive(Debug)]
+pub struct YamlError(backend::Error);
+
+impl YamlError {
+    /// Returns the one-based line and column where the error occurred, if known
+    pub fn line_column(&self) -> Option<(usize, usize)> {
+        let location = self.0.location()?;
+        Some((location.line(), location.column()))
+    }
+
+    /// Returns the byte offset where the error occurred, if known
+    pub fn offset(&self) -> Option<usize> {
+        Some(self.0.location()?.index())
+    }
+}
+
+impl std::fmt::Display for YamlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for YamlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.0.source()
+    }
+}
+
+/// Parse a Yaml document from `reader`
+pub(crate) fn from_reader<T: DeserializeOwned>(
+    reader: &mut (impl std::io::BufRead + ?Sized),
+) -> Result<T, YamlError> {
+    backend::from_reader(reader).map_err(YamlError)
+}
+
 impl<T: DeserializeOwned> FromReader for YamlOf<T> {
-    type Error = serde_yaml::Error;
+    type Error = YamlError;
     fn from_reader(reader: &mut impl std::io::BufRead) -> Result<Self, Self::Error> {
-        let yaml = serde_yaml::from_reader(reader)?;
+        let yaml = from_reader(reader)?;
+        Ok(YamlOf(yaml))
+    }
+}
+
+impl<T: Serialize> ToWriter for YamlOf<T> {
+    type Error = YamlError;
+    fn to_writer(&self, writer: &mut impl std::io::Write) -> Result<(), Self::Error> {
+        backend::to_writer(writer, &self.0).map_err(YamlError)
+    }
+}
+
+impl<T: DeserializeOwned> FromMerged for YamlOf<T> {
+    const EXTENSIONS: &'static [&'static str] = &["yaml", "yml"];
+    type Error = YamlError;
+    fn value_from_reader(
+        reader: &mut impl std::io::BufRead,
+    ) -> Result<serde_json::Value, Self::Error> {
+        from_reader(reader)
+    }
+    fn from_merged(value: serde_json::Value) -> Result<Self, Self::Error> {
+        let value = backend::to_value(value).map_err(YamlError)?;
+        let yaml = backend::from_value::<T>(value).map_err(YamlError)?;
         Ok(YamlOf(yaml))
     }
 }
@@ -51,6 +173,38 @@ impl<T> crate::fs::PathTo<YamlOf<T>> {
         &self.data.0
     }
 
+    /// Returns a mutable reference to the inner YAML datatype
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # fn main() -> anyhow::Result<()> {
+    /// use clap::Parser;
+    /// use clap_adapters::prelude::*;
+    ///
+    /// #[derive(Debug, Parser)]
+    /// struct Cli {
+    ///     #[clap(long)]
+    ///     config: PathTo<YamlOf<serde_json::Value>>,
+    /// }
+    ///
+    /// // Create a config file in a temporary directory
+    /// let config_dir = tempfile::tempdir()?;
+    /// let config_path = config_dir.path().join("config.yaml");
+    /// let config_path_string = config_path.display().to_string();
+    /// std::fs::write(&config_path, "hello: world")?;
+    ///
+    /// // Parse our CLI, then override a value
+    /// let mut cli = Cli::parse_from(["app", "--config", &config_path_string]);
+    /// cli.config.data_mut()["hello"] = serde_json::json!("there");
+    /// assert_eq!(cli.config.data(), &serde_json::json!({"hello":"there"}));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn data_mut(&mut self) -> &mut T {
+        &mut self.data.0
+    }
+
     /// Returns the owned inner datatype parsed from Yaml
     ///
     /// # Example